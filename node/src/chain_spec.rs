@@ -3,6 +3,7 @@ use dark_runtime::{
 	AccountId, AuraConfig, BalancesConfig,
 	GenesisConfig, GrandpaConfig, UtilsConfig,
 	SudoConfig, StorefrontsConfig, SystemConfig,
+	ProductsConfig, ProductHistoryConfig, OrdersConfig, OrderHistoryConfig,
 	WASM_BINARY, Signature, constants::currency::DARKS,
 };
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
@@ -292,6 +293,10 @@ fn testnet_genesis(
 		pallet_storefronts: Some(StorefrontsConfig {
 			endowed_account: root_key,
 		}),
+		pallet_products: Some(ProductsConfig {}),
+		pallet_product_history: Some(ProductHistoryConfig {}),
+		pallet_orders: Some(OrdersConfig {}),
+		pallet_order_history: Some(OrderHistoryConfig {}),
 	}
 }
 
@@ -1,2 +1,3 @@
 pub mod chain_spec;
 pub mod service;
+pub mod rpc;
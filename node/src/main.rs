@@ -5,6 +5,7 @@ mod chain_spec;
 #[macro_use]
 mod service;
 mod cli;
+mod rpc;
 mod command;
 
 fn main() -> sc_cli::Result<()> {
@@ -0,0 +1,31 @@
+//! A collection of node-specific RPC methods.
+
+use std::sync::Arc;
+
+use dark_runtime::{opaque::Block, AccountId};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+
+/// A type of the RPC extension with Darkdot methods.
+pub type IoHandler = jsonrpc_core::IoHandler<sc_rpc::Metadata>;
+
+/// Instantiate all RPC extensions of a full node.
+pub fn create_full<C>(client: Arc<C>) -> IoHandler
+where
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: pallet_storefronts_rpc::StorefrontsRuntimeApi<Block, AccountId>,
+	C::Api: pallet_products_rpc::ProductsRuntimeApi<Block>,
+	C::Api: pallet_orders_rpc::OrdersRuntimeApi<Block, AccountId>,
+{
+	use pallet_storefronts_rpc::{Storefronts, StorefrontsApi};
+	use pallet_products_rpc::{Products, ProductsApi};
+	use pallet_orders_rpc::{Orders, OrdersApi};
+
+	let mut io = IoHandler::default();
+
+	io.extend_with(StorefrontsApi::to_delegate(Storefronts::new(client.clone())));
+	io.extend_with(ProductsApi::to_delegate(Products::new(client.clone())));
+	io.extend_with(OrdersApi::to_delegate(Orders::new(client)));
+
+	io
+}
@@ -0,0 +1,333 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Decode, Encode};
+use frame_support::{
+    decl_error, decl_event, decl_module, decl_storage, ensure,
+    dispatch::{DispatchError, DispatchResult}, traits::Get,
+};
+use sp_runtime::RuntimeDebug;
+use sp_std::prelude::*;
+use frame_system::{self as system, ensure_root, ensure_signed};
+
+use pallet_permissions::StorefrontPermission;
+use pallet_storefronts::Module as Storefronts;
+use pallet_utils::{Module as Utils, StorefrontId, Content, vec_remove_on};
+
+pub type CategoryId = u64;
+
+#[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
+pub struct Category {
+    pub id: CategoryId,
+    pub parent_id: Option<CategoryId>,
+    /// Points to the off-chain content with a name and a description of this category.
+    pub content: Content,
+    /// A hidden category is kept for already tagged products, but new products cannot be tagged with it.
+    pub hidden: bool,
+}
+
+#[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
+pub struct CategoryUpdate {
+    pub parent_id: Option<Option<CategoryId>>,
+    pub content: Option<Content>,
+    pub hidden: Option<bool>,
+}
+
+/// The pallet's configuration trait.
+pub trait Trait: system::Trait
+    + pallet_utils::Trait
+    + pallet_storefronts::Trait
+{
+    /// The overarching event type.
+    type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+
+    /// The maximum number of categories a single product can be tagged with.
+    type MaxCategoriesPerProduct: Get<u16>;
+
+    /// The maximum number of categories a storefront can restrict its products to.
+    type MaxCategoriesPerStorefront: Get<u16>;
+}
+
+// This pallet's storage items.
+decl_storage! {
+    trait Store for Module<T: Trait> as CategoriesModule {
+        pub NextCategoryId get(fn next_category_id): CategoryId = 1;
+
+        pub CategoryById get(fn category_by_id):
+            map hasher(twox_64_concat) CategoryId => Option<Category>;
+
+        pub RootCategoryIds get(fn root_category_ids): Vec<CategoryId>;
+
+        pub CategoryIdsByParentId get(fn category_ids_by_parent_id):
+            map hasher(twox_64_concat) CategoryId => Vec<CategoryId>;
+
+        /// Categories in which a storefront sells its products, including their subcategories.
+        /// A storefront without categories can sell in any category.
+        pub CategoryIdsByStorefrontId get(fn category_ids_by_storefront_id):
+            map hasher(twox_64_concat) StorefrontId => Vec<CategoryId>;
+    }
+}
+
+decl_event!(
+    pub enum Event<T> where
+        <T as system::Trait>::AccountId,
+    {
+        CategoryCreated(CategoryId),
+        CategoryUpdated(CategoryId),
+        StorefrontCategoriesUpdated(AccountId, StorefrontId),
+    }
+);
+
+decl_error! {
+    pub enum Error for Module<T: Trait> {
+        /// Category was not found by id.
+        CategoryNotFound,
+        /// Nothing to update in category.
+        NoUpdatesForCategory,
+        /// A category cannot be moved under itself or one of its subcategories.
+        CategoryCannotBeItsOwnAncestor,
+        /// Products cannot be tagged with a hidden category.
+        CategoryIsHidden,
+        /// The same category is provided more than once.
+        DuplicateCategories,
+        /// Too many categories are provided for a product.
+        TooManyProductCategories,
+        /// Too many categories are provided for a storefront.
+        TooManyStorefrontCategories,
+        /// A storefront does not sell in this category.
+        CategoryNotAllowedInStorefront,
+        /// User has no permission to change categories of this storefront.
+        NoPermissionToUpdateStorefrontCategories,
+    }
+}
+
+decl_module! {
+  pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+
+    const MaxCategoriesPerProduct: u16 = T::MaxCategoriesPerProduct::get();
+
+    const MaxCategoriesPerStorefront: u16 = T::MaxCategoriesPerStorefront::get();
+
+    // Initializing errors
+    type Error = Error<T>;
+
+    // Initializing events
+    fn deposit_event() = default;
+
+    /// Add a new category to the tree. Only root (governance) can call this dispatch.
+    #[weight = 10_000 + T::DbWeight::get().reads_writes(2, 3)]
+    pub fn create_category(origin, parent_id_opt: Option<CategoryId>, content: Content) -> DispatchResult {
+      ensure_root(origin)?;
+
+      Utils::<T>::ensure_content_is_some(&content)?;
+      Utils::<T>::is_valid_content(content.clone())?;
+
+      if let Some(parent_id) = parent_id_opt {
+        Self::ensure_category_exists(parent_id)?;
+      }
+
+      let category_id = Self::next_category_id();
+      let new_category = Category {
+        id: category_id,
+        parent_id: parent_id_opt,
+        content,
+        hidden: false,
+      };
+
+      CategoryById::insert(category_id, new_category);
+      Self::add_to_parent(parent_id_opt, category_id);
+      NextCategoryId::mutate(|n| { *n += 1; });
+
+      Self::deposit_event(RawEvent::CategoryCreated(category_id));
+      Ok(())
+    }
+
+    /// Move, rename or hide a category. Only root (governance) can call this dispatch.
+    #[weight = 10_000 + T::DbWeight::get().reads_writes(3, 3)]
+    pub fn update_category(origin, category_id: CategoryId, update: CategoryUpdate) -> DispatchResult {
+      ensure_root(origin)?;
+
+      let has_updates =
+        update.parent_id.is_some() ||
+        update.content.is_some() ||
+        update.hidden.is_some();
+
+      ensure!(has_updates, Error::<T>::NoUpdatesForCategory);
+
+      let mut category = Self::require_category(category_id)?;
+      let mut is_update_applied = false;
+
+      if let Some(content) = update.content {
+        if content != category.content {
+          Utils::<T>::ensure_content_is_some(&content)?;
+          Utils::<T>::is_valid_content(content.clone())?;
+
+          category.content = content;
+          is_update_applied = true;
+        }
+      }
+
+      if let Some(hidden) = update.hidden {
+        if hidden != category.hidden {
+          category.hidden = hidden;
+          is_update_applied = true;
+        }
+      }
+
+      if let Some(parent_id_opt) = update.parent_id {
+        if parent_id_opt != category.parent_id {
+          if let Some(parent_id) = parent_id_opt {
+            Self::ensure_category_exists(parent_id)?;
+            ensure!(
+              !Self::is_same_or_subcategory(parent_id, category_id),
+              Error::<T>::CategoryCannotBeItsOwnAncestor
+            );
+          }
+
+          Self::remove_from_parent(category.parent_id, category_id);
+          Self::add_to_parent(parent_id_opt, category_id);
+
+          category.parent_id = parent_id_opt;
+          is_update_applied = true;
+        }
+      }
+
+      if is_update_applied {
+        CategoryById::insert(category_id, category);
+        Self::deposit_event(RawEvent::CategoryUpdated(category_id));
+      }
+      Ok(())
+    }
+
+    /// Restrict products of a storefront to the given categories and their subcategories.
+    /// An empty list allows products of any category.
+    /// Only the storefront owner or a user with `UpdateStorefront` permission can call this dispatch.
+    #[weight = 10_000 + T::DbWeight::get().reads_writes(
+      2 + category_ids.len() as u64,
+      1
+    )]
+    pub fn set_storefront_categories(
+      origin,
+      storefront_id: StorefrontId,
+      category_ids: Vec<CategoryId>
+    ) -> DispatchResult {
+      let who = ensure_signed(origin)?;
+
+      let storefront = Storefronts::<T>::require_storefront(storefront_id)?;
+      storefront.ensure_not_archived()?;
+
+      Storefronts::<T>::ensure_account_has_storefront_permission(
+        who.clone(),
+        &storefront,
+        StorefrontPermission::UpdateStorefront,
+        Error::<T>::NoPermissionToUpdateStorefrontCategories.into()
+      )?;
+
+      ensure!(
+        category_ids.len() <= T::MaxCategoriesPerStorefront::get() as usize,
+        Error::<T>::TooManyStorefrontCategories
+      );
+      Self::ensure_no_duplicates(&category_ids)?;
+
+      for category_id in category_ids.iter() {
+        Self::ensure_category_exists(*category_id)?;
+      }
+
+      CategoryIdsByStorefrontId::insert(storefront_id, category_ids);
+
+      Self::deposit_event(RawEvent::StorefrontCategoriesUpdated(who, storefront_id));
+      Ok(())
+    }
+  }
+}
+
+impl<T: Trait> Module<T> {
+
+    /// Check that there is a `Category` with such `category_id` in the storage
+    /// or return `CategoryNotFound` error.
+    pub fn ensure_category_exists(category_id: CategoryId) -> DispatchResult {
+        ensure!(CategoryById::contains_key(category_id), Error::<T>::CategoryNotFound);
+        Ok(())
+    }
+
+    /// Get `Category` by id from the storage or return `CategoryNotFound` error.
+    pub fn require_category(category_id: CategoryId) -> Result<Category, DispatchError> {
+        Ok(Self::category_by_id(category_id).ok_or(Error::<T>::CategoryNotFound)?)
+    }
+
+    /// Check whether `category_id` is `ancestor_id` itself or one of its nested subcategories.
+    pub fn is_same_or_subcategory(category_id: CategoryId, ancestor_id: CategoryId) -> bool {
+        let mut current_id_opt = Some(category_id);
+
+        while let Some(current_id) = current_id_opt {
+            if current_id == ancestor_id {
+                return true;
+            }
+            current_id_opt = Self::category_by_id(current_id).and_then(|category| category.parent_id);
+        }
+
+        false
+    }
+
+    /// Check that a product in a given storefront can be tagged with these categories:
+    /// there are not too many of them, all of them exist, are not hidden
+    /// and are allowed by the storefront.
+    pub fn ensure_product_categories_are_valid(
+        storefront_id: StorefrontId,
+        category_ids: &[CategoryId]
+    ) -> DispatchResult {
+        ensure!(
+            category_ids.len() <= T::MaxCategoriesPerProduct::get() as usize,
+            Error::<T>::TooManyProductCategories
+        );
+        Self::ensure_no_duplicates(category_ids)?;
+
+        for category_id in category_ids.iter() {
+            let category = Self::require_category(*category_id)?;
+            ensure!(!category.hidden, Error::<T>::CategoryIsHidden);
+        }
+
+        Self::ensure_categories_allowed_in_storefront(storefront_id, category_ids)
+    }
+
+    /// Check that every category is one of the storefront categories or their subcategory.
+    pub fn ensure_categories_allowed_in_storefront(
+        storefront_id: StorefrontId,
+        category_ids: &[CategoryId]
+    ) -> DispatchResult {
+        let storefront_category_ids = Self::category_ids_by_storefront_id(storefront_id);
+        if storefront_category_ids.is_empty() {
+            return Ok(());
+        }
+
+        for category_id in category_ids.iter() {
+            let is_allowed = storefront_category_ids.iter()
+                .any(|allowed_id| Self::is_same_or_subcategory(*category_id, *allowed_id));
+
+            ensure!(is_allowed, Error::<T>::CategoryNotAllowedInStorefront);
+        }
+
+        Ok(())
+    }
+
+    fn ensure_no_duplicates(category_ids: &[CategoryId]) -> DispatchResult {
+        let has_duplicates = category_ids.iter().enumerate()
+            .any(|(i, id)| category_ids[..i].contains(id));
+
+        ensure!(!has_duplicates, Error::<T>::DuplicateCategories);
+        Ok(())
+    }
+
+    fn add_to_parent(parent_id_opt: Option<CategoryId>, category_id: CategoryId) {
+        match parent_id_opt {
+            Some(parent_id) => CategoryIdsByParentId::mutate(parent_id, |ids| ids.push(category_id)),
+            None => RootCategoryIds::mutate(|ids| ids.push(category_id)),
+        }
+    }
+
+    fn remove_from_parent(parent_id_opt: Option<CategoryId>, category_id: CategoryId) {
+        match parent_id_opt {
+            Some(parent_id) => CategoryIdsByParentId::mutate(parent_id, |ids| vec_remove_on(ids, category_id)),
+            None => RootCategoryIds::mutate(|ids| vec_remove_on(ids, category_id)),
+        }
+    }
+}
@@ -4,34 +4,45 @@
 mod tests {
     use frame_support::{
         assert_ok, assert_noop,
-        impl_outer_origin, parameter_types,
-        weights::Weight,
+        impl_outer_origin, impl_outer_dispatch, parameter_types,
+        weights::{Weight, GetDispatchInfo},
         dispatch::DispatchResult,
         storage::StorageMap,
+        traits::{Currency, Filter, Get, OnInitialize},
     };
     use sp_core::H256;
     use sp_io::TestExternalities;
     use sp_std::iter::FromIterator;
+    use std::cell::RefCell;
     use sp_runtime::{
-        traits::{BlakeTwo256, IdentityLookup},
+        traits::{BlakeTwo256, IdentityLookup, BadOrigin, Zero},
         testing::Header,
-        Perbill,
+        Perbill, Percent, RuntimeDebug,
     };
     use frame_system::{self as system};
 
+    use pallet_categories::{CategoryId, CategoryUpdate, Error as CategoriesError};
+    use pallet_moderation::{EntityId, EntityStatus, ReportStatus, ReportCategory, GOVERNANCE_SCOPE, Error as ModerationError};
+    use pallet_orders::{
+        OrderId, OrderState, OrderUpdate, EncryptedMessage, StorefrontOrderSettingsUpdate,
+        ENCRYPTED_MESSAGE_TAG_LEN, Error as OrdersError,
+    };
     use pallet_permissions::{
         StorefrontPermission,
         StorefrontPermission as SP,
         StorefrontPermissionSet,
         StorefrontPermissions,
     };
-    use pallet_products::{ProductId, Product, ProductUpdate, ProductExtension, Comment, Error as ProductsError};
+    use pallet_products::{ProductId, Product, ProductUpdate, ProductExtension, ProductPrice, PriceCurrency, StockUpdate, Comment, Error as ProductsError};
     use pallet_profiles::{ProfileUpdate, Error as ProfilesError};
     use pallet_profile_follows::Error as ProfileFollowsError;
     use pallet_reactions::{ReactionId, ReactionKind, ProductReactionScores, Error as ReactionsError};
+    use pallet_reviews::{ReviewId, Error as ReviewsError};
+    use pallet_roles::Error as RolesError;
     use pallet_scores::ScoringAction;
     use pallet_storefronts::{StorefrontById, StorefrontUpdate, Error as StorefrontsError};
     use pallet_storefront_follows::Error as StorefrontFollowsError;
+    use pallet_storefront_multi_ownership::{ChangeId, Error as StorefrontMultiOwnershipError};
     use pallet_storefront_ownership::Error as StorefrontOwnershipError;
     use pallet_utils::{StorefrontId, Error as UtilsError, User, Content};
 
@@ -39,7 +50,15 @@ mod tests {
         pub enum Origin for TestRuntime {}
     }
 
-    #[derive(Clone, Eq, PartialEq)]
+    impl_outer_dispatch! {
+        pub enum Call for TestRuntime where origin: Origin {
+            frame_system::System,
+            pallet_balances::Balances,
+            pallet_storefronts::Storefronts,
+        }
+    }
+
+    #[derive(Clone, Eq, PartialEq, RuntimeDebug)]
     pub struct TestRuntime;
 
     parameter_types! {
@@ -52,7 +71,7 @@ mod tests {
     impl system::Trait for TestRuntime {
         type BaseCallFilter = ();
         type Origin = Origin;
-        type Call = ();
+        type Call = Call;
         type Index = u64;
         type BlockNumber = u64;
         type Hash = H256;
@@ -86,8 +105,20 @@ mod tests {
         type MinimumPeriod = MinimumPeriod;
     }
 
-    parameter_types! {
-        pub const ExistentialDeposit: u64 = 1;
+    thread_local! {
+        static EXISTENTIAL_DEPOSIT: RefCell<u64> = RefCell::new(1);
+    }
+
+    pub struct ExistentialDeposit;
+
+    impl Get<u64> for ExistentialDeposit {
+        fn get() -> u64 {
+            EXISTENTIAL_DEPOSIT.with(|v| *v.borrow())
+        }
+    }
+
+    fn set_existential_deposit(amount: u64) {
+        EXISTENTIAL_DEPOSIT.with(|v| *v.borrow_mut() = amount);
     }
 
     impl pallet_balances::Trait for TestRuntime {
@@ -103,11 +134,30 @@ mod tests {
       pub const MaxHandleLen: u32 = 50;
     }
 
+    thread_local! {
+        static STORAGE_DEPOSIT_BASE: RefCell<u64> = RefCell::new(0);
+    }
+
+    /// Storage deposits are free by default, so that balances of other tests are not affected.
+    pub struct StorageDepositBase;
+
+    impl Get<u64> for StorageDepositBase {
+        fn get() -> u64 {
+            STORAGE_DEPOSIT_BASE.with(|v| *v.borrow())
+        }
+    }
+
+    fn set_storage_deposit_base(amount: u64) {
+        STORAGE_DEPOSIT_BASE.with(|v| *v.borrow_mut() = amount);
+    }
+
     impl pallet_utils::Trait for TestRuntime {
         type Event = ();
         type Currency = Balances;
         type MinHandleLen = MinHandleLen;
         type MaxHandleLen = MaxHandleLen;
+        type StorageDepositBase = StorageDepositBase;
+        type StorageDepositPerByte = ();
     }
 
     parameter_types! {
@@ -163,6 +213,13 @@ mod tests {
             SP::UpdateEntityStatus,
 
             SP::UpdateStorefrontSettings,
+
+            SP::AcceptOrders,
+            SP::ShipOrders,
+            SP::RefundOrders,
+            SP::ViewOrderMessages,
+            SP::OpenDisputes,
+            SP::ResolveDisputes,
         ].into_iter())),
       };
     }
@@ -171,6 +228,60 @@ mod tests {
         type DefaultStorefrontPermissions = DefaultStorefrontPermissions;
     }
 
+    parameter_types! {
+        pub const DefaultAutoblockThreshold: u16 = 20;
+        pub const ReportedOrdersAutoblockThreshold: u32 = 2;
+    }
+
+    impl pallet_moderation::Trait for TestRuntime {
+        type Event = ();
+        type DefaultAutoblockThreshold = DefaultAutoblockThreshold;
+        type ReportedOrdersAutoblockThreshold = ReportedOrdersAutoblockThreshold;
+    }
+
+    parameter_types! {
+        pub const MaxOrderItems: u16 = 50;
+        pub const MaxOrderMessageLength: u32 = 2 * 1024;
+        pub const MaxDisputeEvidence: u16 = 20;
+        pub const AcceptOrderTimeout: u64 = 100;
+        pub const ConfirmOrderTimeout: u64 = 100;
+        pub const MaxExpiredOrdersPerBlock: u32 = 3;
+        pub const MarketplaceFee: Perbill = Perbill::from_percent(2);
+    }
+
+    impl pallet_orders::Trait for TestRuntime {
+        type Event = ();
+        type AfterOrderUpdated = (OrderHistory, Scores);
+        type MaxOrderItems = MaxOrderItems;
+        type MaxOrderMessageLength = MaxOrderMessageLength;
+        type MaxDisputeEvidence = MaxDisputeEvidence;
+        type AcceptOrderTimeout = AcceptOrderTimeout;
+        type ConfirmOrderTimeout = ConfirmOrderTimeout;
+        type MaxExpiredOrdersPerBlock = MaxExpiredOrdersPerBlock;
+        type MarketplaceFee = MarketplaceFee;
+    }
+
+    parameter_types! {}
+
+    impl pallet_order_history::Trait for TestRuntime {}
+
+    parameter_types! {}
+
+    impl pallet_reviews::Trait for TestRuntime {
+        type Event = ();
+    }
+
+    parameter_types! {
+        pub const MaxCategoriesPerProduct: u16 = 2;
+        pub const MaxCategoriesPerStorefront: u16 = 2;
+    }
+
+    impl pallet_categories::Trait for TestRuntime {
+        type Event = ();
+        type MaxCategoriesPerProduct = MaxCategoriesPerProduct;
+        type MaxCategoriesPerStorefront = MaxCategoriesPerStorefront;
+    }
+
     parameter_types! {
         pub const MaxCommentDepth: u32 = 10;
     }
@@ -180,6 +291,8 @@ mod tests {
         type MaxCommentDepth = MaxCommentDepth;
         type ProductScores = Scores;
         type AfterProductUpdated = ProductHistory;
+        type BeforeProductDeleted = (Orders, Scores, Reactions, Reviews);
+        type IsProductBlocked = Moderation;
     }
 
     parameter_types! {}
@@ -235,6 +348,10 @@ mod tests {
         pub const ShareCommentActionWeight: i16 = 5;
         pub const UpvoteCommentActionWeight: i16 = 4;
         pub const DownvoteCommentActionWeight: i16 = -2;
+
+        pub const CompleteOrderActionWeight: i16 = 10;
+        pub const RefuseOrderActionWeight: i16 = -2;
+        pub const LoseDisputeActionWeight: i16 = -20;
     }
 
     impl pallet_scores::Trait for TestRuntime {
@@ -251,6 +368,10 @@ mod tests {
         type ShareCommentActionWeight = ShareCommentActionWeight;
         type UpvoteCommentActionWeight = UpvoteCommentActionWeight;
         type DownvoteCommentActionWeight = DownvoteCommentActionWeight;
+
+        type CompleteOrderActionWeight = CompleteOrderActionWeight;
+        type RefuseOrderActionWeight = RefuseOrderActionWeight;
+        type LoseDisputeActionWeight = LoseDisputeActionWeight;
     }
 
     parameter_types! {}
@@ -267,6 +388,34 @@ mod tests {
         type Event = ();
     }
 
+    parameter_types! {
+        pub const MinStorefrontOwners: u16 = 1;
+        pub const MaxStorefrontOwners: u16 = 100;
+        pub const MaxChangeNotesLength: u16 = 1024;
+        pub const BlocksToLive: u64 = 100;
+        pub const DeleteExpiredChangesPeriod: u64 = 10;
+    }
+
+    /// Multi-owned storefronts can only dispatch calls of the storefronts pallet.
+    pub struct StorefrontOwnersCallFilter;
+
+    impl Filter<Call> for StorefrontOwnersCallFilter {
+        fn filter(c: &Call) -> bool {
+            matches!(c, Call::Storefronts(..))
+        }
+    }
+
+    impl pallet_storefront_multi_ownership::Trait for TestRuntime {
+        type Event = ();
+        type Call = Call;
+        type CallFilter = StorefrontOwnersCallFilter;
+        type MinStorefrontOwners = MinStorefrontOwners;
+        type MaxStorefrontOwners = MaxStorefrontOwners;
+        type MaxChangeNotesLength = MaxChangeNotesLength;
+        type BlocksToLive = BlocksToLive;
+        type DeleteExpiredChangesPeriod = DeleteExpiredChangesPeriod;
+    }
+
     parameter_types! {}
 
     impl pallet_storefronts::Trait for TestRuntime {
@@ -275,7 +424,10 @@ mod tests {
         type StorefrontFollows = StorefrontFollows;
         type BeforeStorefrontCreated = StorefrontFollows;
         type AfterStorefrontUpdated = StorefrontHistory;
-        type StorefrontCreationFee = ();
+        type BeforeStorefrontDeleted = (Orders, Products, StorefrontFollows, Roles);
+        type IsAccountBlocked = Moderation;
+        type IsContentBlocked = Moderation;
+        type IsStorefrontBlocked = Moderation;
     }
 
     parameter_types! {}
@@ -284,17 +436,25 @@ mod tests {
 
     type System = system::Module<TestRuntime>;
     type Balances = pallet_balances::Module<TestRuntime>;
+    type Utils = pallet_utils::Module<TestRuntime>;
 
+    type Categories = pallet_categories::Module<TestRuntime>;
+
+    type Moderation = pallet_moderation::Module<TestRuntime>;
+    type Orders = pallet_orders::Module<TestRuntime>;
+    type OrderHistory = pallet_order_history::Module<TestRuntime>;
     type Products = pallet_products::Module<TestRuntime>;
     type ProductHistory = pallet_product_history::Module<TestRuntime>;
     type ProfileFollows = pallet_profile_follows::Module<TestRuntime>;
     type Profiles = pallet_profiles::Module<TestRuntime>;
     type ProfileHistory = pallet_profile_history::Module<TestRuntime>;
     type Reactions = pallet_reactions::Module<TestRuntime>;
+    type Reviews = pallet_reviews::Module<TestRuntime>;
     type Roles = pallet_roles::Module<TestRuntime>;
     type Scores = pallet_scores::Module<TestRuntime>;
     type StorefrontFollows = pallet_storefront_follows::Module<TestRuntime>;
     type StorefrontHistory = pallet_storefront_history::Module<TestRuntime>;
+    type StorefrontMultiOwnership = pallet_storefront_multi_ownership::Module<TestRuntime>;
     type StorefrontOwnership = pallet_storefront_ownership::Module<TestRuntime>;
     type Storefronts = pallet_storefronts::Module<TestRuntime>;
 
@@ -415,6 +575,31 @@ mod tests {
             ext
         }
 
+        /// Custom ext configuration with a dispute opened by ACCOUNT2 on accepted OrderId 1
+        /// of ProductId 1 priced 100, with 10% buyer and 20% seller escrows
+        /// and ACCOUNT3 added as a dispute arbiter (includes StorefrontId 1)
+        pub fn build_with_disputed_order() -> TestExternalities {
+            let storage = system::GenesisConfig::default()
+                .build_storage::<TestRuntime>()
+                .unwrap();
+
+            let mut ext = TestExternalities::from(storage);
+            ext.execute_with(|| {
+                System::set_block_number(1);
+                let _ = Balances::deposit_creating(&ACCOUNT1, 100);
+
+                assert_ok!(_create_default_storefront());
+                assert_ok!(_set_default_order_escrows());
+                assert_ok!(_create_priced_product(None, None, self::product_price(100)));
+                assert_ok!(_create_default_order(POST1));
+                assert_ok!(_update_order_state(None, 1, OrderState::Accepted));
+                assert_ok!(_open_dispute(None, 1));
+                assert_ok!(_add_dispute_arbiter(ACCOUNT3));
+            });
+
+            ext
+        }
+
         /// Custom ext configuration with storefront follow without Storefront
         pub fn build_with_storefront_follow_no_storefront() -> TestExternalities {
             let storage = system::GenesisConfig::default()
@@ -454,6 +639,8 @@ mod tests {
     const REACTION2: ReactionId = 2;
     const _REACTION3: ReactionId = 3;
 
+    const CHANGE1: ChangeId = 1;
+
     fn storefront_handle() -> Vec<u8> {
         b"storefront_handle".to_vec()
     }
@@ -474,6 +661,7 @@ mod tests {
             handle,
             content,
             hidden,
+            private: None,
             permissions
         }
     }
@@ -486,11 +674,20 @@ mod tests {
         storefront_id: Option<StorefrontId>,
         content: Option<Content>,
         hidden: Option<bool>
-    ) -> ProductUpdate {
+    ) -> ProductUpdate<u64> {
         ProductUpdate {
             storefront_id,
             content,
             hidden,
+            price: None,
+            categories: None,
+        }
+    }
+
+    fn product_price(amount: u64) -> ProductPrice<u64> {
+        ProductPrice {
+            amount,
+            currency: PriceCurrency::Native,
         }
     }
 
@@ -629,13 +826,30 @@ mod tests {
             storefront_id_opt.unwrap_or(Some(SPACE1)),
             extension.unwrap_or_else(self::extension_regular_product),
             content.unwrap_or_else(self::product_content_ipfs),
+            None,
+            Vec::new(),
+        )
+    }
+
+    fn _create_priced_product(
+        origin: Option<Origin>,
+        extension: Option<ProductExtension>,
+        price: ProductPrice<u64>
+    ) -> DispatchResult {
+        Products::create_product(
+            origin.unwrap_or_else(|| Origin::signed(ACCOUNT1)),
+            Some(SPACE1),
+            extension.unwrap_or_else(self::extension_regular_product),
+            self::product_content_ipfs(),
+            Some(price),
+            Vec::new(),
         )
     }
 
     fn _update_product(
         origin: Option<Origin>,
         product_id: Option<ProductId>,
-        update: Option<ProductUpdate>,
+        update: Option<ProductUpdate<u64>>,
     ) -> DispatchResult {
         Products::update_product(
             origin.unwrap_or_else(|| Origin::signed(ACCOUNT1)),
@@ -644,6 +858,14 @@ mod tests {
         )
     }
 
+    fn _delete_product(origin: Option<Origin>, product_id: Option<ProductId>) -> DispatchResult {
+        Products::delete_product(
+            origin.unwrap_or_else(|| Origin::signed(ACCOUNT1)),
+            product_id.unwrap_or(POST1),
+            10,
+        )
+    }
+
     fn _create_default_comment() -> DispatchResult {
         _create_comment(None, None, None, None)
     }
@@ -668,7 +890,7 @@ mod tests {
     fn _update_comment(
         origin: Option<Origin>,
         product_id: Option<ProductId>,
-        update: Option<ProductUpdate>
+        update: Option<ProductUpdate<u64>>
     ) -> DispatchResult {
         _update_product(
             origin,
@@ -861,6 +1083,110 @@ mod tests {
     }
     /* ---------------------------------------------------------------------------------------------- */
 
+    fn _block_account_in_default_storefront(account: AccountId) -> DispatchResult {
+        _update_entity_status(None, EntityId::Account(account), None, Some(EntityStatus::Blocked))
+    }
+
+    fn _update_entity_status(
+        origin: Option<Origin>,
+        entity: EntityId<AccountId>,
+        scope: Option<StorefrontId>,
+        status: Option<EntityStatus>
+    ) -> DispatchResult {
+        Moderation::update_entity_status(
+            origin.unwrap_or_else(|| Origin::signed(ACCOUNT1)),
+            entity,
+            scope.unwrap_or(SPACE1),
+            status,
+        )
+    }
+
+    fn moderation_reason_ipfs() -> Content {
+        Content::IPFS(b"QmYA2fn8cMbVWo4v95RwcwJVyQsNtnEwHerfWR8UNtEwoE".to_vec())
+    }
+
+    fn _report_account_in_default_storefront(origin: Option<Origin>, account: AccountId) -> DispatchResult {
+        Moderation::report_entity(
+            origin.unwrap_or_else(|| Origin::signed(ACCOUNT2)),
+            EntityId::Account(account),
+            SPACE1,
+            ReportCategory::Abuse,
+            self::moderation_reason_ipfs(),
+        )
+    }
+
+    fn _report_order(origin: Option<Origin>, order_id: OrderId, category: ReportCategory) -> DispatchResult {
+        Moderation::report_entity(
+            origin.unwrap_or_else(|| Origin::signed(ACCOUNT2)),
+            EntityId::Order(order_id),
+            SPACE1,
+            category,
+            self::moderation_reason_ipfs(),
+        )
+    }
+
+    /// Creates an order of one item of a product by ACCOUNT2 in StorefrontId 1.
+    fn _create_default_order(product_id: ProductId) -> DispatchResult {
+        _create_order_by(ACCOUNT2, product_id)
+    }
+
+    /// Creates an order of one item of a product by a given buyer in StorefrontId 1.
+    fn _create_order_by(buyer: AccountId, product_id: ProductId) -> DispatchResult {
+        _create_order(buyer, vec![(product_id, 1)], 1_000)
+    }
+
+    /// Creates an order of given items by a given buyer in StorefrontId 1 for at most `max_total`.
+    fn _create_order(buyer: AccountId, items: Vec<(ProductId, u32)>, max_total: u64) -> DispatchResult {
+        let _ = Balances::deposit_creating(&buyer, 1_000);
+
+        Orders::create_order(
+            Origin::signed(buyer),
+            SPACE1,
+            items,
+            max_total,
+            Content::None,
+            None,
+        )
+    }
+
+    fn _update_order_state(origin: Option<Origin>, order_id: OrderId, order_state: OrderState) -> DispatchResult {
+        Orders::update_order(
+            origin.unwrap_or_else(|| Origin::signed(ACCOUNT1)),
+            order_id,
+            OrderUpdate { content: None, order_state, message: None },
+        )
+    }
+
+    fn _cancel_order(origin: Option<Origin>, order_id: OrderId) -> DispatchResult {
+        Orders::cancel_order(origin.unwrap_or_else(|| Origin::signed(ACCOUNT1)), order_id)
+    }
+
+    fn _withdraw_storefront_funds(origin: Option<Origin>, amount: u64) -> DispatchResult {
+        Storefronts::withdraw_storefront_funds(
+            origin.unwrap_or_else(|| Origin::signed(ACCOUNT1)),
+            SPACE1,
+            ACCOUNT3,
+            amount,
+        )
+    }
+
+    fn _appeal_account_status_in_default_storefront(origin: Option<Origin>, account: AccountId) -> DispatchResult {
+        Moderation::appeal_entity_status(
+            origin.unwrap_or_else(|| Origin::signed(account)),
+            EntityId::Account(account),
+            SPACE1,
+            self::moderation_reason_ipfs(),
+        )
+    }
+
+    fn _resolve_appeal(origin: Option<Origin>, appeal_id: Option<u64>, accept: bool) -> DispatchResult {
+        Moderation::resolve_appeal(
+            origin.unwrap_or_else(|| Origin::signed(ACCOUNT2)),
+            appeal_id.unwrap_or(1),
+            accept,
+        )
+    }
+
     // TODO: fix copy-paste from pallet_roles
     /* Roles pallet mocks */
 
@@ -1538,6 +1864,79 @@ mod tests {
         });
     }
 
+    #[test]
+    fn create_priced_product_should_work() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100)));
+
+            let product = Products::product_by_id(POST1).unwrap();
+            assert_eq!(product.price, Some(self::product_price(100)));
+        });
+    }
+
+    #[test]
+    fn create_priced_product_should_fail_with_zero_price() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_noop!(
+                _create_priced_product(None, None, self::product_price(0)),
+                ProductsError::<TestRuntime>::ZeroProductPrice
+            );
+        });
+    }
+
+    #[test]
+    fn create_priced_product_should_fail_when_sharing_product() {
+        ExtBuilder::build_with_product().execute_with(|| {
+            assert_noop!(
+                _create_priced_product(None, Some(self::extension_shared_product(POST1)), self::product_price(100)),
+                ProductsError::<TestRuntime>::PriceIsAllowedOnlyOnRegularProduct
+            );
+        });
+    }
+
+    #[test]
+    fn update_product_price_should_work() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100)));
+
+            let mut update = self::product_update(None, None, None);
+            update.price = Some(Some(self::product_price(150)));
+            assert_ok!(_update_product(None, None, Some(update)));
+
+            let product = Products::product_by_id(POST1).unwrap();
+            assert_eq!(product.price, Some(self::product_price(150)));
+
+            // Check whether the old price recorded in history
+            let product_history = ProductHistory::edit_history(POST1)[0].clone();
+            assert_eq!(product_history.old_data.price, Some(Some(self::product_price(100))));
+        });
+    }
+
+    #[test]
+    fn restock_product_should_work() {
+        ExtBuilder::build_with_product().execute_with(|| {
+            assert_ok!(Products::restock_product(Origin::signed(ACCOUNT1), POST1, StockUpdate::Add(10)));
+            assert_eq!(Products::product_by_id(POST1).unwrap().stock, Some(10));
+
+            assert_ok!(Products::take_product_stock(POST1, 10));
+            assert_eq!(Products::product_by_id(POST1).unwrap().stock, Some(0));
+            assert_noop!(
+                Products::take_product_stock(POST1, 1),
+                ProductsError::<TestRuntime>::InsufficientProductStock
+            );
+        });
+    }
+
+    #[test]
+    fn restock_product_should_fail_with_no_permission() {
+        ExtBuilder::build_with_product().execute_with(|| {
+            assert_noop!(
+                Products::restock_product(Origin::signed(ACCOUNT2), POST1, StockUpdate::Add(10)),
+                ProductsError::<TestRuntime>::NoPermissionToUpdateAnyProduct
+            );
+        });
+    }
+
     #[test]
     fn update_product_should_work_after_transfer_storefront_ownership() {
         ExtBuilder::build_with_product().execute_with(|| {
@@ -2034,6 +2433,9 @@ mod tests {
             assert_eq!(Scores::score_diff_for_action(1, self::scoring_action_share_comment()), ShareCommentActionWeight::get() as i16);
             assert_eq!(Scores::score_diff_for_action(1, self::scoring_action_follow_storefront()), FollowStorefrontActionWeight::get() as i16);
             assert_eq!(Scores::score_diff_for_action(1, self::scoring_action_follow_account()), FollowAccountActionWeight::get() as i16);
+            assert_eq!(Scores::score_diff_for_action(1, ScoringAction::CompleteOrder), CompleteOrderActionWeight::get() as i16);
+            assert_eq!(Scores::score_diff_for_action(1, ScoringAction::RefuseOrder), RefuseOrderActionWeight::get() as i16);
+            assert_eq!(Scores::score_diff_for_action(1, ScoringAction::LoseDispute), LoseDisputeActionWeight::get() as i16);
         });
     }
 
@@ -3091,4 +3493,2506 @@ mod tests {
             ), StorefrontOwnershipError::<TestRuntime>::NotAllowedToRejectOwnershipTransfer); // Rejecting a transfer from ACCOUNT2
         });
     }
-}
\ No newline at end of file
+
+    // Moderation tests
+
+    #[test]
+    fn update_entity_status_should_block_account() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_block_account_in_default_storefront(ACCOUNT2));
+
+            assert_eq!(
+                Moderation::status_by_entity_in_storefront(EntityId::Account(ACCOUNT2), SPACE1),
+                Some(EntityStatus::Blocked)
+            );
+        });
+    }
+
+    #[test]
+    fn update_entity_status_should_unfollow_blocked_account() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_default_follow_storefront()); // Follow StorefrontId 1 by ACCOUNT2
+            assert_ok!(_block_account_in_default_storefront(ACCOUNT2));
+
+            assert_eq!(Storefronts::storefront_by_id(SPACE1).unwrap().followers_count, 1);
+            assert_eq!(StorefrontFollows::storefront_followed_by_account((ACCOUNT2, SPACE1)), false);
+        });
+    }
+
+    #[test]
+    fn update_entity_status_should_fail_with_no_permission() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_noop!(_update_entity_status(
+                Some(Origin::signed(ACCOUNT2)),
+                EntityId::Account(ACCOUNT3),
+                None,
+                Some(EntityStatus::Blocked)
+            ), ModerationError::<TestRuntime>::NoPermissionToUpdateEntityStatus);
+        });
+    }
+
+    #[test]
+    fn create_product_should_fail_when_account_is_blocked() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_block_account_in_default_storefront(ACCOUNT1));
+
+            assert_noop!(_create_default_product(), StorefrontsError::<TestRuntime>::AccountIsBlocked);
+        });
+    }
+
+    #[test]
+    fn create_product_should_fail_when_content_is_blocked() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_update_entity_status(
+                None,
+                EntityId::Content(self::product_content_ipfs()),
+                None,
+                Some(EntityStatus::Blocked)
+            ));
+
+            assert_noop!(_create_default_product(), StorefrontsError::<TestRuntime>::ContentIsBlocked);
+        });
+    }
+
+    #[test]
+    fn update_entity_status_should_move_blocked_product_out_of_storefront() {
+        ExtBuilder::build_with_product().execute_with(|| {
+            assert_ok!(_update_entity_status(
+                None,
+                EntityId::Product(POST1),
+                None,
+                Some(EntityStatus::Blocked)
+            ));
+
+            assert!(Products::product_by_id(POST1).unwrap().storefront_id.is_none());
+            assert!(Products::product_ids_by_storefront_id(SPACE1).is_empty());
+            assert_eq!(Storefronts::storefront_by_id(SPACE1).unwrap().products_count, 0);
+        });
+    }
+
+    #[test]
+    fn create_product_reaction_should_fail_when_account_is_blocked() {
+        ExtBuilder::build_with_product().execute_with(|| {
+            assert_ok!(_block_account_in_default_storefront(ACCOUNT2));
+
+            assert_noop!(_create_product_reaction(
+                Some(Origin::signed(ACCOUNT2)),
+                None,
+                None
+            ), StorefrontsError::<TestRuntime>::AccountIsBlocked);
+        });
+    }
+
+    #[test]
+    fn follow_storefront_should_fail_when_account_is_blocked() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_block_account_in_default_storefront(ACCOUNT2));
+
+            assert_noop!(_default_follow_storefront(), StorefrontsError::<TestRuntime>::AccountIsBlocked);
+        });
+    }
+
+    #[test]
+    fn update_entity_status_should_accept_open_reports() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_report_account_in_default_storefront(None, ACCOUNT3)); // ReportId 1 by ACCOUNT2
+            assert_eq!(Moderation::open_report_ids_by_storefront_id(SPACE1), vec![1]);
+
+            assert_ok!(_block_account_in_default_storefront(ACCOUNT3));
+
+            let report = Moderation::report_by_id(1).unwrap();
+            assert_eq!(report.status, ReportStatus::Accepted);
+            assert!(Moderation::open_report_ids_by_storefront_id(SPACE1).is_empty());
+            assert_eq!(Moderation::blocker_by_entity_in_storefront(EntityId::Account(ACCOUNT3), SPACE1), Some(ACCOUNT1));
+        });
+    }
+
+    #[test]
+    fn update_entity_status_should_reject_open_reports() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_report_account_in_default_storefront(None, ACCOUNT3)); // ReportId 1 by ACCOUNT2
+            assert_ok!(_update_entity_status(
+                None,
+                EntityId::Account(ACCOUNT3),
+                None,
+                Some(EntityStatus::Allowed)
+            ));
+
+            assert_eq!(Moderation::report_by_id(1).unwrap().status, ReportStatus::Rejected);
+            assert!(Moderation::open_report_ids_by_storefront_id(SPACE1).is_empty());
+        });
+    }
+
+    #[test]
+    fn appeal_entity_status_should_work() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_block_account_in_default_storefront(ACCOUNT3));
+            assert_ok!(_appeal_account_status_in_default_storefront(None, ACCOUNT3)); // AppealId 1
+
+            let appeal = Moderation::appeal_by_id(1).unwrap();
+            assert_eq!(appeal.created.account, ACCOUNT3);
+            assert_eq!(appeal.status, ReportStatus::Open);
+            assert_eq!(Moderation::open_appeal_ids_by_storefront_id(SPACE1), vec![1]);
+            assert_eq!(Moderation::open_appeal_id_by_entity_in_storefront(EntityId::Account(ACCOUNT3), SPACE1), Some(1));
+        });
+    }
+
+    #[test]
+    fn appeal_entity_status_should_fail_when_entity_is_not_blocked() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_noop!(
+                _appeal_account_status_in_default_storefront(None, ACCOUNT3),
+                ModerationError::<TestRuntime>::EntityIsNotBlocked
+            );
+        });
+    }
+
+    #[test]
+    fn appeal_entity_status_should_fail_when_not_allowed_to_appeal() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_block_account_in_default_storefront(ACCOUNT3));
+
+            assert_noop!(
+                _appeal_account_status_in_default_storefront(Some(Origin::signed(ACCOUNT2)), ACCOUNT3),
+                ModerationError::<TestRuntime>::NotAllowedToAppeal
+            );
+        });
+    }
+
+    #[test]
+    fn appeal_entity_status_should_fail_when_appeal_already_open() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_block_account_in_default_storefront(ACCOUNT3));
+            assert_ok!(_appeal_account_status_in_default_storefront(None, ACCOUNT3));
+
+            assert_noop!(
+                _appeal_account_status_in_default_storefront(None, ACCOUNT3),
+                ModerationError::<TestRuntime>::AppealAlreadyOpen
+            );
+        });
+    }
+
+    #[test]
+    fn resolve_appeal_should_unblock_entity_when_accepted() {
+        ExtBuilder::build_with_a_few_roles_granted_to_account2(vec![SP::UpdateEntityStatus]).execute_with(|| {
+            assert_ok!(_block_account_in_default_storefront(ACCOUNT3));
+            assert_ok!(_appeal_account_status_in_default_storefront(None, ACCOUNT3));
+
+            assert_ok!(_resolve_appeal(None, None, true)); // Resolved by ACCOUNT2
+
+            assert_eq!(Moderation::appeal_by_id(1).unwrap().status, ReportStatus::Accepted);
+            assert!(Moderation::status_by_entity_in_storefront(EntityId::Account(ACCOUNT3), SPACE1).is_none());
+            assert!(Moderation::open_appeal_ids_by_storefront_id(SPACE1).is_empty());
+            assert_ok!(_follow_storefront(Some(Origin::signed(ACCOUNT3)), None));
+        });
+    }
+
+    #[test]
+    fn resolve_appeal_should_keep_entity_blocked_when_rejected() {
+        ExtBuilder::build_with_a_few_roles_granted_to_account2(vec![SP::UpdateEntityStatus]).execute_with(|| {
+            assert_ok!(_block_account_in_default_storefront(ACCOUNT3));
+            assert_ok!(_appeal_account_status_in_default_storefront(None, ACCOUNT3));
+
+            assert_ok!(_resolve_appeal(None, None, false)); // Resolved by ACCOUNT2
+
+            assert_eq!(Moderation::appeal_by_id(1).unwrap().status, ReportStatus::Rejected);
+            assert_eq!(
+                Moderation::status_by_entity_in_storefront(EntityId::Account(ACCOUNT3), SPACE1),
+                Some(EntityStatus::Blocked)
+            );
+            assert!(Moderation::open_appeal_id_by_entity_in_storefront(EntityId::Account(ACCOUNT3), SPACE1).is_none());
+        });
+    }
+
+    #[test]
+    fn resolve_appeal_should_fail_when_resolved_by_the_same_moderator() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_block_account_in_default_storefront(ACCOUNT3)); // Blocked by ACCOUNT1
+            assert_ok!(_appeal_account_status_in_default_storefront(None, ACCOUNT3));
+
+            assert_noop!(
+                _resolve_appeal(Some(Origin::signed(ACCOUNT1)), None, true),
+                ModerationError::<TestRuntime>::AppealMustBeResolvedByAnotherModerator
+            );
+        });
+    }
+
+    #[test]
+    fn resolve_appeal_should_fail_when_resolved_by_the_appellant() {
+        ExtBuilder::build_with_a_few_roles_granted_to_account2(vec![SP::UpdateEntityStatus]).execute_with(|| {
+            assert_ok!(_block_account_in_default_storefront(ACCOUNT2)); // Blocked by ACCOUNT1
+            assert_ok!(_appeal_account_status_in_default_storefront(None, ACCOUNT2)); // Appealed by ACCOUNT2
+
+            assert_noop!(
+                _resolve_appeal(None, None, true),
+                ModerationError::<TestRuntime>::CannotResolveOwnAppeal
+            );
+        });
+    }
+
+    #[test]
+    fn report_order_should_work() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(10))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1 by ACCOUNT2
+            assert_ok!(_update_order_state(None, 1, OrderState::Accepted));
+
+            assert_ok!(_report_order(None, 1, ReportCategory::NonDelivery)); // ReportId 1
+
+            let report = Moderation::report_by_id(1).unwrap();
+            assert_eq!(report.reported_entity, EntityId::Order(1));
+            assert_eq!(report.category, ReportCategory::NonDelivery);
+            assert_eq!(Moderation::reported_orders_count_by_storefront_id(SPACE1), 1);
+        });
+    }
+
+    #[test]
+    fn report_order_should_not_count_orders_not_accepted_by_seller() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(10))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1 by ACCOUNT2
+
+            assert_ok!(_report_order(None, 1, ReportCategory::NonDelivery));
+            assert_ok!(_cancel_order(Some(Origin::signed(ACCOUNT2)), 1));
+
+            assert_eq!(Moderation::reported_orders_count_by_storefront_id(SPACE1), 0);
+            assert!(!Moderation::is_reported_order_counted(1));
+        });
+    }
+
+    #[test]
+    fn report_order_should_fail_when_not_involved_in_order() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(10)));
+            assert_ok!(_create_default_order(POST1));
+
+            assert_noop!(
+                _report_order(Some(Origin::signed(ACCOUNT3)), 1, ReportCategory::Scam),
+                ModerationError::<TestRuntime>::NotInvolvedInOrder
+            );
+        });
+    }
+
+    #[test]
+    fn report_order_should_fail_when_order_is_not_in_scope() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(10)));
+            assert_ok!(_create_default_order(POST1));
+            assert_ok!(_create_storefront(None, None, Some(None), None)); // StorefrontId 2
+
+            assert_noop!(Moderation::report_entity(
+                Origin::signed(ACCOUNT2),
+                EntityId::Order(1),
+                SPACE2,
+                ReportCategory::Scam,
+                self::moderation_reason_ipfs(),
+            ), ModerationError::<TestRuntime>::EntityIsNotInScope);
+        });
+    }
+
+    #[test]
+    fn report_entity_should_fail_with_order_only_category() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_noop!(Moderation::report_entity(
+                Origin::signed(ACCOUNT2),
+                EntityId::Account(ACCOUNT3),
+                SPACE1,
+                ReportCategory::NotAsDescribed,
+                self::moderation_reason_ipfs(),
+            ), ModerationError::<TestRuntime>::CategoryIsOnlyForOrders);
+        });
+    }
+
+    /// Creates two accepted orders of ProductId 1 by ACCOUNT2 and reports both of them by ACCOUNT2,
+    /// which reaches `ReportedOrdersAutoblockThreshold` and blocks StorefrontId 1 in `GOVERNANCE_SCOPE`.
+    fn _autoblock_default_storefront() -> DispatchResult {
+        _create_priced_product(None, None, self::product_price(10))?; // ProductId 1
+        _create_default_order(POST1)?; // OrderId 1
+        _create_default_order(POST1)?; // OrderId 2
+        _update_order_state(None, 1, OrderState::Accepted)?;
+        _update_order_state(None, 2, OrderState::Accepted)?;
+
+        _report_order(None, 1, ReportCategory::Scam)?;
+        _report_order(None, 2, ReportCategory::NonDelivery)
+    }
+
+    #[test]
+    fn report_order_should_autoblock_storefront_on_threshold() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(10))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 2
+            assert_ok!(_update_order_state(None, 1, OrderState::Accepted));
+            assert_ok!(_update_order_state(None, 2, OrderState::Accepted));
+
+            // Reporting the same order twice counts it once
+            assert_ok!(_report_order(None, 1, ReportCategory::Scam));
+            assert_ok!(_report_order(Some(Origin::signed(ACCOUNT1)), 1, ReportCategory::Scam));
+            assert!(Moderation::status_by_entity_in_storefront(EntityId::Storefront(SPACE1), GOVERNANCE_SCOPE).is_none());
+
+            assert_ok!(_report_order(None, 2, ReportCategory::NonDelivery));
+
+            assert_eq!(Moderation::reported_orders_count_by_storefront_id(SPACE1), 2);
+            assert_eq!(
+                Moderation::status_by_entity_in_storefront(EntityId::Storefront(SPACE1), GOVERNANCE_SCOPE),
+                Some(EntityStatus::Blocked)
+            );
+            assert_eq!(
+                Moderation::blocker_by_entity_in_storefront(EntityId::Storefront(SPACE1), GOVERNANCE_SCOPE),
+                Some(ACCOUNT2)
+            );
+            assert!(Moderation::status_by_entity_in_storefront(EntityId::Storefront(SPACE1), SPACE1).is_none());
+            assert_noop!(Orders::create_order(
+                Origin::signed(ACCOUNT2),
+                SPACE1,
+                vec![(POST1, 1)],
+                1_000,
+                Content::None,
+                None,
+            ), StorefrontsError::<TestRuntime>::StorefrontIsBlocked);
+        });
+    }
+
+    #[test]
+    fn autoblocked_storefront_should_not_be_unblocked_by_its_owner() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_autoblock_default_storefront());
+
+            assert_noop!(
+                Moderation::delete_entity_status(Origin::signed(ACCOUNT1), EntityId::Storefront(SPACE1), GOVERNANCE_SCOPE),
+                ModerationError::<TestRuntime>::NoPermissionToUpdateEntityStatus
+            );
+        });
+    }
+
+    #[test]
+    fn autoblocked_storefront_appeal_should_be_resolved_by_dispute_arbiter() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_autoblock_default_storefront());
+            assert_ok!(Moderation::appeal_entity_status(
+                Origin::signed(ACCOUNT1),
+                EntityId::Storefront(SPACE1),
+                GOVERNANCE_SCOPE,
+                self::moderation_reason_ipfs(),
+            )); // AppealId 1
+
+            assert_noop!(
+                _resolve_appeal(Some(Origin::signed(ACCOUNT3)), None, true),
+                ModerationError::<TestRuntime>::NoPermissionToUpdateEntityStatus
+            );
+
+            // The reporter that triggered the autoblock cannot resolve the appeal
+            assert_ok!(_add_dispute_arbiter(ACCOUNT2));
+            assert_noop!(
+                _resolve_appeal(Some(Origin::signed(ACCOUNT2)), None, true),
+                ModerationError::<TestRuntime>::AppealMustBeResolvedByAnotherModerator
+            );
+
+            assert_ok!(_add_dispute_arbiter(ACCOUNT3));
+            assert_ok!(_resolve_appeal(Some(Origin::signed(ACCOUNT3)), None, true));
+
+            assert!(Moderation::status_by_entity_in_storefront(EntityId::Storefront(SPACE1), GOVERNANCE_SCOPE).is_none());
+            assert_ok!(_create_default_order(POST1));
+        });
+    }
+
+    // Storefront funds tests
+
+    #[test]
+    fn complete_order_should_pay_storefront_account() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1 by ACCOUNT2
+
+            assert_ok!(_update_order_state(None, 1, OrderState::Accepted));
+            assert_ok!(_update_order_state(None, 1, OrderState::Shipped));
+            assert_ok!(_update_order_state(Some(Origin::signed(ACCOUNT2)), 1, OrderState::Complete));
+
+            // 2% of the order total is a marketplace fee
+            assert_eq!(Balances::free_balance(Storefronts::storefront_account(SPACE1)), 98);
+            assert_eq!(Balances::free_balance(ACCOUNT1), 0);
+            assert_eq!(Balances::free_balance(ACCOUNT2), 900);
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), 0);
+        });
+    }
+
+    #[test]
+    fn withdraw_storefront_funds_should_work() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            let _ = Balances::deposit_creating(&Storefronts::storefront_account(SPACE1), 100);
+
+            assert_ok!(_withdraw_storefront_funds(None, 40));
+
+            assert_eq!(Balances::free_balance(Storefronts::storefront_account(SPACE1)), 60);
+            assert_eq!(Balances::free_balance(ACCOUNT3), 40);
+        });
+    }
+
+    #[test]
+    fn withdraw_storefront_funds_should_work_with_manage_treasury_permission() {
+        ExtBuilder::build_with_a_few_roles_granted_to_account2(vec![SP::ManageTreasury]).execute_with(|| {
+            let _ = Balances::deposit_creating(&Storefronts::storefront_account(SPACE1), 100);
+
+            assert_ok!(_withdraw_storefront_funds(Some(Origin::signed(ACCOUNT2)), 100));
+            assert_eq!(Balances::free_balance(ACCOUNT3), 100);
+        });
+    }
+
+    #[test]
+    fn withdraw_storefront_funds_should_fail_with_no_permission() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            let _ = Balances::deposit_creating(&Storefronts::storefront_account(SPACE1), 100);
+
+            assert_noop!(
+                _withdraw_storefront_funds(Some(Origin::signed(ACCOUNT2)), 40),
+                StorefrontsError::<TestRuntime>::NoPermissionToManageTreasury
+            );
+        });
+    }
+
+    // Order permissions tests
+
+    #[test]
+    fn update_order_should_work_with_accept_and_ship_permissions() {
+        ExtBuilder::build_with_a_few_roles_granted_to_account2(vec![SP::AcceptOrders, SP::ShipOrders]).execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_order_by(ACCOUNT3, POST1)); // OrderId 1
+
+            assert_ok!(_update_order_state(Some(Origin::signed(ACCOUNT2)), 1, OrderState::Accepted));
+            assert_ok!(_update_order_state(Some(Origin::signed(ACCOUNT2)), 1, OrderState::Shipped));
+
+            let order = Orders::order_by_id(1).unwrap();
+            assert_eq!(order.order_state, OrderState::Shipped);
+            assert_eq!(order.seller, ACCOUNT1);
+        });
+    }
+
+    #[test]
+    fn update_order_should_fail_with_no_permission_to_accept_orders() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100)));
+            assert_ok!(_create_order_by(ACCOUNT3, POST1));
+
+            assert_noop!(
+                _update_order_state(Some(Origin::signed(ACCOUNT2)), 1, OrderState::Accepted),
+                OrdersError::<TestRuntime>::NoPermissionToAcceptOrders
+            );
+        });
+    }
+
+    #[test]
+    fn update_order_should_fail_with_no_permission_to_ship_orders() {
+        ExtBuilder::build_with_a_few_roles_granted_to_account2(vec![SP::AcceptOrders]).execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100)));
+            assert_ok!(_create_order_by(ACCOUNT3, POST1));
+            assert_ok!(_update_order_state(Some(Origin::signed(ACCOUNT2)), 1, OrderState::Accepted));
+
+            assert_noop!(
+                _update_order_state(Some(Origin::signed(ACCOUNT2)), 1, OrderState::Shipped),
+                OrdersError::<TestRuntime>::NoPermissionToShipOrders
+            );
+        });
+    }
+
+    #[test]
+    fn cancel_order_should_work_with_refund_orders_permission() {
+        ExtBuilder::build_with_a_few_roles_granted_to_account2(vec![SP::RefundOrders]).execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100)));
+            assert_ok!(_create_order_by(ACCOUNT3, POST1));
+            assert_ok!(_update_order_state(None, 1, OrderState::Accepted));
+
+            // Both requests come from the seller side, so the buyer still has to agree
+            assert_ok!(_cancel_order(Some(Origin::signed(ACCOUNT2)), 1));
+            assert_noop!(
+                _cancel_order(None, 1),
+                OrdersError::<TestRuntime>::CancellationAlreadyRequested
+            );
+
+            assert_ok!(_cancel_order(Some(Origin::signed(ACCOUNT3)), 1));
+            assert_eq!(Orders::order_by_id(1).unwrap().order_state, OrderState::Cancelled);
+        });
+    }
+
+    // Runtime API tests
+
+    #[test]
+    fn get_storefront_ids_by_owner_should_paginate() {
+        ExtBuilder::build().execute_with(|| {
+            assert_ok!(_create_default_storefront()); // StorefrontId 1
+            assert_ok!(_create_storefront(None, None, Some(Some(b"storefront2_handle".to_vec())), None)); // StorefrontId 2
+
+            assert_eq!(Storefronts::get_storefront_ids_by_owner(ACCOUNT1, 0, 10), vec![SPACE1, SPACE2]);
+            assert_eq!(Storefronts::get_storefront_ids_by_owner(ACCOUNT1, 1, 10), vec![SPACE2]);
+            assert!(Storefronts::get_storefront_ids_by_owner(ACCOUNT2, 0, 10).is_empty());
+        });
+    }
+
+    #[test]
+    fn has_account_storefront_permission_should_work() {
+        ExtBuilder::build_with_a_few_roles_granted_to_account2(vec![SP::ManageTreasury]).execute_with(|| {
+            assert!(Storefronts::has_account_storefront_permission(ACCOUNT1, SPACE1, SP::UpdateStorefront));
+            assert!(Storefronts::has_account_storefront_permission(ACCOUNT2, SPACE1, SP::ManageTreasury));
+            assert!(!Storefronts::has_account_storefront_permission(ACCOUNT3, SPACE1, SP::ManageTreasury));
+            assert!(!Storefronts::has_account_storefront_permission(ACCOUNT1, SPACE2, SP::UpdateStorefront));
+        });
+    }
+
+    #[test]
+    fn get_product_ids_by_storefront_should_skip_hidden_products() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_default_product()); // ProductId 1
+            assert_ok!(_create_default_product()); // ProductId 2
+            assert_ok!(_create_default_product()); // ProductId 3
+            assert_ok!(_update_product(None, Some(POST2), Some(self::product_update(None, None, Some(true)))));
+
+            assert_eq!(Products::get_product_ids_by_storefront(SPACE1, false, 0, 10), vec![POST1, POST3]);
+            assert_eq!(Products::get_product_ids_by_storefront(SPACE1, true, 1, 1), vec![POST2]);
+        });
+    }
+
+    #[test]
+    fn get_reply_ids_tree_should_work() {
+        ExtBuilder::build_with_comment().execute_with(|| {
+            assert_ok!(_create_comment(None, None, Some(Some(POST2)), None)); // ProductId 3 replies to 2
+            assert_ok!(_create_default_comment()); // ProductId 4
+
+            assert_eq!(
+                Products::get_reply_ids_tree(POST1, 0, 10),
+                vec![(POST1, vec![POST2, 4]), (POST2, vec![POST3])]
+            );
+            assert_eq!(Products::get_reply_ids_tree(POST1, 1, 1), vec![(POST2, vec![POST3])]);
+            assert!(Products::get_reply_ids_tree(POST3, 0, 10).is_empty());
+        });
+    }
+
+    #[test]
+    fn get_order_ids_should_filter_by_state() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(10))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 2
+            assert_ok!(_update_order_state(None, 1, OrderState::Accepted));
+
+            assert_eq!(Orders::get_order_ids_by_seller(ACCOUNT1, None, 0, 10), vec![1, 2]);
+            assert_eq!(Orders::get_order_ids_by_seller(ACCOUNT1, Some(OrderState::Accepted), 0, 10), vec![1]);
+            assert_eq!(Orders::get_order_ids_by_buyer(ACCOUNT2, Some(OrderState::New), 0, 10), vec![2]);
+            assert!(Orders::get_order_ids_by_buyer(ACCOUNT1, None, 0, 10).is_empty());
+        });
+    }
+
+    #[test]
+    fn get_order_ids_by_seller_should_follow_storefront_ownership() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(10))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+
+            assert_ok!(_transfer_default_storefront_ownership());
+            assert_ok!(_accept_default_pending_ownership());
+
+            assert!(Orders::get_order_ids_by_seller(ACCOUNT1, None, 0, 10).is_empty());
+            assert_eq!(Orders::get_order_ids_by_seller(ACCOUNT2, None, 0, 10), vec![1]);
+        });
+    }
+
+    // Product deletion tests
+
+    #[test]
+    fn delete_product_should_work() {
+        ExtBuilder::build_with_product().execute_with(|| {
+            assert_ok!(_delete_product(None, None));
+
+            assert!(Products::product_by_id(POST1).is_none());
+            assert!(Products::product_ids_by_storefront_id(SPACE1).is_empty());
+
+            let storefront = Storefronts::storefront_by_id(SPACE1).unwrap();
+            assert_eq!(storefront.products_count, 0);
+            assert_eq!(storefront.hidden_products_count, 0);
+        });
+    }
+
+    #[test]
+    fn delete_product_should_remove_replies_reactions_and_scores() {
+        ExtBuilder::build_with_comment().execute_with(|| {
+            assert_ok!(_create_product_reaction(Some(Origin::signed(ACCOUNT2)), None, None)); // ReactionId 1
+            assert!(Scores::product_score_by_account((ACCOUNT2, POST1, ScoringAction::UpvoteProduct)).is_some());
+
+            assert_ok!(_delete_product(None, None));
+
+            assert!(Products::product_by_id(POST2).is_none());
+            assert!(Products::reply_ids_by_product_id(POST1).is_empty());
+            assert!(Reactions::reaction_by_id(1).is_none());
+            assert!(Reactions::reaction_ids_by_product_id(POST1).is_empty());
+            assert!(Scores::product_score_by_account((ACCOUNT2, POST1, ScoringAction::UpvoteProduct)).is_none());
+        });
+    }
+
+    #[test]
+    fn delete_comment_should_update_ancestor_counters() {
+        ExtBuilder::build_with_comment().execute_with(|| {
+            assert_ok!(_create_comment(None, None, Some(Some(POST2)), None)); // ProductId 3 replies to 2
+            assert_eq!(Products::product_by_id(POST1).unwrap().replies_count, 2);
+
+            assert_ok!(_delete_product(None, Some(POST2)));
+
+            assert!(Products::product_by_id(POST2).is_none());
+            assert!(Products::product_by_id(POST3).is_none());
+            assert!(Products::reply_ids_by_product_id(POST1).is_empty());
+            assert_eq!(Products::product_by_id(POST1).unwrap().replies_count, 0);
+        });
+    }
+
+    #[test]
+    fn delete_product_should_fail_when_max_replies_is_too_low() {
+        ExtBuilder::build_with_comment().execute_with(|| {
+            assert_noop!(
+                Products::delete_product(Origin::signed(ACCOUNT1), POST1, 0),
+                ProductsError::<TestRuntime>::TooManyRepliesToDelete
+            );
+        });
+    }
+
+    #[test]
+    fn delete_product_should_fail_with_no_permission() {
+        ExtBuilder::build_with_product().execute_with(|| {
+            assert_noop!(
+                _delete_product(Some(Origin::signed(ACCOUNT2)), None),
+                ProductsError::<TestRuntime>::NoPermissionToDeleteAnyProduct
+            );
+        });
+    }
+
+    #[test]
+    fn delete_product_should_fail_with_open_orders() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+
+            assert_noop!(
+                _delete_product(None, None),
+                OrdersError::<TestRuntime>::ProductHasOpenOrders
+            );
+
+            assert_ok!(_update_order_state(None, 1, OrderState::Refused));
+            assert_ok!(_delete_product(None, None));
+        });
+    }
+
+    // Product moving tests
+
+    fn _move_product_to_storefront2() -> DispatchResult {
+        _update_product(None, None, Some(self::product_update(Some(SPACE2), None, None)))
+    }
+
+    #[test]
+    fn move_product_should_work() {
+        ExtBuilder::build_with_product().execute_with(|| {
+            assert_ok!(_create_storefront(None, None, Some(Some(b"storefront2_handle".to_vec())), None)); // StorefrontId 2
+            assert_ok!(_move_product_to_storefront2());
+
+            assert_eq!(Products::product_by_id(POST1).unwrap().storefront_id, Some(SPACE2));
+            assert!(Products::product_ids_by_storefront_id(SPACE1).is_empty());
+            assert_eq!(Products::product_ids_by_storefront_id(SPACE2), vec![POST1]);
+
+            assert_eq!(Storefronts::storefront_by_id(SPACE1).unwrap().products_count, 0);
+            assert_eq!(Storefronts::storefront_by_id(SPACE2).unwrap().products_count, 1);
+        });
+    }
+
+    #[test]
+    fn move_hidden_product_should_move_hidden_counter_and_score() {
+        ExtBuilder::build_with_product().execute_with(|| {
+            assert_ok!(_create_product_reaction(Some(Origin::signed(ACCOUNT2)), None, None));
+            assert_ok!(_update_product(None, None, Some(self::product_update(None, None, Some(true)))));
+            assert_ok!(_create_storefront(None, None, Some(Some(b"storefront2_handle".to_vec())), None)); // StorefrontId 2
+
+            let product_score = Products::product_by_id(POST1).unwrap().score;
+            let old_storefront_score = Storefronts::storefront_by_id(SPACE1).unwrap().score;
+            let new_storefront_score = Storefronts::storefront_by_id(SPACE2).unwrap().score;
+            assert_ok!(_move_product_to_storefront2());
+
+            let old_storefront = Storefronts::storefront_by_id(SPACE1).unwrap();
+            assert_eq!(old_storefront.hidden_products_count, 0);
+            assert_eq!(old_storefront.score, old_storefront_score - product_score);
+
+            let new_storefront = Storefronts::storefront_by_id(SPACE2).unwrap();
+            assert_eq!(new_storefront.hidden_products_count, 1);
+            assert_eq!(new_storefront.score, new_storefront_score + product_score);
+        });
+    }
+
+    #[test]
+    fn move_product_should_fail_with_no_permission_in_new_storefront() {
+        ExtBuilder::build_with_product().execute_with(|| {
+            assert_ok!(_create_storefront(
+                Some(Origin::signed(ACCOUNT2)),
+                None,
+                Some(Some(b"storefront2_handle".to_vec())),
+                None
+            )); // StorefrontId 2
+
+            assert_noop!(
+                _move_product_to_storefront2(),
+                ProductsError::<TestRuntime>::NoPermissionToCreateProducts
+            );
+        });
+    }
+
+    #[test]
+    fn move_product_should_fail_when_not_a_product_owner() {
+        ExtBuilder::build_with_product().execute_with(|| {
+            assert_ok!(_create_role(None, None, None, None, Some(vec![SP::UpdateAnyProduct]))); // RoleId 1
+            assert_ok!(_grant_default_role()); // Grant RoleId 1 to ACCOUNT2
+            assert_ok!(_create_storefront(None, None, Some(Some(b"storefront2_handle".to_vec())), None)); // StorefrontId 2
+
+            assert_noop!(
+                _update_product(
+                    Some(Origin::signed(ACCOUNT2)),
+                    None,
+                    Some(self::product_update(Some(SPACE2), None, None))
+                ),
+                ProductsError::<TestRuntime>::OnlyProductOwnerCanMoveProduct
+            );
+        });
+    }
+
+    #[test]
+    fn move_product_should_not_change_storefronts_when_later_update_fails() {
+        ExtBuilder::build_with_product().execute_with(|| {
+            assert_ok!(_create_storefront(None, None, Some(Some(b"storefront2_handle".to_vec())), None)); // StorefrontId 2
+
+            let update = ProductUpdate {
+                price: Some(Some(self::product_price(0))),
+                ..self::product_update(Some(SPACE2), None, None)
+            };
+            assert_noop!(
+                _update_product(None, None, Some(update)),
+                ProductsError::<TestRuntime>::ZeroProductPrice
+            );
+        });
+    }
+
+    #[test]
+    fn move_comment_should_fail() {
+        ExtBuilder::build_with_comment().execute_with(|| {
+            assert_ok!(_create_storefront(None, None, Some(Some(b"storefront2_handle".to_vec())), None)); // StorefrontId 2
+
+            assert_noop!(
+                _update_comment(None, None, Some(self::product_update(Some(SPACE2), None, None))),
+                ProductsError::<TestRuntime>::CannotUpdateStorefrontIdOnComment
+            );
+        });
+    }
+
+    #[test]
+    fn move_product_should_keep_open_orders_in_original_storefront() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+            assert_ok!(_create_storefront(None, None, Some(Some(b"storefront2_handle".to_vec())), None)); // StorefrontId 2
+
+            assert_ok!(_move_product_to_storefront2());
+
+            assert_eq!(Orders::order_by_id(1).unwrap().storefront_id, SPACE1);
+            // The helper funds the buyer before creating an order, so assert_noop cannot be used here
+            assert_eq!(
+                _create_default_order(POST1),
+                Err(OrdersError::<TestRuntime>::ProductIdNotFoundInGivenStorefront.into())
+            );
+        });
+    }
+
+    // Storefront deletion tests
+
+    fn _delete_storefront(origin: Option<Origin>, storefront_id: Option<StorefrontId>) -> DispatchResult {
+        Storefronts::delete_storefront(
+            origin.unwrap_or_else(|| Origin::signed(ACCOUNT1)),
+            storefront_id.unwrap_or(SPACE1),
+        )
+    }
+
+    #[test]
+    fn delete_storefront_should_archive_storefront_and_release_handle() {
+        ExtBuilder::build_with_product().execute_with(|| {
+            assert_ok!(_default_follow_storefront());
+            assert_ok!(_delete_storefront(None, None));
+
+            let storefront = Storefronts::storefront_by_id(SPACE1).unwrap();
+            assert!(storefront.archived);
+            assert!(storefront.hidden);
+            assert!(storefront.handle.is_none());
+            assert_eq!(storefront.hidden_products_count, 1);
+            assert_eq!(storefront.followers_count, 0);
+
+            assert!(Storefronts::storefront_id_by_handle(self::storefront_handle()).is_none());
+            assert_eq!(Storefronts::storefront_ids_by_owner(ACCOUNT1), vec![SPACE1]);
+            assert!(Products::product_by_id(POST1).unwrap().hidden);
+            assert!(StorefrontFollows::storefront_followers(SPACE1).is_empty());
+            assert!(!StorefrontFollows::storefront_followed_by_account((ACCOUNT2, SPACE1)));
+
+            // The released handle can be used by a new storefront
+            assert_ok!(_create_default_storefront()); // StorefrontId 2
+            assert_eq!(Storefronts::storefront_id_by_handle(self::storefront_handle()), Some(SPACE2));
+        });
+    }
+
+    #[test]
+    fn delete_storefront_should_detach_substorefronts() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_storefront(None, Some(Some(SPACE1)), Some(None), None)); // StorefrontId 2
+            assert_eq!(Storefronts::storefront_ids_by_parent_id(SPACE1), vec![SPACE2]);
+
+            assert_ok!(_delete_storefront(None, None));
+
+            assert!(Storefronts::storefront_by_id(SPACE2).unwrap().parent_id.is_none());
+            assert!(Storefronts::storefront_ids_by_parent_id(SPACE1).is_empty());
+        });
+    }
+
+    #[test]
+    fn delete_storefront_should_keep_orders_of_archived_storefront_by_seller() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+            assert_ok!(_update_order_state(None, 1, OrderState::Refused));
+
+            assert_ok!(_delete_storefront(None, None));
+
+            assert_eq!(Orders::get_order_ids_by_seller(ACCOUNT1, None, 0, 10), vec![1]);
+        });
+    }
+
+    #[test]
+    fn delete_storefront_should_delete_its_roles() {
+        ExtBuilder::build_with_a_few_roles_granted_to_account2(vec![SP::ManageTreasury]).execute_with(|| {
+            assert_ok!(_delete_storefront(None, None));
+
+            assert!(Roles::role_ids_by_storefront_id(SPACE1).is_empty());
+            assert!(Roles::role_by_id(ROLE1).is_none());
+            assert!(Roles::users_by_role_id(ROLE1).is_empty());
+            assert!(Roles::role_ids_by_user_in_storefront((User::Account(ACCOUNT2), SPACE1)).is_empty());
+        });
+    }
+
+    #[test]
+    fn delete_storefront_should_fail_with_open_orders() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+
+            assert_noop!(
+                _delete_storefront(None, None),
+                OrdersError::<TestRuntime>::StorefrontHasOpenOrders
+            );
+        });
+    }
+
+    #[test]
+    fn delete_storefront_should_fail_when_not_an_owner() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_noop!(
+                _delete_storefront(Some(Origin::signed(ACCOUNT2)), None),
+                StorefrontsError::<TestRuntime>::NotAStorefrontOwner
+            );
+        });
+    }
+
+    #[test]
+    fn update_storefront_should_fail_when_storefront_is_archived() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_delete_storefront(None, None));
+
+            assert_noop!(
+                _update_storefront(None, None, Some(self::storefront_update(None, None, None, Some(true), None))),
+                StorefrontsError::<TestRuntime>::StorefrontIsArchived
+            );
+        });
+    }
+
+    #[test]
+    fn update_product_should_fail_when_storefront_is_archived() {
+        ExtBuilder::build_with_product().execute_with(|| {
+            assert_ok!(_delete_storefront(None, None));
+
+            assert_noop!(
+                _update_product(None, None, Some(self::product_update(None, None, Some(true)))),
+                StorefrontsError::<TestRuntime>::StorefrontIsArchived
+            );
+        });
+    }
+
+    #[test]
+    fn create_role_should_fail_when_storefront_is_archived() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_delete_storefront(None, None));
+
+            assert_noop!(
+                _create_default_role(),
+                RolesError::<TestRuntime>::StorefrontIsArchived
+            );
+        });
+    }
+
+    // Storage deposit tests
+
+    const STORAGE_DEPOSIT: u64 = 10;
+
+    #[test]
+    fn create_storefront_should_reserve_storage_deposit_until_deleted() {
+        ExtBuilder::build().execute_with(|| {
+            let _ = Balances::deposit_creating(&ACCOUNT1, 100);
+            set_storage_deposit_base(STORAGE_DEPOSIT);
+
+            assert_ok!(_create_default_storefront()); // StorefrontId 1
+            assert_eq!(Balances::reserved_balance(ACCOUNT1), STORAGE_DEPOSIT);
+            assert_eq!(Storefronts::storage_deposit_by_storefront_id(SPACE1).unwrap().amount, STORAGE_DEPOSIT);
+
+            assert_ok!(_delete_storefront(None, None));
+            assert_eq!(Balances::reserved_balance(ACCOUNT1), 0);
+            assert!(Storefronts::storage_deposit_by_storefront_id(SPACE1).is_none());
+
+            set_storage_deposit_base(0);
+        });
+    }
+
+    #[test]
+    fn create_storefront_should_fail_without_funds_for_storage_deposit() {
+        ExtBuilder::build().execute_with(|| {
+            set_storage_deposit_base(STORAGE_DEPOSIT);
+
+            assert_noop!(
+                _create_default_storefront(),
+                UtilsError::<TestRuntime>::InsufficientBalanceForStorageDeposit
+            );
+
+            set_storage_deposit_base(0);
+        });
+    }
+
+    #[test]
+    fn delete_product_should_release_storage_deposit() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            let _ = Balances::deposit_creating(&ACCOUNT1, 100);
+            set_storage_deposit_base(STORAGE_DEPOSIT);
+
+            assert_ok!(_create_default_product()); // ProductId 1
+            assert_eq!(Balances::reserved_balance(ACCOUNT1), STORAGE_DEPOSIT);
+
+            assert_ok!(_delete_product(None, None));
+            assert_eq!(Balances::reserved_balance(ACCOUNT1), 0);
+            assert!(Products::storage_deposit_by_product_id(POST1).is_none());
+
+            set_storage_deposit_base(0);
+        });
+    }
+
+    #[test]
+    fn unfollow_storefront_should_release_storage_deposit() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            let _ = Balances::deposit_creating(&ACCOUNT2, 100);
+            set_storage_deposit_base(STORAGE_DEPOSIT);
+
+            assert_ok!(_default_follow_storefront());
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), STORAGE_DEPOSIT);
+
+            assert_ok!(_default_unfollow_storefront());
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), 0);
+            assert!(StorefrontFollows::storage_deposit_by_follow((ACCOUNT2, SPACE1)).is_none());
+
+            set_storage_deposit_base(0);
+        });
+    }
+
+    #[test]
+    fn delete_role_should_release_storage_deposit() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            let _ = Balances::deposit_creating(&ACCOUNT1, 100);
+            set_storage_deposit_base(STORAGE_DEPOSIT);
+
+            assert_ok!(_create_default_role()); // RoleId 1
+            assert_eq!(Balances::reserved_balance(ACCOUNT1), STORAGE_DEPOSIT);
+
+            assert_ok!(_delete_default_role());
+            assert_eq!(Balances::reserved_balance(ACCOUNT1), 0);
+            assert!(Roles::storage_deposit_by_role_id(ROLE1).is_none());
+
+            set_storage_deposit_base(0);
+        });
+    }
+
+    #[test]
+    fn closed_order_should_release_storage_deposit() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            set_storage_deposit_base(STORAGE_DEPOSIT);
+
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+            assert_eq!(Orders::storage_deposit_by_order_id(1).unwrap().amount, STORAGE_DEPOSIT);
+
+            assert_ok!(_update_order_state(None, 1, OrderState::Refused));
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), 0);
+            assert!(Orders::storage_deposit_by_order_id(1).is_none());
+
+            set_storage_deposit_base(0);
+        });
+    }
+
+    // Product category tests
+
+    const CATEGORY1: CategoryId = 1;
+    const CATEGORY2: CategoryId = 2;
+    const CATEGORY3: CategoryId = 3;
+
+    fn category_content_ipfs() -> Content {
+        Content::IPFS(b"QmRAQB6YaCyidP37UdDnjFY5vQuiBrcqdyoW1CuDgwxkD4".to_vec())
+    }
+
+    fn _create_category(parent_id_opt: Option<CategoryId>) -> DispatchResult {
+        Categories::create_category(Origin::root(), parent_id_opt, self::category_content_ipfs())
+    }
+
+    fn _create_categorized_product(categories: Vec<CategoryId>) -> DispatchResult {
+        Products::create_product(
+            Origin::signed(ACCOUNT1),
+            Some(SPACE1),
+            self::extension_regular_product(),
+            self::product_content_ipfs(),
+            None,
+            categories,
+        )
+    }
+
+    fn _update_product_categories(product_id: ProductId, categories: Vec<CategoryId>) -> DispatchResult {
+        let mut update = ProductUpdate::default();
+        update.categories = Some(categories);
+        _update_product(None, Some(product_id), Some(update))
+    }
+
+    fn _set_storefront_categories(category_ids: Vec<CategoryId>) -> DispatchResult {
+        Categories::set_storefront_categories(Origin::signed(ACCOUNT1), SPACE1, category_ids)
+    }
+
+    #[test]
+    fn create_product_should_index_it_by_categories() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_category(None)); // CategoryId 1
+            assert_ok!(_create_category(Some(CATEGORY1))); // CategoryId 2
+
+            assert_ok!(_create_categorized_product(vec![CATEGORY1, CATEGORY2])); // ProductId 1
+
+            assert_eq!(Products::product_by_id(POST1).unwrap().categories, vec![CATEGORY1, CATEGORY2]);
+            assert_eq!(Products::product_ids_by_category(CATEGORY1), vec![POST1]);
+            assert_eq!(Products::product_ids_by_category(CATEGORY2), vec![POST1]);
+            assert_eq!(Categories::category_ids_by_parent_id(CATEGORY1), vec![CATEGORY2]);
+        });
+    }
+
+    #[test]
+    fn create_product_should_fail_with_too_many_categories() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_category(None)); // CategoryId 1
+            assert_ok!(_create_category(None)); // CategoryId 2
+            assert_ok!(_create_category(None)); // CategoryId 3
+
+            assert_noop!(
+                _create_categorized_product(vec![CATEGORY1, CATEGORY2, CATEGORY3]),
+                CategoriesError::<TestRuntime>::TooManyProductCategories
+            );
+            assert_noop!(
+                _create_categorized_product(vec![CATEGORY1, CATEGORY1]),
+                CategoriesError::<TestRuntime>::DuplicateCategories
+            );
+        });
+    }
+
+    #[test]
+    fn create_product_should_fail_when_category_is_not_allowed_in_storefront() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_category(None)); // CategoryId 1
+            assert_ok!(_create_category(None)); // CategoryId 2
+            assert_ok!(_create_category(Some(CATEGORY1))); // CategoryId 3
+            assert_ok!(_set_storefront_categories(vec![CATEGORY1]));
+
+            assert_noop!(
+                _create_categorized_product(vec![CATEGORY2]),
+                CategoriesError::<TestRuntime>::CategoryNotAllowedInStorefront
+            );
+
+            // A subcategory of an allowed category is allowed too:
+            assert_ok!(_create_categorized_product(vec![CATEGORY3]));
+        });
+    }
+
+    #[test]
+    fn update_product_should_reindex_its_categories() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_category(None)); // CategoryId 1
+            assert_ok!(_create_category(None)); // CategoryId 2
+            assert_ok!(_create_categorized_product(vec![CATEGORY1])); // ProductId 1
+
+            assert_ok!(_update_product_categories(POST1, vec![CATEGORY2]));
+
+            assert_eq!(Products::product_by_id(POST1).unwrap().categories, vec![CATEGORY2]);
+            assert!(Products::product_ids_by_category(CATEGORY1).is_empty());
+            assert_eq!(Products::product_ids_by_category(CATEGORY2), vec![POST1]);
+        });
+    }
+
+    #[test]
+    fn update_product_should_fail_to_categorize_comment() {
+        ExtBuilder::build_with_comment().execute_with(|| {
+            assert_ok!(_create_category(None)); // CategoryId 1
+
+            assert_noop!(
+                _update_product_categories(POST2, vec![CATEGORY1]),
+                ProductsError::<TestRuntime>::CategoriesAreAllowedOnlyOnRegularProduct
+            );
+        });
+    }
+
+    #[test]
+    fn delete_product_should_remove_it_from_category_index() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_category(None)); // CategoryId 1
+            assert_ok!(_create_categorized_product(vec![CATEGORY1])); // ProductId 1
+
+            assert_ok!(_delete_product(None, None));
+
+            assert!(Products::product_ids_by_category(CATEGORY1).is_empty());
+        });
+    }
+
+    #[test]
+    fn update_category_should_fail_when_moved_under_its_subcategory() {
+        ExtBuilder::build().execute_with(|| {
+            assert_ok!(_create_category(None)); // CategoryId 1
+            assert_ok!(_create_category(Some(CATEGORY1))); // CategoryId 2
+
+            assert_noop!(
+                Categories::update_category(
+                    Origin::root(),
+                    CATEGORY1,
+                    CategoryUpdate { parent_id: Some(Some(CATEGORY2)), content: None, hidden: None }
+                ),
+                CategoriesError::<TestRuntime>::CategoryCannotBeItsOwnAncestor
+            );
+        });
+    }
+
+    // Dispute tests
+
+    fn dispute_evidence_ipfs() -> Content {
+        Content::IPFS(b"QmYA2fn8cMbVWo4v95RwcwJVyQsNtnEwHerfWR8UNtEwoE".to_vec())
+    }
+
+    /// Sets a 10% buyer escrow and a 20% seller escrow for orders of StorefrontId 1.
+    fn _set_default_order_escrows() -> DispatchResult {
+        Orders::update_storefront_order_settings(
+            Origin::signed(ACCOUNT1),
+            SPACE1,
+            StorefrontOrderSettingsUpdate {
+                buyer_escrow: Some(Percent::from_percent(10)),
+                seller_escrow: Some(Percent::from_percent(20)),
+            },
+        )
+    }
+
+    fn _open_dispute(origin: Option<Origin>, order_id: OrderId) -> DispatchResult {
+        Orders::open_dispute(
+            origin.unwrap_or_else(|| Origin::signed(ACCOUNT2)),
+            order_id,
+            self::dispute_evidence_ipfs(),
+        )
+    }
+
+    fn _add_dispute_evidence(origin: Option<Origin>, order_id: OrderId) -> DispatchResult {
+        Orders::add_dispute_evidence(
+            origin.unwrap_or_else(|| Origin::signed(ACCOUNT1)),
+            order_id,
+            self::dispute_evidence_ipfs(),
+        )
+    }
+
+    fn _resolve_dispute(origin: Option<Origin>, order_id: OrderId, ruling: OrderState) -> DispatchResult {
+        Orders::resolve_dispute(origin.unwrap_or_else(|| Origin::signed(ACCOUNT3)), order_id, ruling)
+    }
+
+    fn _add_dispute_arbiter(arbiter: AccountId) -> DispatchResult {
+        Orders::add_dispute_arbiter(Origin::root(), arbiter)
+    }
+
+    #[test]
+    fn open_dispute_should_work() {
+        ExtBuilder::build_with_disputed_order().execute_with(|| {
+            let order = Orders::order_by_id(1).unwrap();
+            assert_eq!(order.order_state, OrderState::Dispute);
+            assert!(order.deadline.is_none());
+
+            let dispute = Orders::dispute_by_order_id(1).unwrap();
+            assert_eq!(dispute.created.account, ACCOUNT2);
+            assert_eq!(dispute.disputed_state, OrderState::Accepted);
+            assert_eq!(dispute.evidence.len(), 1);
+            assert!(dispute.ruling.is_none());
+
+            // Funds of both parties stay reserved until the ruling
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), 110);
+            assert_eq!(Balances::reserved_balance(ACCOUNT1), 20);
+        });
+    }
+
+    #[test]
+    fn open_dispute_should_fail_when_order_is_not_accepted() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+
+            assert_noop!(_open_dispute(None, 1), OrdersError::<TestRuntime>::OrderCannotBeDisputed);
+        });
+    }
+
+    #[test]
+    fn open_dispute_should_fail_when_not_involved_in_order() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+            assert_ok!(_update_order_state(None, 1, OrderState::Accepted));
+
+            assert_noop!(
+                _open_dispute(Some(Origin::signed(ACCOUNT3)), 1),
+                OrdersError::<TestRuntime>::NoPermissionToOpenDisputes
+            );
+        });
+    }
+
+    #[test]
+    fn open_dispute_should_clear_cancellation_request() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+            assert_ok!(_update_order_state(None, 1, OrderState::Accepted));
+            assert_ok!(_cancel_order(Some(Origin::signed(ACCOUNT2)), 1));
+
+            assert_ok!(_open_dispute(None, 1));
+
+            // The seller cannot agree to the old request and cancel the disputed order
+            assert!(Orders::cancellation_requested_by(1).is_none());
+            assert_noop!(_cancel_order(None, 1), OrdersError::<TestRuntime>::OrderCannotBeCancelled);
+        });
+    }
+
+    #[test]
+    fn add_dispute_evidence_should_work() {
+        ExtBuilder::build_with_disputed_order().execute_with(|| {
+            assert_ok!(_add_dispute_evidence(None, 1));
+
+            let dispute = Orders::dispute_by_order_id(1).unwrap();
+            assert_eq!(dispute.evidence.len(), 2);
+            assert_eq!(dispute.evidence[1].created.account, ACCOUNT1);
+            assert_eq!(dispute.updated.unwrap().account, ACCOUNT1);
+        });
+    }
+
+    #[test]
+    fn add_dispute_evidence_should_fail_when_dispute_is_resolved() {
+        ExtBuilder::build_with_disputed_order().execute_with(|| {
+            assert_ok!(_resolve_dispute(None, 1, OrderState::Refunded));
+
+            assert_noop!(
+                _add_dispute_evidence(None, 1),
+                OrdersError::<TestRuntime>::DisputeAlreadyResolved
+            );
+        });
+    }
+
+    #[test]
+    fn add_dispute_evidence_should_fail_when_not_involved_in_order() {
+        ExtBuilder::build_with_disputed_order().execute_with(|| {
+            assert_noop!(
+                _add_dispute_evidence(Some(Origin::signed(ACCOUNT3)), 1),
+                OrdersError::<TestRuntime>::NoPermissionToOpenDisputes
+            );
+        });
+    }
+
+    #[test]
+    fn resolve_dispute_should_refund_buyer_and_release_seller_escrow() {
+        ExtBuilder::build_with_disputed_order().execute_with(|| {
+            assert_ok!(_resolve_dispute(None, 1, OrderState::Refunded));
+
+            assert_eq!(Orders::order_by_id(1).unwrap().order_state, OrderState::Refunded);
+            assert_eq!(Orders::dispute_by_order_id(1).unwrap().ruling, Some(OrderState::Refunded));
+
+            assert_eq!(Balances::free_balance(ACCOUNT2), 1_000);
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), 0);
+            assert_eq!(Balances::free_balance(ACCOUNT1), 100);
+            assert_eq!(Balances::reserved_balance(ACCOUNT1), 0);
+            assert_eq!(Balances::free_balance(Utils::treasury_account()), 0);
+        });
+    }
+
+    #[test]
+    fn resolve_dispute_should_pay_seller_and_slash_buyer_escrow() {
+        ExtBuilder::build_with_disputed_order().execute_with(|| {
+            assert_ok!(_resolve_dispute(None, 1, OrderState::SlashedBuyer));
+
+            assert_eq!(Orders::order_by_id(1).unwrap().order_state, OrderState::SlashedBuyer);
+
+            assert_eq!(Balances::free_balance(ACCOUNT2), 890);
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), 0);
+            assert_eq!(Balances::free_balance(ACCOUNT1), 100);
+            assert_eq!(Balances::reserved_balance(ACCOUNT1), 0);
+
+            // The treasury gets a 2% marketplace fee and the buyer's escrow
+            assert_eq!(Balances::free_balance(Storefronts::storefront_account(SPACE1)), 98);
+            assert_eq!(Balances::free_balance(Utils::treasury_account()), 12);
+        });
+    }
+
+    #[test]
+    fn resolve_dispute_should_refund_buyer_and_slash_seller_escrow() {
+        ExtBuilder::build_with_disputed_order().execute_with(|| {
+            assert_ok!(_resolve_dispute(None, 1, OrderState::SlashedSeller));
+
+            assert_eq!(Orders::order_by_id(1).unwrap().order_state, OrderState::SlashedSeller);
+
+            assert_eq!(Balances::free_balance(ACCOUNT2), 1_000);
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), 0);
+            assert_eq!(Balances::free_balance(ACCOUNT1), 80);
+            assert_eq!(Balances::reserved_balance(ACCOUNT1), 0);
+            assert_eq!(Balances::free_balance(Utils::treasury_account()), 20);
+        });
+    }
+
+    #[test]
+    fn resolve_dispute_should_refund_order_total_and_slash_both_escrows() {
+        ExtBuilder::build_with_disputed_order().execute_with(|| {
+            assert_ok!(_resolve_dispute(None, 1, OrderState::SlashedBoth));
+
+            assert_eq!(Orders::order_by_id(1).unwrap().order_state, OrderState::SlashedBoth);
+
+            assert_eq!(Balances::free_balance(ACCOUNT2), 990);
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), 0);
+            assert_eq!(Balances::free_balance(ACCOUNT1), 80);
+            assert_eq!(Balances::reserved_balance(ACCOUNT1), 0);
+            assert_eq!(Balances::free_balance(Utils::treasury_account()), 30);
+        });
+    }
+
+    #[test]
+    fn resolve_dispute_should_fail_with_invalid_ruling() {
+        ExtBuilder::build_with_disputed_order().execute_with(|| {
+            assert_noop!(
+                _resolve_dispute(None, 1, OrderState::Complete),
+                OrdersError::<TestRuntime>::InvalidDisputeRuling
+            );
+        });
+    }
+
+    #[test]
+    fn resolve_dispute_should_fail_when_not_an_arbiter() {
+        ExtBuilder::build_with_disputed_order().execute_with(|| {
+            assert_ok!(Orders::remove_dispute_arbiter(Origin::root(), ACCOUNT3));
+
+            assert_noop!(
+                _resolve_dispute(None, 1, OrderState::Refunded),
+                OrdersError::<TestRuntime>::NotADisputeArbiter
+            );
+        });
+    }
+
+    #[test]
+    fn resolve_dispute_should_refund_buyer_when_settled_by_seller() {
+        ExtBuilder::build_with_disputed_order().execute_with(|| {
+            assert_ok!(_resolve_dispute(Some(Origin::signed(ACCOUNT1)), 1, OrderState::Refunded));
+
+            assert_eq!(Orders::order_by_id(1).unwrap().order_state, OrderState::Refunded);
+            assert_eq!(Orders::dispute_by_order_id(1).unwrap().ruling, Some(OrderState::Refunded));
+
+            assert_eq!(Balances::free_balance(ACCOUNT2), 1_000);
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), 0);
+            assert_eq!(Balances::free_balance(ACCOUNT1), 100);
+            assert_eq!(Balances::reserved_balance(ACCOUNT1), 0);
+        });
+    }
+
+    #[test]
+    fn resolve_dispute_should_work_with_resolve_disputes_permission() {
+        ExtBuilder::build_with_disputed_order().execute_with(|| {
+            assert_ok!(Orders::remove_dispute_arbiter(Origin::root(), ACCOUNT3));
+            assert_ok!(_create_role(None, None, None, None, Some(vec![SP::ResolveDisputes]))); // RoleId 1
+            assert_ok!(_grant_role(None, None, Some(vec![User::Account(ACCOUNT3)])));
+
+            assert_ok!(_resolve_dispute(None, 1, OrderState::Refunded));
+            assert_eq!(Orders::order_by_id(1).unwrap().order_state, OrderState::Refunded);
+        });
+    }
+
+    #[test]
+    fn resolve_dispute_should_fail_when_seller_rules_other_than_refund() {
+        ExtBuilder::build_with_disputed_order().execute_with(|| {
+            assert_noop!(
+                _resolve_dispute(Some(Origin::signed(ACCOUNT1)), 1, OrderState::SlashedBuyer),
+                OrdersError::<TestRuntime>::SellerCanOnlyRefundDispute
+            );
+        });
+    }
+
+    #[test]
+    fn resolve_dispute_should_fail_when_arbiter_is_involved_in_order() {
+        ExtBuilder::build_with_disputed_order().execute_with(|| {
+            assert_ok!(_add_dispute_arbiter(ACCOUNT1));
+
+            assert_noop!(
+                _resolve_dispute(Some(Origin::signed(ACCOUNT1)), 1, OrderState::SlashedBuyer),
+                OrdersError::<TestRuntime>::ArbiterIsInvolvedInThisOrder
+            );
+        });
+    }
+
+    #[test]
+    fn add_dispute_arbiter_should_fail_when_already_added() {
+        ExtBuilder::build_with_disputed_order().execute_with(|| {
+            assert_eq!(Orders::dispute_arbiters(), vec![ACCOUNT3]);
+
+            assert_noop!(
+                _add_dispute_arbiter(ACCOUNT3),
+                OrdersError::<TestRuntime>::DisputeArbiterAlreadyAdded
+            );
+            assert_noop!(
+                Orders::remove_dispute_arbiter(Origin::root(), ACCOUNT2),
+                OrdersError::<TestRuntime>::DisputeArbiterNotFound
+            );
+        });
+    }
+
+    // Order pricing tests
+
+    fn _update_product_price(price: Option<ProductPrice<u64>>) -> DispatchResult {
+        let mut update = ProductUpdate::default();
+        update.price = Some(price);
+        _update_product(None, None, Some(update))
+    }
+
+    #[test]
+    fn create_order_should_take_prices_from_products() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_order(ACCOUNT2, vec![(POST1, 3)], 300)); // OrderId 1
+
+            let order = Orders::order_by_id(1).unwrap();
+            assert_eq!(order.order_total, 300);
+            assert_eq!(order.items[0].unit_price, 100);
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), 300);
+        });
+    }
+
+    #[test]
+    fn create_order_should_fail_when_price_exceeds_max_total() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+
+            // The seller raises the price after the buyer has seen it
+            assert_ok!(_update_product_price(Some(self::product_price(150))));
+
+            assert_eq!(
+                _create_order(ACCOUNT2, vec![(POST1, 1)], 100),
+                Err(OrdersError::<TestRuntime>::OrderTotalExceedsMaxTotal.into())
+            );
+            assert!(Orders::order_by_id(1).is_none());
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), 0);
+        });
+    }
+
+    #[test]
+    fn create_order_should_fail_when_product_has_no_price() {
+        ExtBuilder::build_with_product().execute_with(|| {
+            assert_eq!(
+                _create_default_order(POST1),
+                Err(OrdersError::<TestRuntime>::ProductHasNoPrice.into())
+            );
+        });
+    }
+
+    // Order deadline tests
+
+    #[test]
+    fn new_order_should_be_auto_refused_after_accept_timeout() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+
+            let deadline = 1 + AcceptOrderTimeout::get();
+            assert_eq!(Orders::order_by_id(1).unwrap().deadline, Some(deadline));
+
+            Orders::on_initialize(deadline - 1);
+            assert_eq!(Orders::order_by_id(1).unwrap().order_state, OrderState::New);
+
+            Orders::on_initialize(deadline);
+
+            let order = Orders::order_by_id(1).unwrap();
+            assert_eq!(order.order_state, OrderState::Refused);
+            assert!(order.deadline.is_none());
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), 0);
+        });
+    }
+
+    #[test]
+    fn shipped_order_should_be_auto_completed_after_confirm_timeout() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+            assert_ok!(_update_order_state(None, 1, OrderState::Accepted));
+            assert_ok!(_update_order_state(None, 1, OrderState::Shipped));
+
+            let deadline = 1 + ConfirmOrderTimeout::get();
+            Orders::on_initialize(deadline);
+
+            let order = Orders::order_by_id(1).unwrap();
+            assert_eq!(order.order_state, OrderState::Complete);
+            assert!(order.deadline.is_none());
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), 0);
+            assert_eq!(Balances::free_balance(Storefronts::storefront_account(SPACE1)), 98);
+        });
+    }
+
+    #[test]
+    fn failed_auto_complete_should_be_rescheduled() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+            assert_ok!(_update_order_state(None, 1, OrderState::Accepted));
+            assert_ok!(_update_order_state(None, 1, OrderState::Shipped));
+
+            // The buyer's funds are gone, so the seller cannot be paid
+            let _ = Balances::slash(&ACCOUNT2, 1_000);
+
+            let deadline = 1 + ConfirmOrderTimeout::get();
+            System::set_block_number(deadline);
+            Orders::on_initialize(deadline);
+
+            let next_deadline = deadline + ConfirmOrderTimeout::get();
+            let order = Orders::order_by_id(1).unwrap();
+            assert_eq!(order.order_state, OrderState::Shipped);
+            assert_eq!(order.deadline, Some(next_deadline));
+            assert_eq!(Orders::order_ids_by_deadline(next_deadline), vec![1]);
+        });
+    }
+
+    #[test]
+    fn expired_orders_over_the_limit_should_be_carried_over() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 2
+            assert_ok!(_create_default_order(POST1)); // OrderId 3
+
+            // The limit of 3 covers the deadline block itself and two of its orders
+            let deadline = 1 + AcceptOrderTimeout::get();
+            Orders::on_initialize(deadline);
+
+            assert_eq!(Orders::order_by_id(1).unwrap().order_state, OrderState::Refused);
+            assert_eq!(Orders::order_by_id(2).unwrap().order_state, OrderState::Refused);
+            assert_eq!(Orders::order_by_id(3).unwrap().order_state, OrderState::New);
+            assert_eq!(Orders::order_ids_by_deadline(deadline), vec![3]);
+            assert_eq!(Orders::next_deadline_to_process(), Some(deadline));
+
+            Orders::on_initialize(deadline + 1);
+
+            assert_eq!(Orders::order_by_id(3).unwrap().order_state, OrderState::Refused);
+            assert!(Orders::order_ids_by_deadline(deadline).is_empty());
+            assert_eq!(Orders::next_deadline_to_process(), Some(deadline + 2));
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), 0);
+        });
+    }
+
+    // Order escrow tests
+
+    #[test]
+    fn create_order_should_reserve_buyer_escrow_from_storefront_settings() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_set_default_order_escrows());
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+
+            let order = Orders::order_by_id(1).unwrap();
+            assert_eq!(order.buyer_escrow, 10);
+            assert_eq!(order.seller_escrow, 20);
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), 110);
+        });
+    }
+
+    #[test]
+    fn accept_order_should_reserve_seller_escrow() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            let _ = Balances::deposit_creating(&ACCOUNT1, 100);
+            assert_ok!(_set_default_order_escrows());
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+
+            assert_ok!(_update_order_state(None, 1, OrderState::Accepted));
+
+            assert_eq!(Balances::reserved_balance(ACCOUNT1), 20);
+            assert_eq!(Balances::free_balance(ACCOUNT1), 80);
+        });
+    }
+
+    #[test]
+    fn accept_order_should_fail_when_seller_cannot_afford_escrow() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_set_default_order_escrows());
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+
+            assert_noop!(
+                _update_order_state(None, 1, OrderState::Accepted),
+                "seller can't afford to lock the amount requested"
+            );
+        });
+    }
+
+    #[test]
+    fn refuse_order_should_release_buyer_escrow() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_set_default_order_escrows());
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+
+            assert_ok!(_update_order_state(None, 1, OrderState::Refused));
+
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), 0);
+            assert_eq!(Balances::free_balance(ACCOUNT2), 1_000);
+        });
+    }
+
+    #[test]
+    fn complete_order_should_release_both_escrows() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            let _ = Balances::deposit_creating(&ACCOUNT1, 100);
+            assert_ok!(_set_default_order_escrows());
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+
+            assert_ok!(_update_order_state(None, 1, OrderState::Accepted));
+            assert_ok!(_update_order_state(None, 1, OrderState::Shipped));
+            assert_ok!(_update_order_state(Some(Origin::signed(ACCOUNT2)), 1, OrderState::Complete));
+
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), 0);
+            assert_eq!(Balances::free_balance(ACCOUNT2), 900);
+            assert_eq!(Balances::reserved_balance(ACCOUNT1), 0);
+            assert_eq!(Balances::free_balance(ACCOUNT1), 100);
+        });
+    }
+
+    // Marketplace fee tests
+
+    /// Creates OrderId 1 of ProductId 1 priced 100 by ACCOUNT2 and completes it.
+    fn _create_and_complete_order() -> DispatchResult {
+        _create_priced_product(None, None, self::product_price(100))?;
+        _create_default_order(POST1)?;
+
+        _update_order_state(None, 1, OrderState::Accepted)?;
+        _update_order_state(None, 1, OrderState::Shipped)?;
+        _update_order_state(Some(Origin::signed(ACCOUNT2)), 1, OrderState::Complete)
+    }
+
+    #[test]
+    fn complete_order_should_send_marketplace_fee_to_treasury() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_eq!(Orders::marketplace_fee(SPACE1), MarketplaceFee::get());
+
+            assert_ok!(_create_and_complete_order());
+
+            assert_eq!(Balances::free_balance(Utils::treasury_account()), 2);
+            assert_eq!(Balances::free_balance(Storefronts::storefront_account(SPACE1)), 98);
+        });
+    }
+
+    #[test]
+    fn complete_order_should_use_marketplace_fee_override() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(Orders::set_marketplace_fee_override(Origin::root(), SPACE1, Some(Perbill::from_percent(10))));
+            assert_eq!(Orders::marketplace_fee(SPACE1), Perbill::from_percent(10));
+
+            assert_ok!(_create_and_complete_order());
+
+            assert_eq!(Balances::free_balance(Utils::treasury_account()), 10);
+            assert_eq!(Balances::free_balance(Storefronts::storefront_account(SPACE1)), 90);
+        });
+    }
+
+    #[test]
+    fn complete_order_should_charge_no_fee_with_zero_override() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(Orders::set_marketplace_fee_override(Origin::root(), SPACE1, Some(Perbill::zero())));
+
+            assert_ok!(_create_and_complete_order());
+
+            assert_eq!(Balances::free_balance(Utils::treasury_account()), 0);
+            assert_eq!(Balances::free_balance(Storefronts::storefront_account(SPACE1)), 100);
+        });
+    }
+
+    #[test]
+    fn complete_order_should_send_seller_share_below_existential_deposit_to_treasury() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            let _ = Balances::deposit_creating(&Utils::treasury_account(), 1);
+            set_existential_deposit(200);
+
+            // The seller share of 98 cannot create a storefront account
+            assert_ok!(_create_and_complete_order());
+
+            assert_eq!(Balances::total_balance(&Storefronts::storefront_account(SPACE1)), 0);
+            assert_eq!(Balances::free_balance(Utils::treasury_account()), 101);
+            assert_eq!(Balances::free_balance(ACCOUNT2), 900);
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), 0);
+            assert_eq!(Orders::order_by_id(1).unwrap().order_state, OrderState::Complete);
+        });
+    }
+
+    #[test]
+    fn complete_order_should_pay_seller_share_below_existential_deposit_to_existing_storefront_account() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            let _ = Balances::deposit_creating(&Storefronts::storefront_account(SPACE1), 200);
+            set_existential_deposit(200);
+
+            assert_ok!(_create_and_complete_order());
+
+            assert_eq!(Balances::free_balance(Storefronts::storefront_account(SPACE1)), 298);
+        });
+    }
+
+    #[test]
+    fn set_marketplace_fee_override_should_reset_to_default_fee() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(Orders::set_marketplace_fee_override(Origin::root(), SPACE1, Some(Perbill::from_percent(10))));
+            assert_ok!(Orders::set_marketplace_fee_override(Origin::root(), SPACE1, None));
+
+            assert!(Orders::marketplace_fee_override_by_storefront_id(SPACE1).is_none());
+            assert_eq!(Orders::marketplace_fee(SPACE1), MarketplaceFee::get());
+        });
+    }
+
+    #[test]
+    fn set_marketplace_fee_override_should_fail_when_not_root() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_noop!(
+                Orders::set_marketplace_fee_override(Origin::signed(ACCOUNT1), SPACE1, Some(Perbill::zero())),
+                BadOrigin
+            );
+        });
+    }
+
+    #[test]
+    fn set_marketplace_fee_override_should_fail_with_storefront_not_found() {
+        ExtBuilder::build().execute_with(|| {
+            assert_noop!(
+                Orders::set_marketplace_fee_override(Origin::root(), SPACE1, Some(Perbill::zero())),
+                StorefrontsError::<TestRuntime>::StorefrontNotFound
+            );
+        });
+    }
+
+    // Cart order tests
+
+    fn _restock_product(product_id: ProductId, update: StockUpdate) -> DispatchResult {
+        Products::restock_product(Origin::signed(ACCOUNT1), product_id, update)
+    }
+
+    #[test]
+    fn create_order_should_work_with_many_items() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_priced_product(None, None, self::product_price(50))); // ProductId 2
+            assert_ok!(_restock_product(POST2, StockUpdate::Add(1)));
+
+            assert_ok!(_create_order(ACCOUNT2, vec![(POST1, 2), (POST2, 1)], 250)); // OrderId 1
+
+            let order = Orders::order_by_id(1).unwrap();
+            assert_eq!(order.items.len(), 2);
+            assert_eq!(order.order_total, 250);
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), 250);
+
+            assert_eq!(Products::product_by_id(POST2).unwrap().stock, Some(0));
+            assert_eq!(Orders::order_ids_by_product_id(POST1), vec![1]);
+            assert_eq!(Orders::order_ids_by_product_id(POST2), vec![1]);
+        });
+    }
+
+    #[test]
+    fn create_order_should_fail_with_duplicate_product() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+
+            assert_eq!(
+                _create_order(ACCOUNT2, vec![(POST1, 1), (POST1, 1)], 1_000),
+                Err(OrdersError::<TestRuntime>::DuplicateProductInOrder.into())
+            );
+        });
+    }
+
+    #[test]
+    fn create_order_should_fail_with_insufficient_funds_and_keep_stock() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_priced_product(None, None, self::product_price(600))); // ProductId 2
+            assert_ok!(_restock_product(POST1, StockUpdate::Add(5)));
+
+            // The buyer has 1000, but the order total is 1300
+            assert_eq!(
+                _create_order(ACCOUNT2, vec![(POST1, 1), (POST2, 2)], 2_000),
+                Err(OrdersError::<TestRuntime>::InsufficientFunds.into())
+            );
+
+            assert!(Orders::order_by_id(1).is_none());
+            assert_eq!(Products::product_by_id(POST1).unwrap().stock, Some(5));
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), 0);
+        });
+    }
+
+    #[test]
+    fn create_order_should_fail_when_any_item_is_out_of_stock() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_priced_product(None, None, self::product_price(50))); // ProductId 2
+            assert_ok!(_restock_product(POST1, StockUpdate::Add(5)));
+            assert_ok!(_restock_product(POST2, StockUpdate::Add(1)));
+
+            assert_eq!(
+                _create_order(ACCOUNT2, vec![(POST1, 1), (POST2, 2)], 1_000),
+                Err(ProductsError::<TestRuntime>::InsufficientProductStock.into())
+            );
+
+            assert_eq!(Products::product_by_id(POST1).unwrap().stock, Some(5));
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), 0);
+        });
+    }
+
+    // Product stock tests
+
+    #[test]
+    fn create_order_should_take_product_stock() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_restock_product(POST1, StockUpdate::Add(5)));
+
+            assert_ok!(_create_order(ACCOUNT2, vec![(POST1, 2)], 200)); // OrderId 1
+
+            assert_eq!(Products::product_by_id(POST1).unwrap().stock, Some(3));
+        });
+    }
+
+    #[test]
+    fn create_order_should_fail_when_product_is_sold_out() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_restock_product(POST1, StockUpdate::Add(1)));
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+
+            assert_eq!(Products::product_by_id(POST1).unwrap().stock, Some(0));
+            assert_eq!(
+                _create_order_by(ACCOUNT3, POST1),
+                Err(ProductsError::<TestRuntime>::InsufficientProductStock.into())
+            );
+        });
+    }
+
+    #[test]
+    fn refuse_order_should_restore_product_stock() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_restock_product(POST1, StockUpdate::Add(5)));
+            assert_ok!(_create_order(ACCOUNT2, vec![(POST1, 2)], 200)); // OrderId 1
+
+            assert_ok!(_update_order_state(None, 1, OrderState::Refused));
+
+            assert_eq!(Products::product_by_id(POST1).unwrap().stock, Some(5));
+        });
+    }
+
+    #[test]
+    fn restock_product_should_keep_items_of_open_orders() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_restock_product(POST1, StockUpdate::Add(5)));
+            assert_ok!(_create_order(ACCOUNT2, vec![(POST1, 2)], 200)); // OrderId 1
+
+            // The seller adds items while the order is open
+            assert_ok!(_restock_product(POST1, StockUpdate::Add(2)));
+            assert_eq!(Products::product_by_id(POST1).unwrap().stock, Some(5));
+
+            // Refused items are added to the current stock, not to the one at the order time
+            assert_ok!(_update_order_state(None, 1, OrderState::Refused));
+            assert_eq!(Products::product_by_id(POST1).unwrap().stock, Some(7));
+        });
+    }
+
+    #[test]
+    fn restock_product_should_remove_items_and_stop_tracking_stock() {
+        ExtBuilder::build_with_product().execute_with(|| {
+            assert_ok!(_restock_product(POST1, StockUpdate::Add(5)));
+
+            assert_ok!(_restock_product(POST1, StockUpdate::Remove(3)));
+            assert_eq!(Products::product_by_id(POST1).unwrap().stock, Some(2));
+
+            assert_noop!(
+                _restock_product(POST1, StockUpdate::Remove(3)),
+                ProductsError::<TestRuntime>::InsufficientProductStock
+            );
+
+            assert_ok!(_restock_product(POST1, StockUpdate::Untrack));
+            assert!(Products::product_by_id(POST1).unwrap().stock.is_none());
+
+            assert_noop!(
+                _restock_product(POST1, StockUpdate::Untrack),
+                ProductsError::<TestRuntime>::NoUpdatesForProductStock
+            );
+        });
+    }
+
+    // Order message tests
+
+    fn encrypted_message(ciphertext_len: usize) -> EncryptedMessage {
+        EncryptedMessage {
+            public_key: [1; 32],
+            nonce: [2; 24],
+            ciphertext: vec![3; ciphertext_len],
+        }
+    }
+
+    fn _send_order_message(origin: Option<Origin>, order_id: OrderId, message: EncryptedMessage) -> DispatchResult {
+        Orders::send_order_message(origin.unwrap_or_else(|| Origin::signed(ACCOUNT2)), order_id, message)
+    }
+
+    #[test]
+    fn send_order_message_should_set_message_of_the_sender_side() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1 by ACCOUNT2
+
+            assert_ok!(_send_order_message(None, 1, self::encrypted_message(32)));
+            assert_ok!(_send_order_message(Some(Origin::signed(ACCOUNT1)), 1, self::encrypted_message(64)));
+
+            let order = Orders::order_by_id(1).unwrap();
+            assert_eq!(order.buyer_message, Some(self::encrypted_message(32)));
+            assert_eq!(order.seller_message, Some(self::encrypted_message(64)));
+        });
+    }
+
+    #[test]
+    fn send_order_message_should_work_with_view_order_messages_permission() {
+        ExtBuilder::build_with_a_few_roles_granted_to_account2(vec![SP::ViewOrderMessages]).execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_order_by(ACCOUNT3, POST1)); // OrderId 1
+
+            // A staff member writes on behalf of the seller
+            assert_ok!(_send_order_message(None, 1, self::encrypted_message(32)));
+
+            let order = Orders::order_by_id(1).unwrap();
+            assert!(order.buyer_message.is_none());
+            assert_eq!(order.seller_message, Some(self::encrypted_message(32)));
+        });
+    }
+
+    #[test]
+    fn send_order_message_should_fail_when_not_involved_in_order() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_order_by(ACCOUNT3, POST1)); // OrderId 1
+
+            assert_noop!(
+                _send_order_message(None, 1, self::encrypted_message(32)),
+                OrdersError::<TestRuntime>::NotInvolvedInThisOrder
+            );
+        });
+    }
+
+    #[test]
+    fn send_order_message_should_fail_without_payload_after_tag() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+
+            assert_noop!(
+                _send_order_message(None, 1, self::encrypted_message(ENCRYPTED_MESSAGE_TAG_LEN)),
+                OrdersError::<TestRuntime>::EncryptedMessageTooShort
+            );
+            assert_ok!(_send_order_message(None, 1, self::encrypted_message(ENCRYPTED_MESSAGE_TAG_LEN + 1)));
+        });
+    }
+
+    #[test]
+    fn send_order_message_should_fail_when_message_is_too_long() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+
+            let max_len = MaxOrderMessageLength::get() as usize;
+            assert_noop!(
+                _send_order_message(None, 1, self::encrypted_message(max_len + 1)),
+                OrdersError::<TestRuntime>::EncryptedMessageTooLong
+            );
+            assert_ok!(_send_order_message(None, 1, self::encrypted_message(max_len)));
+        });
+    }
+
+    #[test]
+    fn update_order_should_fail_with_invalid_message() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+
+            assert_noop!(
+                Orders::update_order(
+                    Origin::signed(ACCOUNT1),
+                    1,
+                    OrderUpdate {
+                        content: None,
+                        order_state: OrderState::Accepted,
+                        message: Some(self::encrypted_message(0)),
+                    }
+                ),
+                OrdersError::<TestRuntime>::EncryptedMessageTooShort
+            );
+        });
+    }
+
+    // Order cancellation tests
+
+    #[test]
+    fn cancel_order_should_work_for_new_order_by_buyer() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_restock_product(POST1, StockUpdate::Add(5)));
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+
+            assert_ok!(_cancel_order(Some(Origin::signed(ACCOUNT2)), 1));
+
+            let order = Orders::order_by_id(1).unwrap();
+            assert_eq!(order.order_state, OrderState::Cancelled);
+            assert!(order.deadline.is_none());
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), 0);
+            assert_eq!(Products::product_by_id(POST1).unwrap().stock, Some(5));
+        });
+    }
+
+    #[test]
+    fn cancel_order_should_fail_for_new_order_by_seller() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+
+            assert_noop!(
+                _cancel_order(None, 1),
+                OrdersError::<TestRuntime>::OnlyBuyerCanCancelNotAcceptedOrder
+            );
+        });
+    }
+
+    #[test]
+    fn cancel_order_should_need_both_parties_for_accepted_order() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            let _ = Balances::deposit_creating(&ACCOUNT1, 100);
+            assert_ok!(_set_default_order_escrows());
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_restock_product(POST1, StockUpdate::Add(5)));
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+            assert_ok!(_update_order_state(None, 1, OrderState::Accepted));
+
+            assert_ok!(_cancel_order(Some(Origin::signed(ACCOUNT2)), 1));
+            assert_eq!(Orders::order_by_id(1).unwrap().order_state, OrderState::Accepted);
+            assert_eq!(Orders::cancellation_requested_by(1), Some(ACCOUNT2));
+            assert_noop!(
+                _cancel_order(Some(Origin::signed(ACCOUNT2)), 1),
+                OrdersError::<TestRuntime>::CancellationAlreadyRequested
+            );
+
+            assert_ok!(_cancel_order(None, 1));
+
+            assert_eq!(Orders::order_by_id(1).unwrap().order_state, OrderState::Cancelled);
+            assert!(Orders::cancellation_requested_by(1).is_none());
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), 0);
+            assert_eq!(Balances::reserved_balance(ACCOUNT1), 0);
+            assert_eq!(Products::product_by_id(POST1).unwrap().stock, Some(5));
+        });
+    }
+
+    #[test]
+    fn cancel_order_should_not_restore_stock_of_shipped_order() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100))); // ProductId 1
+            assert_ok!(_restock_product(POST1, StockUpdate::Add(5)));
+            assert_ok!(_create_default_order(POST1)); // OrderId 1
+            assert_ok!(_update_order_state(None, 1, OrderState::Accepted));
+            assert_ok!(_update_order_state(None, 1, OrderState::Shipped));
+
+            assert_ok!(_cancel_order(None, 1));
+            assert_ok!(_cancel_order(Some(Origin::signed(ACCOUNT2)), 1));
+
+            let order = Orders::order_by_id(1).unwrap();
+            assert_eq!(order.order_state, OrderState::Cancelled);
+            assert!(order.deadline.is_none());
+            assert_eq!(Balances::reserved_balance(ACCOUNT2), 0);
+            assert_eq!(Products::product_by_id(POST1).unwrap().stock, Some(4));
+        });
+    }
+
+    #[test]
+    fn cancel_order_should_fail_for_complete_order() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_and_complete_order());
+
+            assert_noop!(
+                _cancel_order(Some(Origin::signed(ACCOUNT2)), 1),
+                OrdersError::<TestRuntime>::OrderCannotBeCancelled
+            );
+        });
+    }
+
+    // Order scoring tests
+
+    fn seller_reputation() -> u32 {
+        Profiles::social_account_by_id(ACCOUNT1).map_or(1, |account| account.reputation)
+    }
+
+    fn storefront_score() -> i32 {
+        Storefronts::storefront_by_id(SPACE1).unwrap().score
+    }
+
+    #[test]
+    fn complete_order_should_increase_seller_reputation_and_storefront_score() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_and_complete_order());
+
+            let score_diff = CompleteOrderActionWeight::get() as i32;
+            assert_eq!(self::storefront_score(), score_diff);
+            assert_eq!(self::seller_reputation(), 1 + score_diff as u32);
+
+            // Every state change is recorded in the order history
+            let history = OrderHistory::edit_history(1);
+            assert_eq!(history.len(), 3);
+            assert_eq!(history[2].old_data.order_state, OrderState::Shipped);
+        });
+    }
+
+    #[test]
+    fn refuse_order_should_decrease_seller_reputation_and_storefront_score() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_and_complete_order()); // OrderId 1
+            assert_ok!(_create_default_order(POST1)); // OrderId 2
+
+            assert_ok!(_update_order_state(None, 2, OrderState::Refused));
+
+            let score_diff = (CompleteOrderActionWeight::get() + RefuseOrderActionWeight::get()) as i32;
+            assert_eq!(self::storefront_score(), score_diff);
+            assert_eq!(self::seller_reputation(), 1 + score_diff as u32);
+        });
+    }
+
+    #[test]
+    fn lost_dispute_should_decrease_storefront_score() {
+        ExtBuilder::build_with_disputed_order().execute_with(|| {
+            assert_ok!(_resolve_dispute(None, 1, OrderState::SlashedSeller));
+
+            assert_eq!(self::storefront_score(), LoseDisputeActionWeight::get() as i32);
+            // Reputation never goes below 1
+            assert_eq!(self::seller_reputation(), 1);
+        });
+    }
+
+    #[test]
+    fn won_dispute_should_not_change_storefront_score() {
+        ExtBuilder::build_with_disputed_order().execute_with(|| {
+            assert_ok!(_resolve_dispute(None, 1, OrderState::SlashedBuyer));
+
+            assert_eq!(self::storefront_score(), 0);
+            assert_eq!(self::seller_reputation(), 1);
+        });
+    }
+
+    // Review tests
+
+    fn review_content_ipfs() -> Content {
+        Content::IPFS(b"QmYA2fn8cMbVWo4v95RwcwJVyQsNtnEwHerfWR8UNtEwoE".to_vec())
+    }
+
+    /// Reviews a product of OrderId 1 by ACCOUNT2 by default.
+    fn _create_review(origin: Option<Origin>, product_id: Option<ProductId>, rating: u8) -> DispatchResult {
+        Reviews::create_review(
+            origin.unwrap_or_else(|| Origin::signed(ACCOUNT2)),
+            1,
+            product_id.unwrap_or(POST1),
+            rating,
+            self::review_content_ipfs(),
+        )
+    }
+
+    fn _reply_to_review(origin: Option<Origin>, review_id: Option<ReviewId>) -> DispatchResult {
+        Reviews::reply_to_review(
+            origin.unwrap_or_else(|| Origin::signed(ACCOUNT1)),
+            review_id.unwrap_or(1),
+            self::reply_content_ipfs(),
+        )
+    }
+
+    #[test]
+    fn create_review_should_work() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_and_complete_order());
+            assert_ok!(_create_review(None, None, 4));
+
+            let review = Reviews::review_by_id(1).unwrap();
+            assert_eq!(review.created.account, ACCOUNT2);
+            assert_eq!(review.storefront_id, SPACE1);
+            assert_eq!(review.rating, 4);
+            assert_eq!(Reviews::review_ids_by_product_id(POST1), vec![1]);
+            assert_eq!(Reviews::review_id_by_order_and_product((1, POST1)), Some(1));
+
+            // Ratings are aggregated on the product and its storefront
+            let product = Products::product_by_id(POST1).unwrap();
+            assert_eq!((product.reviews_count, product.ratings_sum), (1, 4));
+
+            let storefront = Storefronts::storefront_by_id(SPACE1).unwrap();
+            assert_eq!((storefront.reviews_count, storefront.ratings_sum), (1, 4));
+        });
+    }
+
+    #[test]
+    fn create_review_should_fail_when_order_is_not_complete() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_priced_product(None, None, self::product_price(100)));
+            assert_ok!(_create_default_order(POST1));
+
+            assert_noop!(_create_review(None, None, 4), ReviewsError::<TestRuntime>::OrderIsNotComplete);
+        });
+    }
+
+    #[test]
+    fn create_review_should_fail_when_not_a_buyer() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_and_complete_order());
+
+            assert_noop!(
+                _create_review(Some(Origin::signed(ACCOUNT3)), None, 4),
+                ReviewsError::<TestRuntime>::NotAnOrderBuyer
+            );
+        });
+    }
+
+    #[test]
+    fn create_review_should_fail_when_product_is_not_in_order() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_and_complete_order());
+
+            assert_noop!(
+                _create_review(None, Some(POST2), 4),
+                ReviewsError::<TestRuntime>::ProductNotInOrder
+            );
+        });
+    }
+
+    #[test]
+    fn create_review_should_fail_when_product_already_reviewed_in_order() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_and_complete_order());
+            assert_ok!(_create_review(None, None, 4));
+
+            assert_noop!(_create_review(None, None, 5), ReviewsError::<TestRuntime>::ReviewAlreadyExists);
+        });
+    }
+
+    #[test]
+    fn create_review_should_fail_with_invalid_rating() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_and_complete_order());
+
+            assert_noop!(_create_review(None, None, 0), ReviewsError::<TestRuntime>::InvalidRating);
+            assert_noop!(_create_review(None, None, 6), ReviewsError::<TestRuntime>::InvalidRating);
+        });
+    }
+
+    #[test]
+    fn reply_to_review_should_work_for_storefront_owner() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_and_complete_order());
+            assert_ok!(_create_review(None, None, 2));
+            assert_ok!(_reply_to_review(None, None));
+
+            let reply = Reviews::review_by_id(1).unwrap().reply.unwrap();
+            assert_eq!(reply.created.account, ACCOUNT1);
+            assert_eq!(reply.content, self::reply_content_ipfs());
+        });
+    }
+
+    #[test]
+    fn reply_to_review_should_work_with_represent_storefront_externally_permission() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_and_complete_order());
+            assert_ok!(_create_review(None, None, 2));
+
+            assert_ok!(_create_role(None, None, None, None, Some(vec![SP::RepresentStorefrontExternally])));
+            assert_ok!(_grant_role(None, None, Some(vec![User::Account(ACCOUNT3)])));
+
+            assert_ok!(_reply_to_review(Some(Origin::signed(ACCOUNT3)), None));
+            assert_eq!(Reviews::review_by_id(1).unwrap().reply.unwrap().created.account, ACCOUNT3);
+        });
+    }
+
+    #[test]
+    fn reply_to_review_should_fail_with_no_permission() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_and_complete_order());
+            assert_ok!(_create_review(None, None, 2));
+
+            assert_noop!(
+                _reply_to_review(Some(Origin::signed(ACCOUNT3)), None),
+                ReviewsError::<TestRuntime>::NoPermissionToReplyToReviews
+            );
+        });
+    }
+
+    #[test]
+    fn reply_to_review_should_fail_when_already_replied() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_and_complete_order());
+            assert_ok!(_create_review(None, None, 2));
+            assert_ok!(_reply_to_review(None, None));
+
+            assert_noop!(_reply_to_review(None, None), ReviewsError::<TestRuntime>::ReplyAlreadyExists);
+        });
+    }
+
+    #[test]
+    fn delete_product_should_remove_its_reviews_but_keep_storefront_rating() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_and_complete_order());
+            assert_ok!(_create_review(None, None, 1));
+
+            assert_ok!(_delete_product(None, None));
+
+            assert!(Reviews::review_by_id(1).is_none());
+            assert!(Reviews::review_ids_by_product_id(POST1).is_empty());
+            assert!(Reviews::review_id_by_order_and_product((1, POST1)).is_none());
+
+            let storefront = Storefronts::storefront_by_id(SPACE1).unwrap();
+            assert_eq!((storefront.reviews_count, storefront.ratings_sum), (1, 1));
+        });
+    }
+
+    // Storefront multi-ownership tests
+
+    fn _create_default_storefront_owners() -> DispatchResult {
+        StorefrontMultiOwnership::create_storefront_owners(
+            Origin::signed(ACCOUNT1),
+            SPACE1,
+            vec![ACCOUNT1, ACCOUNT2],
+            2,
+        )
+    }
+
+    fn hide_storefront_call() -> Box<Call> {
+        Box::new(Call::Storefronts(pallet_storefronts::Call::update_storefront(
+            SPACE1,
+            self::storefront_update(None, None, None, Some(true), None),
+        )))
+    }
+
+    fn _propose_change(origin: Option<Origin>, call: Option<Box<Call>>) -> DispatchResult {
+        StorefrontMultiOwnership::propose_change(
+            origin.unwrap_or_else(|| Origin::signed(ACCOUNT1)),
+            SPACE1,
+            vec![],
+            vec![],
+            None,
+            Some(call.unwrap_or_else(self::hide_storefront_call)),
+            vec![],
+        )
+    }
+
+    fn _confirm_change(origin: Option<Origin>, max_weight: Option<Weight>) -> DispatchResult {
+        StorefrontMultiOwnership::confirm_change(
+            origin.unwrap_or_else(|| Origin::signed(ACCOUNT2)),
+            SPACE1,
+            CHANGE1,
+            max_weight.unwrap_or_else(|| self::hide_storefront_call().get_dispatch_info().weight),
+        )
+    }
+
+    #[test]
+    fn create_storefront_owners_should_transfer_storefront_to_storefront_account() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_default_storefront_owners());
+
+            let storefront_account = Storefronts::storefront_account(SPACE1);
+            assert_eq!(Storefronts::storefront_by_id(SPACE1).unwrap().owner, storefront_account);
+            assert_eq!(Storefronts::storefront_ids_by_owner(storefront_account), vec![SPACE1]);
+            assert!(Storefronts::storefront_ids_by_owner(ACCOUNT1).is_empty());
+        });
+    }
+
+    #[test]
+    fn propose_change_should_work() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_default_storefront_owners());
+            assert_ok!(_propose_change(None, None));
+
+            let change = StorefrontMultiOwnership::change_by_id(CHANGE1).unwrap();
+            assert_eq!(change.confirmed_by, vec![ACCOUNT1]);
+            assert_eq!(StorefrontMultiOwnership::pending_change_id_by_storefront_id(SPACE1), Some(CHANGE1));
+
+            // The call is not dispatched until the change is confirmed
+            assert!(!Storefronts::storefront_by_id(SPACE1).unwrap().hidden);
+        });
+    }
+
+    #[test]
+    fn propose_change_should_fail_when_call_is_not_allowed() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_default_storefront_owners());
+
+            let transfer_call = Box::new(Call::Balances(pallet_balances::Call::transfer(ACCOUNT3, 10)));
+            assert_noop!(
+                _propose_change(None, Some(transfer_call)),
+                StorefrontMultiOwnershipError::<TestRuntime>::CallIsNotAllowed
+            );
+        });
+    }
+
+    #[test]
+    fn confirm_change_should_dispatch_call_by_storefront_account() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_default_storefront_owners());
+            assert_ok!(_propose_change(None, None));
+            assert_ok!(_confirm_change(None, None));
+
+            assert!(Storefronts::storefront_by_id(SPACE1).unwrap().hidden);
+            assert!(StorefrontMultiOwnership::pending_change_id_by_storefront_id(SPACE1).is_none());
+            assert_eq!(StorefrontMultiOwnership::executed_change_ids_by_storefront_id(SPACE1), vec![CHANGE1]);
+            assert_eq!(StorefrontMultiOwnership::change_by_id(CHANGE1).unwrap().confirmed_by, vec![ACCOUNT1, ACCOUNT2]);
+        });
+    }
+
+    #[test]
+    fn confirm_change_should_fail_when_max_weight_is_too_low() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_default_storefront_owners());
+            assert_ok!(_propose_change(None, None));
+
+            assert_noop!(
+                _confirm_change(None, Some(0)),
+                StorefrontMultiOwnershipError::<TestRuntime>::MaxWeightTooLow
+            );
+        });
+    }
+
+    #[test]
+    fn confirm_change_should_fail_when_not_a_storefront_owner() {
+        ExtBuilder::build_with_storefront().execute_with(|| {
+            assert_ok!(_create_default_storefront_owners());
+            assert_ok!(_propose_change(None, None));
+
+            assert_noop!(
+                _confirm_change(Some(Origin::signed(ACCOUNT3)), None),
+                StorefrontMultiOwnershipError::<TestRuntime>::NotAStorefrontOwner
+            );
+        });
+    }
+}
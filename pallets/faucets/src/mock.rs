@@ -95,6 +95,8 @@ impl pallet_utils::Trait for Test {
     type Currency = Balances;
     type MinHandleLen = MinHandleLen;
     type MaxHandleLen = MaxHandleLen;
+    type StorageDepositBase = ();
+    type StorageDepositPerByte = ();
 }
 
 impl Trait for Test {
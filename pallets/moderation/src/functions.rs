@@ -11,6 +11,10 @@ impl<T: Trait> Module<T> {
         Ok(Self::report_by_id(report_id).ok_or(Error::<T>::ReportNotFound)?)
     }
 
+    pub fn require_appeal(appeal_id: AppealId) -> Result<Appeal<T>, DispatchError> {
+        Ok(Self::appeal_by_id(appeal_id).ok_or(Error::<T>::AppealNotFound)?)
+    }
+
     /// Get entity storefront_id if it exists.
     /// Content and Account has no scope, consider check with `if let Some`
     fn get_entity_scope(entity: &EntityId<T::AccountId>) -> Result<Option<StorefrontId>, DispatchError> {
@@ -31,6 +35,11 @@ impl<T: Trait> Module<T> {
 
                 Ok(Some(storefront_id))
             },
+            EntityId::Order(order_id) => {
+                let order = Orders::<T>::require_order(*order_id)?;
+
+                Ok(Some(order.storefront_id))
+            },
         }
     }
 
@@ -42,6 +51,7 @@ impl<T: Trait> Module<T> {
             EntityId::Account(_) => Ok(()),
             EntityId::Storefront(storefront_id) => Storefronts::<T>::ensure_storefront_exists(*storefront_id),
             EntityId::Product(product_id) => Products::<T>::ensure_product_exists(*product_id),
+            EntityId::Order(order_id) => Orders::<T>::require_order(*order_id).map(|_| ()),
         }.map_err(|_| Error::<T>::EntityNotFound.into())
     }
 
@@ -50,15 +60,119 @@ impl<T: Trait> Module<T> {
         // TODO: think, what and where we should change something if entity is moved
         match entity {
             EntityId::Content(_) => (),
-            EntityId::Account(account_id)
-                => StorefrontFollows::<T>::unfollow_storefront_by_account(account_id.clone(), scope)?,
+            EntityId::Account(account_id) => {
+                if StorefrontFollows::<T>::storefront_followed_by_account((account_id.clone(), scope)) {
+                    StorefrontFollows::<T>::unfollow_storefront_by_account(account_id.clone(), scope)?;
+                }
+            },
             EntityId::Storefront(storefront_id) => Storefronts::<T>::try_move_storefront_to_root(*storefront_id)?,
             EntityId::Product(product_id) => Products::<T>::delete_product_from_storefront(*product_id)?,
+            EntityId::Order(_) => (),
         }
         StatusByEntityInStorefront::<T>::insert(entity, scope, EntityStatus::Blocked);
         Ok(())
     }
 
+    /// Remember who has changed the entity status and resolve open reports on this entity:
+    /// reports are accepted if the entity is blocked, and rejected if it is allowed.
+    /// An open appeal is resolved the opposite way.
+    pub(crate) fn on_entity_status_updated(
+        who: &T::AccountId,
+        entity: &EntityId<T::AccountId>,
+        scope: StorefrontId,
+        status: &EntityStatus
+    ) {
+        let (report_status, appeal_status) = match status {
+            EntityStatus::Blocked => {
+                BlockerByEntityInStorefront::<T>::insert(entity, scope, who);
+                (ReportStatus::Accepted, ReportStatus::Rejected)
+            },
+            EntityStatus::Allowed => {
+                BlockerByEntityInStorefront::<T>::remove(entity, scope);
+                (ReportStatus::Rejected, ReportStatus::Accepted)
+            },
+        };
+
+        Self::close_open_appeal(who, entity, scope, appeal_status);
+
+        for report_id in Self::report_ids_by_entity_in_storefront(entity, scope) {
+            if let Some(mut report) = Self::report_by_id(report_id) {
+                if report.status != ReportStatus::Open {
+                    continue;
+                }
+
+                report.status = report_status.clone();
+                report.updated = Some(WhoAndWhen::<T>::new(who.clone()));
+                ReportById::<T>::insert(report_id, report);
+                OpenReportIdsByStorefrontId::mutate(scope, |ids| vec_remove_on(ids, report_id));
+
+                Self::deposit_event(RawEvent::ReportResolved(who.clone(), report_id, report_status.clone()));
+            }
+        }
+    }
+
+    /// Close an open appeal on the entity when a moderator changes its status directly:
+    /// the appeal is accepted if the entity is not blocked anymore, and rejected otherwise.
+    pub(crate) fn close_open_appeal(
+        who: &T::AccountId,
+        entity: &EntityId<T::AccountId>,
+        scope: StorefrontId,
+        appeal_status: AppealStatus
+    ) {
+        if let Some(appeal_id) = Self::open_appeal_id_by_entity_in_storefront(entity, scope) {
+            if let Some(mut appeal) = Self::appeal_by_id(appeal_id) {
+                appeal.status = appeal_status.clone();
+                appeal.updated = Some(WhoAndWhen::<T>::new(who.clone()));
+                AppealById::<T>::insert(appeal_id, appeal);
+            }
+
+            OpenAppealIdByEntityInStorefront::<T>::remove(entity, scope);
+            OpenAppealIdsByStorefrontId::mutate(scope, |ids| vec_remove_on(ids, appeal_id));
+
+            Self::deposit_event(RawEvent::AppealResolved(who.clone(), appeal_id, appeal_status));
+        }
+    }
+
+    /// Check that `who` is the blocked account itself or the owner of the blocked entity.
+    pub(crate) fn ensure_can_appeal(who: &T::AccountId, entity: &EntityId<T::AccountId>) -> DispatchResult {
+        let is_allowed = match entity {
+            EntityId::Content(_) => false,
+            EntityId::Account(account) => account == who,
+            EntityId::Storefront(storefront_id) => Storefronts::<T>::require_storefront(*storefront_id)?.is_owner(who),
+            EntityId::Product(product_id) => Products::<T>::require_product(*product_id)?.is_owner(who),
+            EntityId::Order(order_id) => Orders::<T>::require_order(*order_id)?.seller == *who,
+        };
+
+        ensure!(is_allowed, Error::<T>::NotAllowedToAppeal);
+        Ok(())
+    }
+
+    /// Count a reported order of the `scope` storefront and block this storefront in `GOVERNANCE_SCOPE`
+    /// when the number of reported orders reaches `ReportedOrdersAutoblockThreshold`.
+    /// The storefront stays where it is. The reporter is recorded as a blocker,
+    /// so they cannot resolve an appeal on this block.
+    pub(crate) fn autoblock_storefront_on_reported_order(
+        reporter: &T::AccountId,
+        scope: StorefrontId,
+        order_id: OrderId
+    ) {
+        IsReportedOrderCounted::insert(order_id, true);
+        let reported_orders_count = ReportedOrdersCountByStorefrontId::mutate(scope, |count| {
+            *count = count.saturating_add(1);
+            *count
+        });
+
+        if reported_orders_count >= T::ReportedOrdersAutoblockThreshold::get() {
+            let entity = EntityId::Storefront(scope);
+
+            if Self::status_by_entity_in_storefront(&entity, GOVERNANCE_SCOPE) != Some(EntityStatus::Blocked) {
+                StatusByEntityInStorefront::<T>::insert(&entity, GOVERNANCE_SCOPE, EntityStatus::Blocked);
+                BlockerByEntityInStorefront::<T>::insert(&entity, GOVERNANCE_SCOPE, reporter);
+                Self::deposit_event(RawEvent::StorefrontAutoblocked(scope, reported_orders_count));
+            }
+        }
+    }
+
     pub(crate) fn ensure_account_status_manager(who: T::AccountId, storefront: &Storefront<T>) -> DispatchResult {
         Storefronts::<T>::ensure_account_has_storefront_permission(
             who,
@@ -68,6 +182,18 @@ impl<T: Trait> Module<T> {
         )
     }
 
+    /// Check that `who` can manage entity statuses within `scope`: dispute arbiters
+    /// in `GOVERNANCE_SCOPE`, or accounts with `UpdateEntityStatus` permission in a storefront.
+    pub(crate) fn ensure_account_status_manager_in_scope(who: T::AccountId, scope: StorefrontId) -> DispatchResult {
+        if scope == GOVERNANCE_SCOPE {
+            ensure!(Orders::<T>::is_dispute_arbiter(&who), Error::<T>::NoPermissionToUpdateEntityStatus);
+            return Ok(());
+        }
+
+        let storefront = Storefronts::<T>::require_storefront(scope).map_err(|_| Error::<T>::InvalidScope)?;
+        Self::ensure_account_status_manager(who, &storefront)
+    }
+
     pub(crate) fn ensure_entity_in_scope(entity: &EntityId<T::AccountId>, scope: StorefrontId) -> DispatchResult {
         if let Some(entity_scope) = Self::get_entity_scope(entity)? {
             ensure!(entity_scope == scope, Error::<T>::EntityIsNotInScope);
@@ -88,14 +214,47 @@ impl<T: Trait> Report<T> {
         created_by: T::AccountId,
         reported_entity: EntityId<T::AccountId>,
         scope: StorefrontId,
+        category: ReportCategory,
         reason: Content
     ) -> Self {
         Self {
             id,
             created: WhoAndWhen::<T>::new(created_by),
+            updated: None,
             reported_entity,
             reported_within: scope,
-            reason
+            category,
+            reason,
+            status: ReportStatus::Open,
+        }
+    }
+}
+
+impl ReportCategory {
+    pub fn is_order_only(&self) -> bool {
+        match self {
+            ReportCategory::NonDelivery | ReportCategory::NotAsDescribed => true,
+            _ => false,
+        }
+    }
+}
+
+impl<T: Trait> Appeal<T> {
+    pub fn new(
+        id: AppealId,
+        created_by: T::AccountId,
+        appealed_entity: EntityId<T::AccountId>,
+        scope: StorefrontId,
+        reason: Content
+    ) -> Self {
+        Self {
+            id,
+            created: WhoAndWhen::<T>::new(created_by),
+            updated: None,
+            appealed_entity,
+            appealed_within: scope,
+            reason,
+            status: ReportStatus::Open,
         }
     }
 }
@@ -111,10 +270,8 @@ impl<T: Trait> SuggestedStatus<T> {
 }
 
 // TODO: maybe simplify using one common trait?
-impl<T: Trait> IsAccountBlocked for Module<T> {
-    type AccountId = T::AccountId;
-
-    fn is_account_blocked(account: Self::AccountId, scope: StorefrontId) -> bool {
+impl<T: Trait> IsAccountBlocked<T::AccountId> for Module<T> {
+    fn is_account_blocked(account: T::AccountId, scope: StorefrontId) -> bool {
         let entity = EntityId::Account(account);
 
         Self::status_by_entity_in_storefront(entity, scope) == Some(EntityStatus::Blocked)
@@ -122,19 +279,17 @@ impl<T: Trait> IsAccountBlocked for Module<T> {
 }
 
 impl<T: Trait> IsStorefrontBlocked for Module<T> {
-    type StorefrontId = StorefrontId;
-
-    fn is_storefront_blocked(storefront_id: Self::StorefrontId, scope: StorefrontId) -> bool {
+    /// A storefront blocked in `GOVERNANCE_SCOPE` is considered blocked within any scope.
+    fn is_storefront_blocked(storefront_id: StorefrontId, scope: StorefrontId) -> bool {
         let entity = EntityId::Storefront(storefront_id);
 
-        Self::status_by_entity_in_storefront(entity, scope) == Some(EntityStatus::Blocked)
+        Self::status_by_entity_in_storefront(&entity, scope) == Some(EntityStatus::Blocked)
+            || Self::status_by_entity_in_storefront(&entity, GOVERNANCE_SCOPE) == Some(EntityStatus::Blocked)
     }
 }
 
-impl<T: Trait> IsProductBlocked for Module<T> {
-    type ProductId = ProductId;
-
-    fn is_product_blocked(product_id: Self::ProductId, scope: StorefrontId) -> bool {
+impl<T: Trait> IsProductBlocked<ProductId> for Module<T> {
+    fn is_product_blocked(product_id: ProductId, scope: StorefrontId) -> bool {
         let entity = EntityId::Product(product_id);
 
         Self::status_by_entity_in_storefront(entity, scope) == Some(EntityStatus::Blocked)
@@ -142,9 +297,7 @@ impl<T: Trait> IsProductBlocked for Module<T> {
 }
 
 impl<T: Trait> IsContentBlocked for Module<T> {
-    type Content = Content;
-
-    fn is_content_blocked(content: Self::Content, scope: StorefrontId) -> bool {
+    fn is_content_blocked(content: Content, scope: StorefrontId) -> bool {
         let entity = EntityId::Content(content);
 
         Self::status_by_entity_in_storefront(entity, scope) == Some(EntityStatus::Blocked)
@@ -10,21 +10,26 @@ use frame_support::{
 };
 use frame_system::{self as system, ensure_signed};
 
-use pallet_utils::{Content, WhoAndWhen, StorefrontId, Module as Utils};
+use pallet_utils::{Content, WhoAndWhen, StorefrontId, Module as Utils, vec_remove_on};
+use pallet_orders::{Module as Orders, OrderId, OrderState};
 use pallet_products::ProductId;
 use pallet_storefronts::Module as Storefronts;
 
-/*
 #[cfg(test)]
 mod mock;
 
 #[cfg(test)]
 mod tests;
-*/
 
 pub mod functions;
 
 pub type ReportId = u64;
+pub type AppealId = u64;
+
+/// A reserved scope for marketplace-wide decisions, such as autoblocking a storefront
+/// with too many reported orders. It is moderated by dispute arbiters instead of storefront roles.
+/// Storefront ids start from 1001, so this scope never matches a real storefront.
+pub const GOVERNANCE_SCOPE: StorefrontId = 0;
 
 #[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
 pub enum EntityId<AccountId> {
@@ -32,6 +37,20 @@ pub enum EntityId<AccountId> {
     Account(AccountId),
     Storefront(StorefrontId),
     Product(ProductId),
+    Order(OrderId),
+}
+
+#[derive(Encode, Decode, Clone, Copy, Eq, PartialEq, RuntimeDebug)]
+pub enum ReportCategory {
+    Spam,
+    Abuse,
+    Scam,
+    Counterfeit,
+    /// Order-only: the seller did not ship an order.
+    NonDelivery,
+    /// Order-only: the delivered goods differ from the product description.
+    NotAsDescribed,
+    Other,
 }
 
 #[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
@@ -40,13 +59,37 @@ pub enum EntityStatus {
     Blocked,
 }
 
+#[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
+pub enum ReportStatus {
+    Open,
+    Accepted,
+    Rejected,
+}
+
+/// Appeals are resolved in the same way as reports.
+pub type AppealStatus = ReportStatus;
+
 #[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
 pub struct Report<T: Trait> {
-    id: ReportId,
-    created: WhoAndWhen<T>,
-    reported_entity: EntityId<T::AccountId>,
-    reported_within: StorefrontId,
-    reason: Content,
+    pub id: ReportId,
+    pub created: WhoAndWhen<T>,
+    pub updated: Option<WhoAndWhen<T>>,
+    pub reported_entity: EntityId<T::AccountId>,
+    pub reported_within: StorefrontId,
+    pub category: ReportCategory,
+    pub reason: Content,
+    pub status: ReportStatus,
+}
+
+#[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
+pub struct Appeal<T: Trait> {
+    pub id: AppealId,
+    pub created: WhoAndWhen<T>,
+    pub updated: Option<WhoAndWhen<T>>,
+    pub appealed_entity: EntityId<T::AccountId>,
+    pub appealed_within: StorefrontId,
+    pub reason: Content,
+    pub status: AppealStatus,
 }
 
 #[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
@@ -58,12 +101,12 @@ pub struct SuggestedStatus<T: Trait> {
 
 #[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
 pub struct StorefrontModerationSettings {
-    autoblock_threshold: Option<u16>
+    pub autoblock_threshold: Option<u16>
 }
 
 #[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
 pub struct StorefrontModerationSettingsUpdate {
-    autoblock_threshold: Option<Option<u16>>
+    pub autoblock_threshold: Option<Option<u16>>
 }
 
 /// The pallet's configuration trait.
@@ -71,12 +114,16 @@ pub trait Trait: system::Trait
     + pallet_products::Trait
     + pallet_storefronts::Trait
     + pallet_storefront_follows::Trait
+    + pallet_orders::Trait
     + pallet_utils::Trait
 {
     /// The overarching event type.
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
 
     type DefaultAutoblockThreshold: Get<u16>;
+
+    /// The number of reported orders of a storefront after which it is blocked in `GOVERNANCE_SCOPE`.
+    type ReportedOrdersAutoblockThreshold: Get<u32>;
 }
 
 // This pallet's storage items.
@@ -92,6 +139,10 @@ decl_storage! {
 
         pub ReportIdsByStorefrontId: map hasher(twox_64_concat) StorefrontId => Vec<ReportId>;
 
+        /// Reports that are not resolved yet, i.e. a moderation queue of a storefront.
+        pub OpenReportIdsByStorefrontId get(fn open_report_ids_by_storefront_id):
+            map hasher(twox_64_concat) StorefrontId => Vec<ReportId>;
+
         pub ReportIdsByEntityInStorefront get(fn report_ids_by_entity_in_storefront): double_map
             hasher(twox_64_concat) EntityId<T::AccountId>,
             hasher(twox_64_concat) StorefrontId
@@ -107,8 +158,38 @@ decl_storage! {
             hasher(twox_64_concat) StorefrontId
              => Vec<SuggestedStatus<T>>;
 
+        /// Number of distinct orders reported within a storefront
+        /// after they have been accepted by the seller.
+        pub ReportedOrdersCountByStorefrontId get(fn reported_orders_count_by_storefront_id):
+            map hasher(twox_64_concat) StorefrontId => u32;
+
+        /// Whether a reported order is already counted in `ReportedOrdersCountByStorefrontId`.
+        pub IsReportedOrderCounted get(fn is_reported_order_counted):
+            map hasher(twox_64_concat) OrderId => bool;
+
         pub StorefrontSettings get(fn storefront_settings):
             map hasher(twox_64_concat) StorefrontId => Option<StorefrontModerationSettings>;
+
+        /// An account that blocked the entity in a storefront, either directly or by the last
+        /// suggestion that reached an autoblock threshold.
+        pub BlockerByEntityInStorefront get(fn blocker_by_entity_in_storefront): double_map
+            hasher(twox_64_concat) EntityId<T::AccountId>,
+            hasher(twox_64_concat) StorefrontId
+                => Option<T::AccountId>;
+
+        NextAppealId get(fn next_appeal_id): AppealId = 1;
+
+        pub AppealById get(fn appeal_by_id):
+            map hasher(twox_64_concat) AppealId => Option<Appeal<T>>;
+
+        pub OpenAppealIdByEntityInStorefront get(fn open_appeal_id_by_entity_in_storefront): double_map
+            hasher(twox_64_concat) EntityId<T::AccountId>,
+            hasher(twox_64_concat) StorefrontId
+                => Option<AppealId>;
+
+        /// Appeals that are not resolved yet, i.e. an appeal queue of a storefront.
+        pub OpenAppealIdsByStorefrontId get(fn open_appeal_ids_by_storefront_id):
+            map hasher(twox_64_concat) StorefrontId => Vec<AppealId>;
     }
 }
 
@@ -123,6 +204,12 @@ decl_event!(
         EntityStatusUpdated(AccountId, StorefrontId, EntityId, Option<EntityStatus>),
         EntityStatusDeleted(AccountId, StorefrontId, EntityId),
         StorefrontSettingsUpdated(AccountId, StorefrontId),
+        ReportResolved(AccountId, ReportId, ReportStatus),
+        EntityStatusAppealed(AccountId, StorefrontId, EntityId, AppealId),
+        AppealResolved(AccountId, AppealId, AppealStatus),
+        /// Storefront was blocked in `GOVERNANCE_SCOPE` because the number of reported orders
+        /// reached `ReportedOrdersAutoblockThreshold`.
+        StorefrontAutoblocked(StorefrontId, u32),
     }
 );
 
@@ -157,6 +244,26 @@ decl_error! {
         ScopeDiffersFromReport,
         /// Entity status update is already suggested by this account
         SuggestionAlreadyCreated,
+        /// Appeal was not found by its id.
+        AppealNotFound,
+        /// Appeal has already been accepted or rejected.
+        AppealAlreadyResolved,
+        /// There is an open appeal on this entity in the storefront.
+        AppealAlreadyOpen,
+        /// Appeal reason shouldn't be empty.
+        AppealReasonIsEmpty,
+        /// Only a blocked entity can be appealed.
+        EntityIsNotBlocked,
+        /// Only the blocked account or the owner of the blocked entity can appeal.
+        NotAllowedToAppeal,
+        /// Appeal must be resolved by a moderator other than the one who blocked the entity.
+        AppealMustBeResolvedByAnotherModerator,
+        /// Appeal cannot be resolved by the account that created it.
+        CannotResolveOwnAppeal,
+        /// Only the buyer or the seller of an order can report it.
+        NotInvolvedInOrder,
+        /// This report category can be used only for orders.
+        CategoryIsOnlyForOrders,
     }
 }
 
@@ -167,6 +274,8 @@ decl_module! {
 
         const DefaultAutoblockThreshold: u16 = T::DefaultAutoblockThreshold::get();
 
+        const ReportedOrdersAutoblockThreshold: u32 = T::ReportedOrdersAutoblockThreshold::get();
+
         // Initializing errors
         type Error = Error<T>;
 
@@ -180,6 +289,7 @@ decl_module! {
             origin,
             entity: EntityId<T::AccountId>,
             scope: StorefrontId,
+            category: ReportCategory,
             reason: Content
         ) -> DispatchResult {
             let who = ensure_signed(origin)?;
@@ -192,16 +302,40 @@ decl_module! {
 
             ensure!(Self::report_id_by_account((&entity, &who)).is_none(), Error::<T>::AlreadyReported);
 
+            // Only orders accepted by the seller are counted towards the autoblock threshold,
+            // so that a buyer cannot inflate it by reporting and cancelling new orders.
+            let mut is_order = false;
+            let mut should_count_order = None;
+            if let EntityId::Order(order_id) = &entity {
+                let order = Orders::<T>::require_order(*order_id)?;
+                ensure!(order.is_involved(&who), Error::<T>::NotInvolvedInOrder);
+
+                is_order = true;
+                let is_accepted_by_seller = match order.order_state {
+                    OrderState::Accepted | OrderState::Shipped | OrderState::Dispute => true,
+                    _ => false,
+                };
+                if is_accepted_by_seller && !Self::is_reported_order_counted(order_id) {
+                    should_count_order = Some(*order_id);
+                }
+            }
+            ensure!(is_order || !category.is_order_only(), Error::<T>::CategoryIsOnlyForOrders);
+
             let report_id = Self::next_report_id();
-            let new_report = Report::<T>::new(report_id, who.clone(), entity.clone(), scope, reason);
+            let new_report = Report::<T>::new(report_id, who.clone(), entity.clone(), scope, category, reason);
 
             ReportById::<T>::insert(report_id, new_report);
             ReportIdByAccount::<T>::insert((&entity, &who), report_id);
             ReportIdsByStorefrontId::mutate(scope, |ids| ids.push(report_id));
+            OpenReportIdsByStorefrontId::mutate(scope, |ids| ids.push(report_id));
             ReportIdsByEntityInStorefront::<T>::mutate(&entity, scope, |ids| ids.push(report_id));
             NextReportId::mutate(|n| { *n += 1; });
 
-            Self::deposit_event(RawEvent::EntityReported(who, scope, entity, report_id));
+            Self::deposit_event(RawEvent::EntityReported(who.clone(), scope, entity, report_id));
+
+            if let Some(order_id) = should_count_order {
+                Self::autoblock_storefront_on_reported_order(&who, scope, order_id);
+            }
             Ok(())
         }
 
@@ -249,6 +383,7 @@ decl_module! {
             if let Some(autoblock_threshold) = autoblock_threshold_opt {
                 if block_suggestions_total >= autoblock_threshold as usize {
                     Self::block_entity_in_scope(&entity, scope)?;
+                    Self::on_entity_status_updated(&who, &entity, scope, &EntityStatus::Blocked);
                 }
             }
 
@@ -257,7 +392,7 @@ decl_module! {
             Ok(())
         }
 
-        /// Block any `entity` provided.
+        /// Block any `entity` provided. An open appeal on this entity is closed.
         /// `origin` - any permitted account (e.g. Storefront owner or moderator that's set via role)
         #[weight = 10_000]
         pub fn update_entity_status(
@@ -282,8 +417,11 @@ decl_module! {
                 } else {
                     StatusByEntityInStorefront::<T>::insert(entity.clone(), scope, status);
                 }
+                Self::on_entity_status_updated(&who, &entity, scope, status);
             } else {
                 StatusByEntityInStorefront::<T>::remove(entity.clone(), scope);
+                BlockerByEntityInStorefront::<T>::remove(entity.clone(), scope);
+                Self::close_open_appeal(&who, &entity, scope, ReportStatus::Accepted);
             }
 
             Self::deposit_event(RawEvent::EntityStatusUpdated(who, scope, entity, status_opt));
@@ -297,19 +435,98 @@ decl_module! {
             let status = Self::status_by_entity_in_storefront(&entity, scope);
             ensure!(status.is_some(), Error::<T>::EntityHasNoAnyStatusInScope);
 
-            let storefront = Storefronts::<T>::require_storefront(scope).map_err(|_| Error::<T>::InvalidScope)?;
-            Self::ensure_account_status_manager(who.clone(), &storefront)?;
+            Self::ensure_account_status_manager_in_scope(who.clone(), scope)?;
 
             StatusByEntityInStorefront::<T>::remove(&entity, scope);
+            BlockerByEntityInStorefront::<T>::remove(&entity, scope);
+            Self::close_open_appeal(&who, &entity, scope, ReportStatus::Accepted);
 
             Self::deposit_event(RawEvent::EntityStatusDeleted(who, scope, entity));
             Ok(())
         }
 
+        /// Appeal a blocked `entity` with a mandatory reason.
+        /// `origin` - the blocked account or the owner of the blocked storefront or product.
+        #[weight = 10_000 + T::DbWeight::get().reads_writes(5, 4)]
+        pub fn appeal_entity_status(
+            origin,
+            entity: EntityId<T::AccountId>,
+            scope: StorefrontId,
+            reason: Content
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            Utils::<T>::ensure_content_is_some(&reason).map_err(|_| Error::<T>::AppealReasonIsEmpty)?;
+            Utils::<T>::is_valid_content(reason.clone())?;
+
+            ensure!(
+                Self::status_by_entity_in_storefront(&entity, scope) == Some(EntityStatus::Blocked),
+                Error::<T>::EntityIsNotBlocked
+            );
+            ensure!(
+                Self::open_appeal_id_by_entity_in_storefront(&entity, scope).is_none(),
+                Error::<T>::AppealAlreadyOpen
+            );
+            Self::ensure_can_appeal(&who, &entity)?;
+
+            let appeal_id = Self::next_appeal_id();
+            let new_appeal = Appeal::<T>::new(appeal_id, who.clone(), entity.clone(), scope, reason);
+
+            AppealById::<T>::insert(appeal_id, new_appeal);
+            OpenAppealIdByEntityInStorefront::<T>::insert(&entity, scope, appeal_id);
+            OpenAppealIdsByStorefrontId::mutate(scope, |ids| ids.push(appeal_id));
+            NextAppealId::mutate(|n| { *n += 1; });
+
+            Self::deposit_event(RawEvent::EntityStatusAppealed(who, scope, entity, appeal_id));
+            Ok(())
+        }
+
+        /// Accept or reject an appeal. An accepted appeal unblocks the entity.
+        /// `origin` - any permitted account, except the one who blocked the entity or created the appeal.
+        #[weight = 10_000 + T::DbWeight::get().reads_writes(6, 5)]
+        pub fn resolve_appeal(origin, appeal_id: AppealId, accept: bool) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let mut appeal = Self::require_appeal(appeal_id)?;
+            ensure!(appeal.status == ReportStatus::Open, Error::<T>::AppealAlreadyResolved);
+            ensure!(appeal.created.account != who, Error::<T>::CannotResolveOwnAppeal);
+
+            let entity = appeal.appealed_entity.clone();
+            let scope = appeal.appealed_within;
+
+            Self::ensure_account_status_manager_in_scope(who.clone(), scope)?;
+
+            ensure!(
+                Self::status_by_entity_in_storefront(&entity, scope) == Some(EntityStatus::Blocked),
+                Error::<T>::EntityIsNotBlocked
+            );
+            ensure!(
+                Self::blocker_by_entity_in_storefront(&entity, scope) != Some(who.clone()),
+                Error::<T>::AppealMustBeResolvedByAnotherModerator
+            );
+
+            if accept {
+                appeal.status = ReportStatus::Accepted;
+                StatusByEntityInStorefront::<T>::remove(&entity, scope);
+                BlockerByEntityInStorefront::<T>::remove(&entity, scope);
+            } else {
+                appeal.status = ReportStatus::Rejected;
+            }
+            appeal.updated = Some(WhoAndWhen::<T>::new(who.clone()));
+
+            let new_status = appeal.status.clone();
+            AppealById::<T>::insert(appeal_id, appeal);
+            OpenAppealIdByEntityInStorefront::<T>::remove(&entity, scope);
+            OpenAppealIdsByStorefrontId::mutate(scope, |ids| vec_remove_on(ids, appeal_id));
+
+            Self::deposit_event(RawEvent::AppealResolved(who, appeal_id, new_status));
+            Ok(())
+        }
+
         // todo: add ability to delete report_ids
 
         #[weight = 10_000]
-        fn update_storefront_settings(origin, storefront_id: StorefrontId, update: StorefrontModerationSettingsUpdate) -> DispatchResult {
+        pub fn update_storefront_settings(origin, storefront_id: StorefrontId, update: StorefrontModerationSettingsUpdate) -> DispatchResult {
             let who = ensure_signed(origin)?;
 
             let has_updates = update.autoblock_threshold.is_some();
@@ -1,81 +1,371 @@
-use crate::{Module, Trait};
+use crate::{Module, Trait, EntityId, ReportCategory, ReportId, AppealId, EntityStatus};
+
 use sp_core::H256;
-use frame_support::{impl_outer_origin, parameter_types, weights::Weight};
+use sp_io::TestExternalities;
+use sp_std::iter::FromIterator;
 use sp_runtime::{
-	traits::{BlakeTwo256, IdentityLookup}, testing::Header, Perbill,
+    traits::{BlakeTwo256, IdentityLookup}, testing::Header, Perbill,
+};
+use frame_support::{
+    impl_outer_origin, parameter_types, assert_ok,
+    weights::Weight,
+    dispatch::DispatchResult,
 };
 use frame_system as system;
 
+use pallet_permissions::{
+    StorefrontPermission as SP,
+    StorefrontPermissions,
+    StorefrontPermissionSet
+};
+use pallet_utils::{StorefrontId, User, Content};
+
 impl_outer_origin! {
-	pub enum Origin for Test {}
+    pub enum Origin for Test {}
 }
 
 #[derive(Clone, Eq, PartialEq)]
 pub struct Test;
 
 parameter_types! {
-	pub const BlockHashCount: u64 = 250;
-	pub const MaximumBlockWeight: Weight = 1024;
-	pub const MaximumBlockLength: u32 = 2 * 1024;
-	pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
+    pub const BlockHashCount: u64 = 250;
+    pub const MaximumBlockWeight: Weight = 1024;
+    pub const MaximumBlockLength: u32 = 2 * 1024;
+    pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
 }
 
 impl system::Trait for Test {
-	type BaseCallFilter = ();
-	type Origin = Origin;
-	type Call = ();
-	type Index = u64;
-	type BlockNumber = u64;
-	type Hash = H256;
-	type Hashing = BlakeTwo256;
-	type AccountId = u64;
-	type Lookup = IdentityLookup<Self::AccountId>;
-	type Header = Header;
-	type Event = ();
-	type BlockHashCount = BlockHashCount;
-	type MaximumBlockWeight = MaximumBlockWeight;
-	type DbWeight = ();
-	type BlockExecutionWeight = ();
-	type ExtrinsicBaseWeight = ();
-	type MaximumExtrinsicWeight = MaximumBlockWeight;
-	type MaximumBlockLength = MaximumBlockLength;
-	type AvailableBlockRatio = AvailableBlockRatio;
-	type Version = ();
-	type ModuleToIndex = ();
-	type AccountData = ();
-	type OnNewAccount = ();
-	type OnKilledAccount = ();
+    type BaseCallFilter = ();
+    type Origin = Origin;
+    type Call = ();
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = u64;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type Event = ();
+    type BlockHashCount = BlockHashCount;
+    type MaximumBlockWeight = MaximumBlockWeight;
+    type DbWeight = ();
+    type BlockExecutionWeight = ();
+    type ExtrinsicBaseWeight = ();
+    type MaximumExtrinsicWeight = MaximumBlockWeight;
+    type MaximumBlockLength = MaximumBlockLength;
+    type AvailableBlockRatio = AvailableBlockRatio;
+    type Version = ();
+    type ModuleToIndex = ();
+    type AccountData = pallet_balances::AccountData<u64>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
 }
 
 parameter_types! {
-  pub const MinimumPeriod: u64 = 5;
+    pub const MinimumPeriod: u64 = 5;
 }
 
 impl pallet_timestamp::Trait for Test {
-	type Moment = u64;
-	type OnTimestampSet = ();
-	type MinimumPeriod = MinimumPeriod;
+    type Moment = u64;
+    type OnTimestampSet = ();
+    type MinimumPeriod = MinimumPeriod;
 }
 
 parameter_types! {
-  pub const MinHandleLen: u32 = 5;
-  pub const MaxHandleLen: u32 = 50;
+    pub const ExistentialDeposit: u64 = 1;
+}
+
+impl pallet_balances::Trait for Test {
+    type Balance = u64;
+    type DustRemoval = ();
+    type Event = ();
+    type ExistentialDeposit = ExistentialDeposit;
+    type AccountStore = System;
+}
+
+parameter_types! {
+    pub const MinHandleLen: u32 = 5;
+    pub const MaxHandleLen: u32 = 50;
 }
 
 impl pallet_utils::Trait for Test {
-	type MinHandleLen = MinHandleLen;
-	type MaxHandleLen = MaxHandleLen;
+    type Event = ();
+    type Currency = Balances;
+    type MinHandleLen = MinHandleLen;
+    type MaxHandleLen = MaxHandleLen;
+    type StorageDepositBase = ();
+    type StorageDepositPerByte = ();
+}
+
+parameter_types! {
+    pub DefaultStorefrontPermissions: StorefrontPermissions = StorefrontPermissions {
+
+        // No permissions disabled by default
+        none: None,
+
+        everyone: Some(StorefrontPermissionSet::from_iter(vec![
+            SP::UpdateOwnProducts,
+            SP::DeleteOwnProducts,
+            SP::HideOwnProducts,
+
+            SP::CreateComments,
+            SP::UpdateOwnComments,
+            SP::DeleteOwnComments,
+            SP::HideOwnComments,
+        ].into_iter())),
+
+        // Followers can do everything that everyone else can.
+        follower: None,
+
+        storefront_owner: Some(StorefrontPermissionSet::from_iter(vec![
+            SP::ManageRoles,
+
+            SP::CreateSubstorefronts,
+            SP::CreateProducts,
+
+            SP::UpdateStorefront,
+            SP::UpdateAnyProduct,
+            SP::DeleteAnyProduct,
+            SP::HideAnyProduct,
+
+            SP::SuggestEntityStatus,
+            SP::UpdateEntityStatus,
+
+            SP::UpdateStorefrontSettings,
+        ].into_iter())),
+    };
+}
+
+impl pallet_permissions::Trait for Test {
+    type DefaultStorefrontPermissions = DefaultStorefrontPermissions;
+}
+
+parameter_types! {
+    pub const MaxUsersToProcessPerDeleteRole: u16 = 40;
+}
+
+impl pallet_roles::Trait for Test {
+    type Event = ();
+    type MaxUsersToProcessPerDeleteRole = MaxUsersToProcessPerDeleteRole;
+    type Storefronts = Storefronts;
+    type StorefrontFollows = StorefrontFollows;
+}
+
+impl pallet_profiles::Trait for Test {
+    type Event = ();
+    type AfterProfileUpdated = ();
+}
+
+impl pallet_storefronts::Trait for Test {
+    type Event = ();
+    type Roles = Roles;
+    type StorefrontFollows = StorefrontFollows;
+    type BeforeStorefrontCreated = ();
+    type AfterStorefrontUpdated = ();
+    type BeforeStorefrontDeleted = ();
+    type IsAccountBlocked = Moderation;
+    type IsContentBlocked = Moderation;
+    type IsStorefrontBlocked = Moderation;
+}
+
+impl pallet_storefront_follows::Trait for Test {
+    type Event = ();
+    type BeforeStorefrontFollowed = ();
+    type BeforeStorefrontUnfollowed = ();
+}
+
+parameter_types! {
+    pub const MaxCategoriesPerProduct: u16 = 2;
+    pub const MaxCategoriesPerStorefront: u16 = 2;
+}
+
+impl pallet_categories::Trait for Test {
+    type Event = ();
+    type MaxCategoriesPerProduct = MaxCategoriesPerProduct;
+    type MaxCategoriesPerStorefront = MaxCategoriesPerStorefront;
+}
+
+parameter_types! {
+    pub const MaxCommentDepth: u32 = 10;
+}
+
+impl pallet_products::Trait for Test {
+    type Event = ();
+    type MaxCommentDepth = MaxCommentDepth;
+    type ProductScores = ();
+    type AfterProductUpdated = ();
+    type BeforeProductDeleted = ();
+    type IsProductBlocked = Moderation;
+}
+
+parameter_types! {
+    pub const MaxOrderItems: u16 = 50;
+    pub const MaxOrderMessageLength: u32 = 2 * 1024;
+    pub const MaxDisputeEvidence: u16 = 20;
+    pub const AcceptOrderTimeout: u64 = 100;
+    pub const ConfirmOrderTimeout: u64 = 100;
+    pub const MaxExpiredOrdersPerBlock: u32 = 3;
+    pub const MarketplaceFee: Perbill = Perbill::from_percent(2);
+}
+
+impl pallet_orders::Trait for Test {
+    type Event = ();
+    type AfterOrderUpdated = ();
+    type MaxOrderItems = MaxOrderItems;
+    type MaxOrderMessageLength = MaxOrderMessageLength;
+    type MaxDisputeEvidence = MaxDisputeEvidence;
+    type AcceptOrderTimeout = AcceptOrderTimeout;
+    type ConfirmOrderTimeout = ConfirmOrderTimeout;
+    type MaxExpiredOrdersPerBlock = MaxExpiredOrdersPerBlock;
+    type MarketplaceFee = MarketplaceFee;
+}
+
+parameter_types! {
+    pub const DefaultAutoblockThreshold: u16 = 2;
+    pub const ReportedOrdersAutoblockThreshold: u32 = 2;
 }
 
 impl Trait for Test {
-	type Event = ();
+    type Event = ();
+    type DefaultAutoblockThreshold = DefaultAutoblockThreshold;
+    type ReportedOrdersAutoblockThreshold = ReportedOrdersAutoblockThreshold;
 }
 
-#[allow(dead_code)]
-pub type Moderation = Module<Test>;
+type System = system::Module<Test>;
+type Balances = pallet_balances::Module<Test>;
+type Roles = pallet_roles::Module<Test>;
+type Storefronts = pallet_storefronts::Module<Test>;
+type StorefrontFollows = pallet_storefront_follows::Module<Test>;
+pub(crate) type Moderation = Module<Test>;
+
+pub type AccountId = u64;
+
+pub struct ExtBuilder;
+
+impl ExtBuilder {
+    pub fn build() -> TestExternalities {
+        let storage = system::GenesisConfig::default()
+            .build_storage::<Test>()
+            .unwrap();
+
+        let mut ext = TestExternalities::from(storage);
+        ext.execute_with(|| System::set_block_number(1));
+
+        ext
+    }
+
+    /// A storefront of `ACCOUNT1` with a moderator role granted to `ACCOUNT2` and `ACCOUNT3`.
+    pub fn build_with_storefront_and_moderators() -> TestExternalities {
+        let mut ext = Self::build();
+        ext.execute_with(|| {
+            assert_ok!(Storefronts::create_storefront(
+                Origin::signed(ACCOUNT1),
+                None,
+                None,
+                self::storefront_content_ipfs()
+            ));
+
+            assert_ok!(Roles::create_role(
+                Origin::signed(ACCOUNT1),
+                STOREFRONT1,
+                None,
+                self::storefront_content_ipfs(),
+                vec![SP::SuggestEntityStatus, SP::UpdateEntityStatus]
+            ));
+            assert_ok!(Roles::grant_role(
+                Origin::signed(ACCOUNT1),
+                ROLE1,
+                vec![User::Account(ACCOUNT2), User::Account(ACCOUNT3)]
+            ));
+        });
+
+        ext
+    }
+}
+
+pub(crate) const ACCOUNT1: AccountId = 1;
+pub(crate) const ACCOUNT2: AccountId = 2;
+pub(crate) const ACCOUNT3: AccountId = 3;
+pub(crate) const ACCOUNT4: AccountId = 4;
+pub(crate) const ACCOUNT5: AccountId = 5;
+
+pub(crate) const ROLE1: u64 = 1;
+
+pub(crate) const STOREFRONT1: StorefrontId = 1001;
+pub(crate) const STOREFRONT2: StorefrontId = 1002;
+
+pub(crate) const REPORT1: ReportId = 1;
+pub(crate) const APPEAL1: AppealId = 1;
+
+pub(crate) fn storefront_content_ipfs() -> Content {
+    Content::IPFS(b"bafyreib3mgbou4xln42qqcgj6qlt3cif35x4ribisxgq7unhpun525l54e".to_vec())
+}
+
+pub(crate) fn reason_content_ipfs() -> Content {
+    Content::IPFS(b"QmRAQB6YaCyidP37UdDnjFY5vQuiBrcqdyoW1CuDgwxkD4".to_vec())
+}
+
+/// The entity that is reported, blocked and appealed in most of the tests.
+pub(crate) fn blocked_account() -> EntityId<AccountId> {
+    EntityId::Account(ACCOUNT4)
+}
+
+pub(crate) fn _report_default_entity() -> DispatchResult {
+    _report_entity(None, None, None, None, None)
+}
+
+pub(crate) fn _report_entity(
+    origin: Option<Origin>,
+    entity: Option<EntityId<AccountId>>,
+    scope: Option<StorefrontId>,
+    category: Option<ReportCategory>,
+    reason: Option<Content>,
+) -> DispatchResult {
+    Moderation::report_entity(
+        origin.unwrap_or_else(|| Origin::signed(ACCOUNT5)),
+        entity.unwrap_or_else(self::blocked_account),
+        scope.unwrap_or(STOREFRONT1),
+        category.unwrap_or(ReportCategory::Spam),
+        reason.unwrap_or_else(self::reason_content_ipfs),
+    )
+}
+
+pub(crate) fn _suggest_blocked_status(origin: Option<Origin>) -> DispatchResult {
+    Moderation::suggest_entity_status(
+        origin.unwrap_or_else(|| Origin::signed(ACCOUNT2)),
+        self::blocked_account(),
+        STOREFRONT1,
+        Some(EntityStatus::Blocked),
+        Some(REPORT1),
+    )
+}
+
+pub(crate) fn _block_default_entity(origin: Option<Origin>) -> DispatchResult {
+    Moderation::update_entity_status(
+        origin.unwrap_or_else(|| Origin::signed(ACCOUNT1)),
+        self::blocked_account(),
+        STOREFRONT1,
+        Some(EntityStatus::Blocked),
+    )
+}
+
+pub(crate) fn _appeal_default_entity() -> DispatchResult {
+    _appeal_entity(None, None)
+}
+
+pub(crate) fn _appeal_entity(origin: Option<Origin>, reason: Option<Content>) -> DispatchResult {
+    Moderation::appeal_entity_status(
+        origin.unwrap_or_else(|| Origin::signed(ACCOUNT4)),
+        self::blocked_account(),
+        STOREFRONT1,
+        reason.unwrap_or_else(self::reason_content_ipfs),
+    )
+}
 
-// This function basically just builds a genesis storage key/value store according to
-// our desired mockup.
-pub fn _new_test_ext() -> sp_io::TestExternalities {
-	system::GenesisConfig::default().build_storage::<Test>().unwrap().into()
+pub(crate) fn _resolve_default_appeal(origin: Option<Origin>, accept: bool) -> DispatchResult {
+    Moderation::resolve_appeal(
+        origin.unwrap_or_else(|| Origin::signed(ACCOUNT2)),
+        APPEAL1,
+        accept,
+    )
 }
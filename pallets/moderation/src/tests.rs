@@ -1,8 +1,330 @@
-/*use crate::{Error, mock::*};
-use frame_support::{assert_ok, assert_noop};
+use crate::mock::*;
+use crate::*;
+
+use frame_support::{
+    assert_ok, assert_noop,
+};
+
+// Report tests
+// ----------------------------------------------------------------------------
+
+#[test]
+fn report_entity_should_work() {
+    ExtBuilder::build_with_storefront_and_moderators().execute_with(|| {
+        assert_ok!(_report_default_entity()); // ReportId 1
+
+        let report = Moderation::report_by_id(REPORT1).unwrap();
+        assert_eq!(report.created.account, ACCOUNT5);
+        assert_eq!(report.reported_entity, blocked_account());
+        assert_eq!(report.reported_within, STOREFRONT1);
+        assert_eq!(report.category, ReportCategory::Spam);
+        assert_eq!(report.status, ReportStatus::Open);
+
+        assert_eq!(Moderation::next_report_id(), REPORT1 + 1);
+        assert_eq!(Moderation::report_id_by_account((blocked_account(), ACCOUNT5)), Some(REPORT1));
+        assert_eq!(Moderation::open_report_ids_by_storefront_id(STOREFRONT1), vec![REPORT1]);
+        assert_eq!(Moderation::report_ids_by_entity_in_storefront(blocked_account(), STOREFRONT1), vec![REPORT1]);
+    });
+}
+
+#[test]
+fn report_entity_should_fail_when_already_reported_by_account() {
+    ExtBuilder::build_with_storefront_and_moderators().execute_with(|| {
+        assert_ok!(_report_default_entity());
+
+        assert_noop!(_report_default_entity(), Error::<Test>::AlreadyReported);
+    });
+}
+
+#[test]
+fn report_entity_should_fail_with_empty_reason() {
+    ExtBuilder::build_with_storefront_and_moderators().execute_with(|| {
+        assert_noop!(
+            _report_entity(None, None, None, None, Some(Content::None)),
+            Error::<Test>::ReasonIsEmpty
+        );
+    });
+}
+
+#[test]
+fn report_entity_should_fail_when_scope_not_found() {
+    ExtBuilder::build_with_storefront_and_moderators().execute_with(|| {
+        assert_noop!(
+            _report_entity(None, None, Some(STOREFRONT2), None, None),
+            Error::<Test>::InvalidScope
+        );
+    });
+}
+
+#[test]
+fn report_entity_should_fail_when_order_only_category_used_for_not_an_order() {
+    ExtBuilder::build_with_storefront_and_moderators().execute_with(|| {
+        assert_noop!(
+            _report_entity(None, None, None, Some(ReportCategory::NonDelivery), None),
+            Error::<Test>::CategoryIsOnlyForOrders
+        );
+    });
+}
+
+// Autoblock tests
+// ----------------------------------------------------------------------------
+
+#[test]
+fn suggest_entity_status_should_autoblock_entity_when_threshold_reached() {
+    ExtBuilder::build_with_storefront_and_moderators().execute_with(|| {
+        assert_ok!(_report_default_entity());
+
+        // The first suggestion is below the threshold of 2
+        assert_ok!(_suggest_blocked_status(None));
+        assert!(Moderation::status_by_entity_in_storefront(blocked_account(), STOREFRONT1).is_none());
+        assert_eq!(Moderation::report_by_id(REPORT1).unwrap().status, ReportStatus::Open);
+
+        assert_ok!(_suggest_blocked_status(Some(Origin::signed(ACCOUNT3))));
+
+        assert_eq!(
+            Moderation::status_by_entity_in_storefront(blocked_account(), STOREFRONT1),
+            Some(EntityStatus::Blocked)
+        );
+        // The suggestion that reached the threshold is recorded as a blocker
+        assert_eq!(
+            Moderation::blocker_by_entity_in_storefront(blocked_account(), STOREFRONT1),
+            Some(ACCOUNT3)
+        );
+        assert_eq!(Moderation::report_by_id(REPORT1).unwrap().status, ReportStatus::Accepted);
+        assert!(Moderation::open_report_ids_by_storefront_id(STOREFRONT1).is_empty());
+    });
+}
+
+#[test]
+fn suggest_entity_status_should_fail_when_already_suggested_by_account() {
+    ExtBuilder::build_with_storefront_and_moderators().execute_with(|| {
+        assert_ok!(_report_default_entity());
+        assert_ok!(_suggest_blocked_status(None));
+
+        assert_noop!(_suggest_blocked_status(None), Error::<Test>::SuggestionAlreadyCreated);
+    });
+}
+
+#[test]
+fn suggest_entity_status_should_fail_without_permission() {
+    ExtBuilder::build_with_storefront_and_moderators().execute_with(|| {
+        assert_ok!(_report_default_entity());
+
+        assert_noop!(
+            _suggest_blocked_status(Some(Origin::signed(ACCOUNT5))),
+            Error::<Test>::NoPermissionToSuggestEntityStatus
+        );
+    });
+}
+
+// Appeal tests
+// ----------------------------------------------------------------------------
+
+#[test]
+fn appeal_entity_status_should_work() {
+    ExtBuilder::build_with_storefront_and_moderators().execute_with(|| {
+        assert_ok!(_block_default_entity(None));
+        assert_ok!(_appeal_default_entity()); // AppealId 1
+
+        let appeal = Moderation::appeal_by_id(APPEAL1).unwrap();
+        assert_eq!(appeal.created.account, ACCOUNT4);
+        assert_eq!(appeal.appealed_entity, blocked_account());
+        assert_eq!(appeal.appealed_within, STOREFRONT1);
+        assert_eq!(appeal.status, ReportStatus::Open);
+
+        assert_eq!(Moderation::open_appeal_id_by_entity_in_storefront(blocked_account(), STOREFRONT1), Some(APPEAL1));
+        assert_eq!(Moderation::open_appeal_ids_by_storefront_id(STOREFRONT1), vec![APPEAL1]);
+    });
+}
+
+#[test]
+fn appeal_entity_status_should_fail_when_entity_not_blocked() {
+    ExtBuilder::build_with_storefront_and_moderators().execute_with(|| {
+        assert_noop!(_appeal_default_entity(), Error::<Test>::EntityIsNotBlocked);
+    });
+}
+
+#[test]
+fn appeal_entity_status_should_fail_when_not_allowed_to_appeal() {
+    ExtBuilder::build_with_storefront_and_moderators().execute_with(|| {
+        assert_ok!(_block_default_entity(None));
+
+        assert_noop!(
+            _appeal_entity(Some(Origin::signed(ACCOUNT5)), None),
+            Error::<Test>::NotAllowedToAppeal
+        );
+    });
+}
+
+#[test]
+fn appeal_entity_status_should_fail_when_appeal_already_open() {
+    ExtBuilder::build_with_storefront_and_moderators().execute_with(|| {
+        assert_ok!(_block_default_entity(None));
+        assert_ok!(_appeal_default_entity());
+
+        assert_noop!(_appeal_default_entity(), Error::<Test>::AppealAlreadyOpen);
+    });
+}
+
+#[test]
+fn resolve_appeal_should_unblock_entity_when_accepted() {
+    ExtBuilder::build_with_storefront_and_moderators().execute_with(|| {
+        assert_ok!(_block_default_entity(None));
+        assert_ok!(_appeal_default_entity());
+
+        assert_ok!(_resolve_default_appeal(None, true));
+
+        assert_eq!(Moderation::appeal_by_id(APPEAL1).unwrap().status, ReportStatus::Accepted);
+        assert!(Moderation::status_by_entity_in_storefront(blocked_account(), STOREFRONT1).is_none());
+        assert!(Moderation::blocker_by_entity_in_storefront(blocked_account(), STOREFRONT1).is_none());
+        assert!(Moderation::open_appeal_id_by_entity_in_storefront(blocked_account(), STOREFRONT1).is_none());
+        assert!(Moderation::open_appeal_ids_by_storefront_id(STOREFRONT1).is_empty());
+    });
+}
+
+#[test]
+fn resolve_appeal_should_keep_entity_blocked_when_rejected() {
+    ExtBuilder::build_with_storefront_and_moderators().execute_with(|| {
+        assert_ok!(_block_default_entity(None));
+        assert_ok!(_appeal_default_entity());
+
+        assert_ok!(_resolve_default_appeal(None, false));
+
+        assert_eq!(Moderation::appeal_by_id(APPEAL1).unwrap().status, ReportStatus::Rejected);
+        assert_eq!(
+            Moderation::status_by_entity_in_storefront(blocked_account(), STOREFRONT1),
+            Some(EntityStatus::Blocked)
+        );
+        assert!(Moderation::open_appeal_id_by_entity_in_storefront(blocked_account(), STOREFRONT1).is_none());
+    });
+}
+
+#[test]
+fn resolve_appeal_should_fail_when_resolved_by_moderator_who_blocked_entity() {
+    ExtBuilder::build_with_storefront_and_moderators().execute_with(|| {
+        // ACCOUNT1 (storefront owner) blocks the entity
+        assert_ok!(_block_default_entity(None));
+        assert_ok!(_appeal_default_entity());
+
+        assert_noop!(
+            _resolve_default_appeal(Some(Origin::signed(ACCOUNT1)), true),
+            Error::<Test>::AppealMustBeResolvedByAnotherModerator
+        );
+        assert_noop!(
+            _resolve_default_appeal(Some(Origin::signed(ACCOUNT1)), false),
+            Error::<Test>::AppealMustBeResolvedByAnotherModerator
+        );
+    });
+}
+
+#[test]
+fn resolve_appeal_should_fail_when_resolved_by_moderator_who_autoblocked_entity() {
+    ExtBuilder::build_with_storefront_and_moderators().execute_with(|| {
+        assert_ok!(_report_default_entity());
+        assert_ok!(_suggest_blocked_status(None));
+        assert_ok!(_suggest_blocked_status(Some(Origin::signed(ACCOUNT3))));
+        assert_ok!(_appeal_default_entity());
+
+        assert_noop!(
+            _resolve_default_appeal(Some(Origin::signed(ACCOUNT3)), true),
+            Error::<Test>::AppealMustBeResolvedByAnotherModerator
+        );
+
+        // Any other moderator can resolve it
+        assert_ok!(_resolve_default_appeal(Some(Origin::signed(ACCOUNT1)), true));
+    });
+}
+
+#[test]
+fn resolve_appeal_should_fail_without_permission() {
+    ExtBuilder::build_with_storefront_and_moderators().execute_with(|| {
+        assert_ok!(_block_default_entity(None));
+        assert_ok!(_appeal_default_entity());
+
+        assert_noop!(
+            _resolve_default_appeal(Some(Origin::signed(ACCOUNT5)), true),
+            Error::<Test>::NoPermissionToUpdateEntityStatus
+        );
+    });
+}
+
+#[test]
+fn resolve_appeal_should_fail_when_resolved_by_appellant() {
+    ExtBuilder::build_with_storefront_and_moderators().execute_with(|| {
+        assert_ok!(_block_default_entity(None));
+        assert_ok!(_appeal_default_entity());
+
+        assert_noop!(
+            _resolve_default_appeal(Some(Origin::signed(ACCOUNT4)), true),
+            Error::<Test>::CannotResolveOwnAppeal
+        );
+    });
+}
+
+#[test]
+fn resolve_appeal_should_fail_when_already_resolved() {
+    ExtBuilder::build_with_storefront_and_moderators().execute_with(|| {
+        assert_ok!(_block_default_entity(None));
+        assert_ok!(_appeal_default_entity());
+        assert_ok!(_resolve_default_appeal(None, false));
+
+        assert_noop!(
+            _resolve_default_appeal(Some(Origin::signed(ACCOUNT3)), true),
+            Error::<Test>::AppealAlreadyResolved
+        );
+    });
+}
 
 #[test]
-fn it_works_for_default_value() {
-	new_test_ext().execute_with(|| {
-	});
-}*/
+fn update_entity_status_should_accept_open_appeal_when_entity_allowed() {
+    ExtBuilder::build_with_storefront_and_moderators().execute_with(|| {
+        assert_ok!(_block_default_entity(None));
+        assert_ok!(_appeal_default_entity());
+
+        assert_ok!(Moderation::update_entity_status(
+            Origin::signed(ACCOUNT2),
+            blocked_account(),
+            STOREFRONT1,
+            Some(EntityStatus::Allowed)
+        ));
+
+        let appeal = Moderation::appeal_by_id(APPEAL1).unwrap();
+        assert_eq!(appeal.status, ReportStatus::Accepted);
+        assert_eq!(appeal.updated.unwrap().account, ACCOUNT2);
+        assert!(Moderation::open_appeal_id_by_entity_in_storefront(blocked_account(), STOREFRONT1).is_none());
+        assert!(Moderation::open_appeal_ids_by_storefront_id(STOREFRONT1).is_empty());
+
+        // The closed appeal cannot be resolved anymore
+        assert_noop!(_resolve_default_appeal(Some(Origin::signed(ACCOUNT3)), false), Error::<Test>::AppealAlreadyResolved);
+    });
+}
+
+#[test]
+fn update_entity_status_should_reject_open_appeal_when_entity_blocked_again() {
+    ExtBuilder::build_with_storefront_and_moderators().execute_with(|| {
+        assert_ok!(_block_default_entity(None));
+        assert_ok!(_appeal_default_entity());
+
+        assert_ok!(_block_default_entity(Some(Origin::signed(ACCOUNT2))));
+
+        assert_eq!(Moderation::appeal_by_id(APPEAL1).unwrap().status, ReportStatus::Rejected);
+        assert!(Moderation::open_appeal_id_by_entity_in_storefront(blocked_account(), STOREFRONT1).is_none());
+
+        // The entity can be appealed again
+        assert_ok!(_appeal_default_entity());
+    });
+}
+
+#[test]
+fn delete_entity_status_should_accept_open_appeal() {
+    ExtBuilder::build_with_storefront_and_moderators().execute_with(|| {
+        assert_ok!(_block_default_entity(None));
+        assert_ok!(_appeal_default_entity());
+
+        assert_ok!(Moderation::delete_entity_status(Origin::signed(ACCOUNT2), blocked_account(), STOREFRONT1));
+
+        assert_eq!(Moderation::appeal_by_id(APPEAL1).unwrap().status, ReportStatus::Accepted);
+        assert!(Moderation::open_appeal_id_by_entity_in_storefront(blocked_account(), STOREFRONT1).is_none());
+        assert!(Moderation::open_appeal_ids_by_storefront_id(STOREFRONT1).is_empty());
+    });
+}
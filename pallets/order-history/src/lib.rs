@@ -1,7 +1,7 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use codec::{Decode, Encode};
-use frame_support::{decl_module, decl_storage};
+use frame_support::{decl_module, decl_storage, weights::Weight};
 use sp_runtime::RuntimeDebug;
 use sp_std::prelude::Vec;
 use frame_system::{self as system};
@@ -9,6 +9,8 @@ use frame_system::{self as system};
 use pallet_orders::{OrderId, Order, OrderUpdate, AfterOrderUpdated};
 use pallet_utils::WhoAndWhen;
 
+mod migration;
+
 #[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
 pub struct OrderHistoryRecord<T: Trait> {
     pub edited: WhoAndWhen<T>,
@@ -26,11 +28,22 @@ decl_storage! {
     trait Store for Module<T: Trait> as OrderHistoryModule {
         pub EditHistory get(fn edit_history):
             map hasher(twox_64_concat) OrderId => Vec<OrderHistoryRecord<T>>;
+
+        /// Whether history records are stored in the current layout. New chains start with it.
+        pub IsOrderHistoryLayoutMigrated get(fn is_order_history_layout_migrated)
+            build(|_config: &GenesisConfig| true): bool;
     }
 }
 
 decl_module! {
-  pub struct Module<T: Trait> for enum Call where origin: T::Origin {}
+  pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+    fn on_runtime_upgrade() -> Weight {
+      if Self::is_order_history_layout_migrated() {
+        return 0;
+      }
+      migration::migrate_order_history::<T>()
+    }
+  }
 }
 
 impl<T: Trait> OrderHistoryRecord<T> {
@@ -0,0 +1,48 @@
+use frame_support::{storage::IterableStorageMap, traits::Get};
+use sp_std::cell::Cell;
+
+use pallet_orders::OrderState;
+use pallet_utils::Content;
+
+use super::*;
+
+/// An order update as it was stored before encrypted messages were added.
+#[derive(Decode)]
+struct OldOrderUpdate {
+    content: Option<Content>,
+    order_state: OrderState,
+}
+
+#[derive(Decode)]
+struct OldOrderHistoryRecord<T: Trait> {
+    edited: WhoAndWhen<T>,
+    old_data: OldOrderUpdate,
+}
+
+impl<T: Trait> From<OldOrderHistoryRecord<T>> for OrderHistoryRecord<T> {
+    fn from(old: OldOrderHistoryRecord<T>) -> Self {
+        OrderHistoryRecord {
+            edited: old.edited,
+            old_data: OrderUpdate {
+                content: old.old_data.content,
+                order_state: old.old_data.order_state,
+                message: None,
+            },
+        }
+    }
+}
+
+/// Convert all order history records to the current layout.
+pub(crate) fn migrate_order_history<T: Trait>() -> Weight {
+    let histories_count = Cell::new(0u64);
+
+    EditHistory::<T>::translate(|_order_id, old_records: Vec<OldOrderHistoryRecord<T>>| {
+        histories_count.set(histories_count.get() + 1);
+        Some(old_records.into_iter().map(Into::into).collect())
+    });
+
+    IsOrderHistoryLayoutMigrated::put(true);
+
+    let changes = histories_count.get();
+    T::DbWeight::get().reads_writes(changes, changes + 1)
+}
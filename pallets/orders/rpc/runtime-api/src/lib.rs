@@ -0,0 +1,26 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use sp_std::prelude::*;
+
+use pallet_orders::{OrderId, OrderState};
+
+sp_api::decl_runtime_apis! {
+	pub trait OrdersApi<AccountId> where
+		AccountId: Codec
+	{
+		fn get_order_ids_by_buyer(
+			buyer: AccountId,
+			state: Option<OrderState>,
+			offset: u64,
+			limit: u64,
+		) -> Vec<OrderId>;
+
+		fn get_order_ids_by_seller(
+			seller: AccountId,
+			state: Option<OrderState>,
+			offset: u64,
+			limit: u64,
+		) -> Vec<OrderId>;
+	}
+}
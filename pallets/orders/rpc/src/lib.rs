@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use codec::Codec;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+use pallet_orders::{OrderId, OrderState};
+pub use pallet_orders_rpc_runtime_api::OrdersApi as OrdersRuntimeApi;
+
+#[rpc]
+pub trait OrdersApi<BlockHash, AccountId> {
+	#[rpc(name = "orders_getOrderIdsByBuyer")]
+	fn get_order_ids_by_buyer(
+		&self,
+		buyer: AccountId,
+		state: Option<OrderState>,
+		offset: u64,
+		limit: u64,
+		at: Option<BlockHash>,
+	) -> Result<Vec<OrderId>>;
+
+	#[rpc(name = "orders_getOrderIdsBySeller")]
+	fn get_order_ids_by_seller(
+		&self,
+		seller: AccountId,
+		state: Option<OrderState>,
+		offset: u64,
+		limit: u64,
+		at: Option<BlockHash>,
+	) -> Result<Vec<OrderId>>;
+}
+
+/// A struct that implements the `OrdersApi`.
+pub struct Orders<C, Block> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<Block>,
+}
+
+impl<C, Block> Orders<C, Block> {
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: Default::default() }
+	}
+}
+
+/// Error code of a failed runtime API call.
+const RUNTIME_ERROR: i64 = 1;
+
+fn runtime_error(err: impl std::fmt::Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(RUNTIME_ERROR),
+		message: "Unable to query orders.".into(),
+		data: Some(format!("{:?}", err).into()),
+	}
+}
+
+impl<C, Block, AccountId> OrdersApi<<Block as BlockT>::Hash, AccountId> for Orders<C, Block>
+where
+	Block: BlockT,
+	C: Send + Sync + 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: OrdersRuntimeApi<Block, AccountId>,
+	AccountId: Codec,
+{
+	fn get_order_ids_by_buyer(
+		&self,
+		buyer: AccountId,
+		state: Option<OrderState>,
+		offset: u64,
+		limit: u64,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<OrderId>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.get_order_ids_by_buyer(&at, buyer, state, offset, limit).map_err(runtime_error)
+	}
+
+	fn get_order_ids_by_seller(
+		&self,
+		seller: AccountId,
+		state: Option<OrderState>,
+		offset: u64,
+		limit: u64,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<OrderId>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.get_order_ids_by_seller(&at, seller, state, offset, limit).map_err(runtime_error)
+	}
+}
@@ -8,11 +8,14 @@ use frame_support::{
     traits::{
         Currency, Get, ExistenceRequirement,
         OriginTrait, IsType, Filter,
+        Imbalance, OnUnbalanced, ReservableCurrency,
     },
     Parameter,
 };
 
+use pallet_products::Error as ProductsError;
 use pallet_utils::{StorefrontId, vec_remove_on};
+use sp_runtime::traits::{CheckedAdd, One, Saturating, Zero};
 
 use super::*;
 
@@ -22,12 +25,13 @@ impl<T: Trait> Order<T> {
         id: OrderId,
         created_by: T::AccountId,
         storefront_id: StorefrontId,
-        product_id: ProductId,
+        items: Vec<OrderItem<BalanceOf<T>>>,
         order_total: BalanceOf<T>,
         seller: T::AccountId,
         buyer_escrow: BalanceOf<T>,
         seller_escrow: BalanceOf<T>,
-        content: Content
+        content: Content,
+        buyer_message: Option<EncryptedMessage>
     ) -> Self {
         Order {
             id,
@@ -36,12 +40,15 @@ impl<T: Trait> Order<T> {
             owner: created_by,
             order_state: OrderState::New,
             storefront_id: storefront_id,
-            product_id: product_id,
+            items,
             order_total: order_total,
             seller: seller,
             buyer_escrow: buyer_escrow,
             seller_escrow: seller_escrow,
             content,
+            buyer_message,
+            seller_message: None,
+            deadline: None,
         }
     }
 
@@ -50,6 +57,30 @@ impl<T: Trait> Order<T> {
        self.owner == *account
    }
 
+    /// Total amount reserved by the buyer: the order total plus the buyer's escrow.
+    pub fn buyer_total(&self) -> BalanceOf<T> {
+        self.order_total.saturating_add(self.buyer_escrow)
+    }
+
+    /// Whether the account is either the buyer or the seller of this order.
+    pub fn is_involved(&self, account: &T::AccountId) -> bool {
+        self.is_owner(account) || self.seller == *account
+    }
+
+    /// Whether this order reached a final state and will not change anymore.
+    pub fn is_closed(&self) -> bool {
+        match self.order_state {
+            OrderState::Refused
+            | OrderState::Complete
+            | OrderState::Refunded
+            | OrderState::SlashedBuyer
+            | OrderState::SlashedSeller
+            | OrderState::SlashedBoth
+            | OrderState::Cancelled => true,
+            _ => false,
+        }
+    }
+
 
 
 
@@ -61,11 +92,40 @@ impl<T: Trait> Order<T> {
 
 
 
+impl<T: Trait> Dispute<T> {
+
+    pub fn new(
+        created_by: T::AccountId,
+        order: &Order<T>,
+        evidence: Content
+    ) -> Self {
+        let mut dispute = Dispute {
+            order_id: order.id,
+            created: WhoAndWhen::<T>::new(created_by.clone()),
+            updated: None,
+            disputed_state: order.order_state,
+            evidence: Vec::new(),
+            ruling: None,
+        };
+
+        dispute.evidence.push(DisputeEvidence {
+            created: WhoAndWhen::<T>::new(created_by),
+            content: evidence,
+        });
+        dispute
+    }
+
+    pub fn is_resolved(&self) -> bool {
+        self.ruling.is_some()
+    }
+}
+
 impl Default for OrderUpdate {
     fn default() -> Self {
         OrderUpdate {
             content: None,
-            order_state: OrderState::Pending
+            order_state: OrderState::Pending,
+            message: None
         }
     }
 }
@@ -78,46 +138,421 @@ impl<T: Trait> Module<T> {
     Ok(Self::order_by_id(order_id).ok_or(Error::<T>::OrderNotFound)?)
 }
 
-// pub fn u32_to_balance_option(input: u32) -> Option<BalanceOf<T>> {
-//   input.try_into().ok()
-// }
-
-    // Transfer tokens amount/entire free balance (if amount is `None`) from key account to owner
-    // pub fn fund_buyer_escrow(
-    //     buyer_account: &T::AccountId,
-    //     treasur: &T::AccountId,
-    //     amount: BalanceOf<T>
-    // ) -> DispatchResult {
-    //     T::Currency::transfer(
-    //         buyer_account,
-    //         treasur,
-    //         amount.unwrap(),
-    //         ExistenceRequirement::AllowDeath
-    //     )
-    // }
-
-// Fund buyer escrow
-// pub fn fund_buyer_escrow(from: T::AccountId, to: T::AccountId, value: u32) -> DispatchResult {
-    
-//     let sender_balance = Self::get_balance(&from);
-//     let receiver_balance = Self::get_balance(&to);
-  
-//     // Calculate new balances
-//     let updated_from_balance = sender_balance.checked_sub(value).ok_or(<Error<T>>::InsufficientFunds)?;
-//     let updated_to_balance = receiver_balance.checked_add(value).expect("Entire supply fits in u32; qed");
-  
-//     // Write new balances to storage
-//     <Balances<T>>::insert(&from, updated_from_balance);
-//     <Balances<T>>::insert(&to, updated_to_balance);
-  
-//     Self::deposit_event(RawEvent::Transfer(from, to, value));
-//     Ok(())
-//   }
-  
+    /// Resolve order line items from `(product_id, quantity)` pairs using on-chain product prices
+    /// and calculate the order total.
+    pub fn build_order_items(
+        storefront_id: StorefrontId,
+        items: Vec<(ProductId, u32)>
+    ) -> Result<(Vec<OrderItem<BalanceOf<T>>>, BalanceOf<T>), DispatchError> {
+        let mut order_items: Vec<OrderItem<BalanceOf<T>>> = Vec::new();
+        let mut order_total: BalanceOf<T> = Zero::zero();
+
+        for (product_id, quantity) in items {
+            ensure!(quantity > 0, Error::<T>::ZeroOrderQuantity);
+            ensure!(
+                !order_items.iter().any(|item| item.product_id == product_id),
+                Error::<T>::DuplicateProductInOrder
+            );
 
+            let product = Products::<T>::require_product(product_id)?;
 
+            // ensure given product_id belongs to the given store, if data incoherence, cancel order
+            ensure!(product.storefront_id == Some(storefront_id), Error::<T>::ProductIdNotFoundInGivenStorefront);
+            ensure!(!product.hidden, Error::<T>::CannotCreateInHiddenScope);
+            Products::<T>::ensure_product_is_not_blocked(product_id, storefront_id)?;
+            ensure!(product.has_stock(quantity), ProductsError::<T>::InsufficientProductStock);
 
-}
+            let price = product.price.ok_or(Error::<T>::ProductHasNoPrice)?;
+            let item_total = price.amount.checked_mul(&<BalanceOf<T>>::from(quantity))
+                .ok_or(Error::<T>::OrderTotalOverflow)?;
+            order_total = order_total.checked_add(&item_total)
+                .ok_or(Error::<T>::OrderTotalOverflow)?;
+
+            order_items.push(OrderItem {
+                product_id,
+                quantity,
+                unit_price: price.amount,
+            });
+        }
+
+        Ok((order_items, order_total))
+    }
+
+    /// Check that a ciphertext can hold an authentication tag plus a non-empty payload
+    /// and does not exceed `MaxOrderMessageLength`.
+    pub fn ensure_valid_message(message: &EncryptedMessage) -> DispatchResult {
+        let len = message.ciphertext.len();
+        ensure!(len > ENCRYPTED_MESSAGE_TAG_LEN, Error::<T>::EncryptedMessageTooShort);
+        ensure!(len <= T::MaxOrderMessageLength::get() as usize, Error::<T>::EncryptedMessageTooLong);
+        Ok(())
+    }
+
+    /// Put a message to the editor's side of the order: the buyer's or the seller's one.
+    /// A replaced message is saved to `old_data`.
+    pub fn set_order_message(
+        editor: &T::AccountId,
+        order: &mut Order<T>,
+        message: EncryptedMessage,
+        old_data: &mut OrderUpdate
+    ) -> DispatchResult {
+        Self::ensure_valid_message(&message)?;
+
+        if order.is_owner(editor) {
+            old_data.message = order.buyer_message.replace(message);
+        } else if Self::is_seller_or_has_permission(editor, order, StorefrontPermission::ViewOrderMessages) {
+            old_data.message = order.seller_message.replace(message);
+        } else {
+            return Err(Error::<T>::NotInvolvedInThisOrder.into());
+        }
+
+        Ok(())
+    }
+
+    /// Whether the account can act on behalf of the seller of this order:
+    /// it is the seller itself or it has a `permission` in the order's storefront.
+    pub fn is_seller_or_has_permission(
+        account: &T::AccountId,
+        order: &Order<T>,
+        permission: StorefrontPermission
+    ) -> bool {
+        if order.seller == *account {
+            return true;
+        }
+
+        Storefronts::<T>::require_storefront(order.storefront_id)
+            .and_then(|storefront| Storefronts::<T>::ensure_account_has_storefront_permission(
+                account.clone(),
+                &storefront,
+                permission,
+                Error::<T>::NotInvolvedInThisOrder.into()
+            ))
+            .is_ok()
+    }
+
+    /// Get a page of ids of orders made by the buyer, optionally only the ones in a given `state`.
+    pub fn get_order_ids_by_buyer(
+        buyer: T::AccountId,
+        state: Option<OrderState>,
+        offset: u64,
+        limit: u64
+    ) -> Vec<OrderId> {
+        Self::paginate_order_ids_in_state(Self::order_id_by_account(buyer), state, offset, limit)
+    }
+
+    /// Get a page of ids of orders of the storefronts that the seller owns now,
+    /// optionally only the ones in a given `state`.
+    pub fn get_order_ids_by_seller(
+        seller: T::AccountId,
+        state: Option<OrderState>,
+        offset: u64,
+        limit: u64
+    ) -> Vec<OrderId> {
+        let mut order_ids: Vec<OrderId> = Storefronts::<T>::storefront_ids_by_owner(seller)
+            .into_iter()
+            .flat_map(|storefront_id| OrderIdsByStorefrontId::get(storefront_id))
+            .collect();
+        order_ids.sort_unstable();
+
+        Self::paginate_order_ids_in_state(order_ids, state, offset, limit)
+    }
+
+    fn paginate_order_ids_in_state(
+        order_ids: Vec<OrderId>,
+        state: Option<OrderState>,
+        offset: u64,
+        limit: u64
+    ) -> Vec<OrderId> {
+        let order_ids = order_ids.into_iter().filter(|order_id| match state {
+            Some(state) => Self::order_by_id(order_id).map_or(false, |order| order.order_state == state),
+            None => true,
+        });
+
+        paginate(order_ids, offset, limit)
+    }
+
+    /// Check that the account can act on behalf of the seller of this order or return `error`.
+    pub fn ensure_seller_permission(
+        account: &T::AccountId,
+        order: &Order<T>,
+        permission: StorefrontPermission,
+        error: Error<T>
+    ) -> DispatchResult {
+        ensure!(Self::is_seller_or_has_permission(account, order, permission), error);
+        Ok(())
+    }
+
+    /// Get a `Dispute` of the order that has no ruling yet.
+    pub fn require_open_dispute(order_id: OrderId) -> Result<Dispute<T>, DispatchError> {
+        let dispute = Self::dispute_by_order_id(order_id).ok_or(Error::<T>::DisputeNotFound)?;
+        ensure!(!dispute.is_resolved(), Error::<T>::DisputeAlreadyResolved);
+        Ok(dispute)
+    }
+
+    pub fn is_dispute_arbiter(account: &T::AccountId) -> bool {
+        Self::dispute_arbiters().contains(account)
+    }
+
+    /// Move the order to a new state, save it and notify `AfterOrderUpdated` handlers.
+    pub fn change_order_state(editor: T::AccountId, order: &mut Order<T>, new_state: OrderState) {
+        let old_data = OrderUpdate {
+            content: None,
+            order_state: order.order_state,
+            message: None,
+        };
+
+        order.order_state = new_state;
+        order.updated = Some(WhoAndWhen::<T>::new(editor.clone()));
+        Self::clear_stale_cancellation_request(order);
+
+        <OrderById<T>>::insert(order.id, order.clone());
+        Self::release_order_storage_deposit(order);
+        T::AfterOrderUpdated::after_order_updated(editor.clone(), order, old_data);
+
+        Self::deposit_event(RawEvent::OrderUpdated(editor, order.id, new_state));
+    }
+
+    /// Return the buyer's storage deposit once the order is closed and will not change anymore.
+    pub fn release_order_storage_deposit(order: &Order<T>) {
+        if order.is_closed() {
+            if let Some(deposit) = StorageDepositByOrderId::<T>::take(order.id) {
+                Utils::<T>::release_storage_deposit(deposit);
+            }
+        }
+    }
+
+    /// Forget a cancellation request once the order has left the accepted and shipped states,
+    /// so that it cannot be agreed to later, e.g. after a dispute.
+    pub fn clear_stale_cancellation_request(order: &Order<T>) {
+        match order.order_state {
+            OrderState::Accepted | OrderState::Shipped => (),
+            _ => CancellationRequestedBy::<T>::remove(order.id),
+        }
+    }
+
+    /// Set a block at which the order will be processed automatically if nobody acts on it.
+    pub fn schedule_order_deadline(order: &mut Order<T>, timeout: T::BlockNumber) {
+        Self::cancel_order_deadline(order);
+
+        // The deadline must be at least the next block, as `on_initialize` of the current one has passed.
+        let now = <system::Module<T>>::block_number();
+        let deadline = now.saturating_add(timeout.max(One::one()));
+
+        OrderIdsByDeadline::<T>::mutate(deadline, |ids| ids.push(order.id));
+        order.deadline = Some(deadline);
+    }
+
+    pub fn cancel_order_deadline(order: &mut Order<T>) {
+        if let Some(deadline) = order.deadline.take() {
+            OrderIdsByDeadline::<T>::mutate(deadline, |ids| vec_remove_on(ids, order.id));
+        }
+    }
+
+    /// Auto-refuse or auto-complete orders whose deadline has come.
+    /// At most `MaxExpiredOrdersPerBlock` deadline blocks and orders are processed at once,
+    /// the rest of them stay in `OrderIdsByDeadline` and are processed in the next blocks.
+    pub fn process_expired_orders(now: T::BlockNumber) -> Weight {
+        let mut budget = T::MaxExpiredOrdersPerBlock::get() as usize;
+        let mut block = Self::next_deadline_to_process().unwrap_or(now);
+        let mut orders_count: Weight = 0;
+        let mut blocks_count: Weight = 0;
+
+        while block <= now && budget > 0 {
+            let mut order_ids = OrderIdsByDeadline::<T>::take(block);
+            blocks_count += 1;
+            budget -= 1;
+
+            let rest = if order_ids.len() > budget {
+                order_ids.split_off(budget)
+            } else {
+                Vec::new()
+            };
+            budget -= order_ids.len();
+
+            for order_id in order_ids {
+                Self::process_expired_order(order_id);
+                orders_count += 1;
+            }
+
+            if !rest.is_empty() {
+                // Orders of this block keep their deadline, so they can still be cancelled from here.
+                OrderIdsByDeadline::<T>::insert(block, rest);
+                break;
+            }
+
+            block = block.saturating_add(One::one());
+        }
+
+        NextDeadlineToProcess::<T>::put(block);
 
+        T::DbWeight::get().reads_writes(
+            1 + blocks_count + orders_count * 2,
+            1 + blocks_count + orders_count * 4
+        )
+    }
+
+    fn process_expired_order(order_id: OrderId) {
+        if let Some(mut order) = Self::order_by_id(order_id) {
+            order.deadline = None;
+
+            match order.order_state {
+                OrderState::New | OrderState::Pending => {
+                    Self::release_buyer_funds(&order);
+                    Self::change_order_state(order.seller.clone(), &mut order, OrderState::Refused);
+                    Self::deposit_event(RawEvent::OrderAutoRefused(order_id));
+                }
+                OrderState::Shipped => {
+                    if Self::complete_order(&order).is_ok() {
+                        Self::change_order_state(order.owner.clone(), &mut order, OrderState::Complete);
+                        Self::deposit_event(RawEvent::OrderAutoCompleted(order_id));
+                    } else {
+                        // Retry later, the buyer can still confirm or dispute the order meanwhile.
+                        Self::schedule_order_deadline(&mut order, T::ConfirmOrderTimeout::get());
+                        <OrderById<T>>::insert(order_id, order);
+                    }
+                }
+                // The order has been moved on already, e.g. to a dispute.
+                _ => (),
+            }
+        }
+    }
+
+    /// Return reserved funds and escrow to the buyer when the order is refused or cancelled.
+    pub fn release_buyer_funds(order: &Order<T>) {
+        Self::unreserve_buyer_funds(order);
+        Self::restore_order_stock(order);
+    }
+
+    /// Return reserved funds and escrow to the buyer without returning the order items to stock.
+    pub fn unreserve_buyer_funds(order: &Order<T>) {
+        <T as pallet_utils::Trait>::Currency::unreserve(&order.owner, order.buyer_total());
+        Self::deposit_event(RawEvent::UnlockEscrowFunds(order.owner.clone(), order.id));
+    }
+
+    /// Return items of an order that will not be sold back to product stocks.
+    pub fn restore_order_stock(order: &Order<T>) {
+        for item in order.items.iter() {
+            Products::<T>::return_product_stock(item.product_id, item.quantity);
+        }
+    }
 
+    /// A marketplace fee applied to orders of the storefront.
+    pub fn marketplace_fee(storefront_id: StorefrontId) -> Perbill {
+        Self::marketplace_fee_override_by_storefront_id(storefront_id)
+            .unwrap_or_else(T::MarketplaceFee::get)
+    }
+
+    /// Pay the order total reserved by the buyer to the storefront account,
+    /// minus a marketplace fee that goes to the treasury.
+    /// A seller share below the existential deposit cannot create a storefront account,
+    /// so it goes to the treasury together with the fee.
+    fn pay_seller(order: &Order<T>) -> DispatchResult {
+        let buyer = &order.owner;
+        let storefront_account = Storefronts::<T>::storefront_account(order.storefront_id);
 
+        let mut fee = Self::marketplace_fee(order.storefront_id) * order.order_total;
+        let mut seller_share = order.order_total.saturating_sub(fee);
+
+        let is_new_storefront_account =
+            <T as pallet_utils::Trait>::Currency::total_balance(&storefront_account).is_zero();
+        if is_new_storefront_account && seller_share < <T as pallet_utils::Trait>::Currency::minimum_balance() {
+            fee = order.order_total;
+            seller_share = Zero::zero();
+        }
+
+        // Reserved funds cannot be repatriated to an account that does not exist yet,
+        // which is the case for a storefront that has never been paid before.
+        if !seller_share.is_zero() {
+            <T as pallet_utils::Trait>::Currency::unreserve(buyer, seller_share);
+            if let Err(error) = <T as pallet_utils::Trait>::Currency::transfer(
+                buyer, &storefront_account, seller_share, ExistenceRequirement::AllowDeath
+            ) {
+                // The order stays unpaid, so the buyer's funds must stay reserved.
+                let _ = <T as pallet_utils::Trait>::Currency::reserve(buyer, seller_share);
+                return Err(error);
+            }
+        }
+
+        if !fee.is_zero() {
+            let (imbalance, _) = <T as pallet_utils::Trait>::Currency::slash_reserved(buyer, fee);
+            let charged = imbalance.peek();
+
+            <Utils<T> as OnUnbalanced<_>>::on_unbalanced(imbalance);
+            Self::deposit_event(RawEvent::OrderFeeCharged(order.id, charged));
+        }
+
+        Ok(())
+    }
+
+    /// Pay the seller from the buyer's reserved funds and release escrows of both parties.
+    pub fn complete_order(order: &Order<T>) -> DispatchResult {
+        let buyer = &order.owner;
+        let seller = &order.seller;
+
+        Self::pay_seller(order)?;
+        Self::deposit_event(RawEvent::OrderCompleteConfirmation(buyer.clone(), order.id));
+
+        <T as pallet_utils::Trait>::Currency::unreserve(buyer, order.buyer_escrow);
+        Self::deposit_event(RawEvent::UnlockEscrowFunds(buyer.clone(), order.id));
+
+        <T as pallet_utils::Trait>::Currency::unreserve(seller, order.seller_escrow);
+        Self::deposit_event(RawEvent::UnlockEscrowFunds(seller.clone(), order.id));
+
+        Ok(())
+    }
+
+    /// Release or slash funds reserved by the buyer and the seller according to a dispute ruling.
+    pub fn settle_dispute_funds(order: &Order<T>, ruling: OrderState) -> DispatchResult {
+        let buyer = &order.owner;
+        let seller = &order.seller;
+
+        match ruling {
+            OrderState::Refunded => {
+                <T as pallet_utils::Trait>::Currency::unreserve(buyer, order.buyer_total());
+                Self::deposit_event(RawEvent::OrderRefunded(buyer.clone(), order.id));
+                Self::restore_order_stock(order);
+
+                <T as pallet_utils::Trait>::Currency::unreserve(seller, order.seller_escrow);
+                Self::deposit_event(RawEvent::UnlockEscrowFunds(seller.clone(), order.id));
+            }
+
+            OrderState::SlashedBuyer => {
+                // The buyer's claim is rejected, so the seller gets paid for the order.
+                Self::pay_seller(order)?;
+                Self::deposit_event(RawEvent::EscrowFundsRepatriated(buyer.clone(), order.id, order.order_total));
+
+                Self::slash_reserved_to_treasury(buyer, order.id, order.buyer_escrow);
+
+                <T as pallet_utils::Trait>::Currency::unreserve(seller, order.seller_escrow);
+                Self::deposit_event(RawEvent::UnlockEscrowFunds(seller.clone(), order.id));
+            }
+
+            OrderState::SlashedSeller => {
+                <T as pallet_utils::Trait>::Currency::unreserve(buyer, order.buyer_total());
+                Self::deposit_event(RawEvent::OrderRefunded(buyer.clone(), order.id));
+
+                Self::slash_reserved_to_treasury(seller, order.id, order.seller_escrow);
+            }
+
+            OrderState::SlashedBoth => {
+                // The goods payment goes back to the buyer, but both escrows are lost.
+                <T as pallet_utils::Trait>::Currency::unreserve(buyer, order.order_total);
+                Self::deposit_event(RawEvent::OrderRefunded(buyer.clone(), order.id));
+
+                Self::slash_reserved_to_treasury(buyer, order.id, order.buyer_escrow);
+                Self::slash_reserved_to_treasury(seller, order.id, order.seller_escrow);
+            }
+
+            _ => return Err(Error::<T>::InvalidDisputeRuling.into()),
+        }
+
+        Ok(())
+    }
+
+    fn slash_reserved_to_treasury(who: &T::AccountId, order_id: OrderId, amount: BalanceOf<T>) {
+        let (imbalance, _) = <T as pallet_utils::Trait>::Currency::slash_reserved(who, amount);
+        let slashed = imbalance.peek();
+
+        <Utils<T> as OnUnbalanced<_>>::on_unbalanced(imbalance);
+        Self::deposit_event(RawEvent::EscrowFundsSlashed(who.clone(), order_id, slashed));
+    }
+}
@@ -1,31 +1,35 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use codec::{Decode, Encode};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
 use frame_support::{
     decl_error, decl_event, decl_module, decl_storage, ensure,
     dispatch::{DispatchResult},
-    traits::{ ReservableCurrency, Currency, Get, ExistenceRequirement,
+    traits::{ ReservableCurrency, Currency, Get,
       },
+    weights::Weight,
     debug,
 };
-use sp_runtime::RuntimeDebug;
+use sp_runtime::{RuntimeDebug, Percent, Perbill, traits::{CheckedMul, Saturating}};
 use sp_std::prelude::*;
-use frame_system::{self as system, ensure_signed};
-
-// use pallet_permissions::StorefrontPermission;
-use pallet_storefronts::{Module as Storefronts, Storefront, StorefrontById};
-use pallet_utils::{Module as Utils, StorefrontId, WhoAndWhen, Content};
-use pallet_products::{Module as Products, Product, ProductById, ProductId};
-
-// use core::convert::TryInto;
+use frame_system::{self as system, ensure_signed, ensure_root};
 
+use pallet_permissions::StorefrontPermission;
+use pallet_storefronts::{Module as Storefronts, BeforeStorefrontDeleted, Storefront, StorefrontById};
+use pallet_utils::{Module as Utils, StorefrontId, WhoAndWhen, Content, StorageDeposit, StorageDepositOf, vec_remove_on, paginate};
+use pallet_products::{Module as Products, BeforeProductDeleted, Product, ProductById, ProductId};
 
 pub mod functions;
+mod migration;
 
 pub type BalanceOf<T> = <<T as pallet_utils::Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
 
 pub type OrderId = u64;
 
+/// Length of the Poly1305 authentication tag prepended to every encrypted message.
+pub const ENCRYPTED_MESSAGE_TAG_LEN: usize = 16;
+
 
 
 // Order
@@ -41,12 +45,31 @@ pub struct Order<T: Trait> {
     pub buyer_escrow: BalanceOf<T>,
     pub seller_escrow: BalanceOf<T>,
     pub storefront_id: StorefrontId,
-    pub product_id: ProductId,
+    pub items: Vec<OrderItem<BalanceOf<T>>>,
     pub content: Content,
+
+    /// The latest encrypted message from the buyer to the seller, e.g. shipping details.
+    pub buyer_message: Option<EncryptedMessage>,
+    /// The latest encrypted message from the seller to the buyer, e.g. tracking info.
+    pub seller_message: Option<EncryptedMessage>,
+
+    /// A block at which the order will be auto-refused (if not accepted yet)
+    /// or auto-completed (if shipped but not confirmed by the buyer).
+    pub deadline: Option<T::BlockNumber>,
 }
 
 
+// Order line item
+#[derive(Encode, Decode, Clone, Copy, Eq, PartialEq, RuntimeDebug)]
+pub struct OrderItem<Balance> {
+    pub product_id: ProductId,
+    pub quantity: u32,
+    /// A product price at the moment the order was created.
+    pub unit_price: Balance,
+}
+
 #[derive(Encode, Decode, Clone, Copy, Eq, PartialEq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub enum OrderState {
     New,
     Pending,
@@ -59,6 +82,7 @@ pub enum OrderState {
     SlashedBuyer,
     SlashedSeller,
     SlashedBoth,
+    Cancelled,
 }
 
 impl Default for OrderState {
@@ -73,9 +97,61 @@ impl Default for OrderState {
 pub struct OrderUpdate {
     pub content: Option<Content>,
     pub order_state: OrderState,
+    /// A message from the editor to the other party of the order.
+    /// Replaces the buyer's message if sent by the buyer, or the seller's one if sent by the seller.
+    pub message: Option<EncryptedMessage>,
 }
 
+/// A message encrypted off-chain with the recipient's public key, e.g. a shipping address
+/// encrypted to the seller or a tracking number encrypted to the buyer.
+/// The chain only checks the format of the message and never sees the plaintext.
+#[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
+pub struct EncryptedMessage {
+    /// An X25519 public key of the sender used to derive the shared secret.
+    pub public_key: [u8; 32],
+    /// A nonce of the XSalsa20-Poly1305 box.
+    pub nonce: [u8; 24],
+    /// An encrypted payload including its authentication tag.
+    pub ciphertext: Vec<u8>,
+}
+
+// Storefront order settings
+/// Escrow amounts applied to new orders of a storefront.
+/// Both are a percentage of the order total.
+#[derive(Encode, Decode, Clone, Copy, Default, Eq, PartialEq, RuntimeDebug)]
+pub struct StorefrontOrderSettings {
+    pub buyer_escrow: Percent,
+    pub seller_escrow: Percent,
+}
+
+#[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
+pub struct StorefrontOrderSettingsUpdate {
+    pub buyer_escrow: Option<Percent>,
+    pub seller_escrow: Option<Percent>,
+}
+
+// Dispute
+#[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
+pub struct Dispute<T: Trait> {
+    pub order_id: OrderId,
+    pub created: WhoAndWhen<T>,
+    pub updated: Option<WhoAndWhen<T>>,
+
+    /// The state the order was in when the dispute has been opened.
+    pub disputed_state: OrderState,
+
+    /// Evidence submitted by the buyer and the seller, in chronological order.
+    pub evidence: Vec<DisputeEvidence<T>>,
+
+    /// A final order state chosen by an arbiter. `None` while the dispute is open.
+    pub ruling: Option<OrderState>,
+}
 
+#[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
+pub struct DisputeEvidence<T: Trait> {
+    pub created: WhoAndWhen<T>,
+    pub content: Content,
+}
 
 
 
@@ -88,10 +164,30 @@ pub trait Trait: system::Trait
 {
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
     type AfterOrderUpdated: AfterOrderUpdated<Self>;
-   // type TestAmount: Get<BalanceOf<Self>>;
 
+    /// Max number of different products (line items) in a single order.
+    type MaxOrderItems: Get<u16>;
+
+    /// Max length of a ciphertext of an encrypted order message.
+    type MaxOrderMessageLength: Get<u32>;
 
+    /// Max number of evidence items that can be submitted to a single dispute.
+    type MaxDisputeEvidence: Get<u16>;
 
+    /// Number of blocks a seller has to accept a new order before it gets auto-refused.
+    type AcceptOrderTimeout: Get<Self::BlockNumber>;
+
+    /// Number of blocks a buyer has to confirm a shipped order or open a dispute
+    /// before the order gets auto-completed.
+    type ConfirmOrderTimeout: Get<Self::BlockNumber>;
+
+    /// Max number of deadline blocks and expired orders processed in a single block.
+    /// Expired orders over this limit are processed in the next blocks.
+    type MaxExpiredOrdersPerBlock: Get<u32>;
+
+    /// A share of each paid order total that goes to the treasury.
+    /// Can be overridden per storefront by root.
+    type MarketplaceFee: Get<Perbill>;
 }
 
 #[impl_trait_for_tuples::impl_for_tuples(10)]
@@ -102,13 +198,36 @@ pub trait AfterOrderUpdated<T: Trait> {
 decl_event!(
     pub enum Event<T> where
         <T as system::Trait>::AccountId,
+        Balance = BalanceOf<T>,
     {
         OrderCreated(AccountId, OrderId),
         OrderUpdated(AccountId, OrderId, OrderState),
         OrderDeleted(AccountId, OrderId),
+        OrderMessageSent(AccountId, OrderId),
+        /// One party of an accepted order asked to cancel it. Waiting for the other party.
+        OrderCancellationRequested(AccountId, OrderId),
+        OrderCancelled(AccountId, OrderId),
         LockEscrowFunds(AccountId, OrderId),
         UnlockEscrowFunds(AccountId, OrderId),
         OrderCompleteConfirmation(AccountId, OrderId),
+        DisputeOpened(AccountId, OrderId),
+        DisputeEvidenceAdded(AccountId, OrderId),
+        DisputeResolved(AccountId, OrderId, OrderState),
+        OrderRefunded(AccountId, OrderId),
+        /// Reserved funds of the account were slashed to the treasury.
+        EscrowFundsSlashed(AccountId, OrderId, Balance),
+        /// Reserved funds of the buyer were paid to the seller by a dispute ruling.
+        EscrowFundsRepatriated(AccountId, OrderId, Balance),
+        StorefrontOrderSettingsUpdated(AccountId, StorefrontId),
+        DisputeArbiterAdded(AccountId),
+        DisputeArbiterRemoved(AccountId),
+        /// The seller has not accepted the order in time.
+        OrderAutoRefused(OrderId),
+        /// The buyer has neither confirmed nor disputed the shipped order in time.
+        OrderAutoCompleted(OrderId),
+        /// A marketplace fee was taken from the order total and sent to the treasury.
+        OrderFeeCharged(OrderId, Balance),
+        MarketplaceFeeOverrideSet(StorefrontId, Option<Perbill>),
     }
 );
 
@@ -128,9 +247,36 @@ decl_storage! {
         pub OrderIdsByAccount get(fn order_id_by_account):
              map hasher(twox_64_concat) T::AccountId => Vec<OrderId>;
 
-        // pub BalanceOf get(fn balance_of): 
-        //      map hasher(twox_64_concat) T::AccountId => u64;
-             
+        pub DisputeByOrderId get(fn dispute_by_order_id):
+             map hasher(twox_64_concat) OrderId => Option<Dispute<T>>;
+
+        /// Accounts designated by root to rule on order disputes.
+        pub DisputeArbiters get(fn dispute_arbiters): Vec<T::AccountId>;
+
+        pub OrderSettingsByStorefrontId get(fn order_settings_by_storefront_id):
+             map hasher(twox_64_concat) StorefrontId => StorefrontOrderSettings;
+
+        /// A marketplace fee set by root for a storefront instead of the default `MarketplaceFee`.
+        pub MarketplaceFeeOverrideByStorefrontId get(fn marketplace_fee_override_by_storefront_id):
+             map hasher(twox_64_concat) StorefrontId => Option<Perbill>;
+
+        /// A party of an accepted order that asked to cancel it.
+        pub CancellationRequestedBy get(fn cancellation_requested_by):
+             map hasher(twox_64_concat) OrderId => Option<T::AccountId>;
+
+        pub OrderIdsByDeadline get(fn order_ids_by_deadline):
+             map hasher(twox_64_concat) T::BlockNumber => Vec<OrderId>;
+
+        /// The earliest deadline block that may still have expired orders to process.
+        pub NextDeadlineToProcess get(fn next_deadline_to_process): Option<T::BlockNumber>;
+
+        /// A storage deposit of the buyer. It is released once the order is closed.
+        pub StorageDepositByOrderId get(fn storage_deposit_by_order_id):
+             map hasher(twox_64_concat) OrderId => Option<StorageDepositOf<T>>;
+
+        /// Whether orders are stored in the current layout. New chains start with it.
+        pub IsOrderLayoutMigrated get(fn is_order_layout_migrated)
+            build(|_config: &GenesisConfig| true): bool;
     }
 }
 
@@ -141,19 +287,83 @@ decl_error! {
         CannotCreateInHiddenScope,
         ProductIdNotFoundInGivenStorefront,
         CanNotOrderOwnProducts,
-        OrderGivenSellerIsNotProductOwner,
+        /// Product has no price, so it cannot be ordered.
+        ProductHasNoPrice,
+        /// Order quantity must be greater than zero.
+        ZeroOrderQuantity,
+        /// Order total overflows when summing up the product prices multiplied by quantities.
+        OrderTotalOverflow,
+        /// Order total is greater than the max total the buyer agreed to pay.
+        OrderTotalExceedsMaxTotal,
+        /// Order must contain at least one item.
+        NoItemsInOrder,
+        TooManyOrderItems,
+        /// The same product appears in the order more than once.
+        DuplicateProductInOrder,
+        /// A product cannot be deleted while it has orders that are not closed yet.
+        ProductHasOpenOrders,
+        /// A storefront cannot be deleted while it has orders that are not closed yet.
+        StorefrontHasOpenOrders,
+
+        // Cancellation related errors:
+        /// Use `cancel_order` to move an order to the `Cancelled` state.
+        OrderMustBeCancelledExplicitly,
+        /// Only the buyer can cancel an order that is not accepted yet. The seller should refuse it.
+        OnlyBuyerCanCancelNotAcceptedOrder,
+        /// Only new, pending, accepted or shipped orders can be cancelled.
+        OrderCannotBeCancelled,
+        CancellationAlreadyRequested,
+
+        // Message related errors:
+        /// Ciphertext is too short to contain an authentication tag and a payload.
+        EncryptedMessageTooShort,
+        EncryptedMessageTooLong,
         NoUpdatesForOrder,
         OrderNotFound,
         ProductNotFound,
         MustWaitSellerAcceptsOrder,
         MustWaitBuyerConfirmsShipment,
         OrderStateDoesNotExpectUpdate,
-        OrderFLowNotFullyImplementedYet,
         InsufficientFunds,
         OrderUpdateStateCanNotBeNew,
         NotInvolvedInThisOrder,
         MustWaitSellerShipsOrder,
         OnlyOrderOwnerCanComplete,
+        OnlySellerCanShipOrder,
+        /// User has no permission to accept or refuse orders of this storefront.
+        NoPermissionToAcceptOrders,
+        /// User has no permission to ship orders of this storefront.
+        NoPermissionToShipOrders,
+        /// User has no permission to cancel orders of this storefront with a refund.
+        NoPermissionToRefundOrders,
+        /// User has no permission to send order messages on behalf of the seller.
+        NoPermissionToViewOrderMessages,
+        /// User has no permission to open disputes or add evidence on behalf of the seller.
+        NoPermissionToOpenDisputes,
+        NoUpdatesForStorefrontOrderSettings,
+        /// User has no permission to update order settings of this storefront.
+        NoPermissionToUpdateStorefrontSettings,
+
+        // Dispute related errors:
+        /// Use `open_dispute` to move an order to the `Dispute` state.
+        DisputeMustBeOpenedExplicitly,
+        /// Refunded and slashed states can only be set by a dispute ruling.
+        StateIsSetByDisputeRuling,
+        /// A dispute can be opened only for accepted or shipped orders.
+        OrderCannotBeDisputed,
+        DisputeNotFound,
+        DisputeAlreadyResolved,
+        TooManyDisputeEvidence,
+        /// A dispute ruling must be one of the refunded or slashed states.
+        InvalidDisputeRuling,
+        /// Only dispute arbiters, the seller or accounts with `ResolveDisputes` permission can settle disputes.
+        NotADisputeArbiter,
+        /// The seller side can settle a dispute only by refunding the buyer.
+        SellerCanOnlyRefundDispute,
+        /// An arbiter cannot rule on a dispute where they are the buyer or the seller.
+        ArbiterIsInvolvedInThisOrder,
+        DisputeArbiterAlreadyAdded,
+        DisputeArbiterNotFound,
     }
 }
 
@@ -168,100 +378,120 @@ decl_module! {
     // Initializing events
     fn deposit_event() = default;
 
+    const MaxOrderItems: u16 = T::MaxOrderItems::get();
+
+    const MaxOrderMessageLength: u32 = T::MaxOrderMessageLength::get();
+
+    const AcceptOrderTimeout: T::BlockNumber = T::AcceptOrderTimeout::get();
+
+    const ConfirmOrderTimeout: T::BlockNumber = T::ConfirmOrderTimeout::get();
+
+    const MaxExpiredOrdersPerBlock: u32 = T::MaxExpiredOrdersPerBlock::get();
+
+    const MarketplaceFee: Perbill = T::MarketplaceFee::get();
+
+    fn on_initialize(block_number: T::BlockNumber) -> Weight {
+      Self::process_expired_orders(block_number)
+    }
+
+    fn on_runtime_upgrade() -> Weight {
+      if Self::is_order_layout_migrated() {
+        return 0;
+      }
+      migration::migrate_orders::<T>()
+    }
+
     
     
     // create order
-    #[weight = 100_000 + T::DbWeight::get().reads_writes(8, 8)]
+    /// Create an order of `items`, given as pairs of a product id and a quantity,
+    /// that all belong to the storefront `storefront_id`.
+    /// The order fails if its total calculated from the current product prices exceeds `max_total`,
+    /// so a buyer never pays more than they agreed to, even if a price has been changed meanwhile.
+    #[weight = 100_000 + T::DbWeight::get().reads_writes(
+      6 + items.len() as u64,
+      7 + items.len() as u64
+    )]
     pub fn create_order(
       origin,
       storefront_id: StorefrontId,
-      product_id: ProductId,
-      order_total: BalanceOf<T>,
-      seller: T::AccountId,
-      buyer_escrow: BalanceOf<T>,
-      seller_escrow: BalanceOf<T>,
-      content: Content
+      items: Vec<(ProductId, u32)>,
+      max_total: BalanceOf<T>,
+      content: Content,
+      buyer_message: Option<EncryptedMessage>
     ) -> DispatchResult {
       let creator = ensure_signed(origin)?;
 
       Utils::<T>::is_valid_content(content.clone())?;
 
-      let new_order_id = Self::next_order_id();
-      let new_order: Order<T> = Order::new(new_order_id, creator.clone(), storefront_id, product_id, order_total, seller, buyer_escrow, seller_escrow, content);
-     //let new_order: Order<T> = Order::new(new_order_id, creator.clone(), storefront_id, product_id, order_total, seller,  content);
+      if let Some(ref message) = buyer_message {
+        Self::ensure_valid_message(message)?;
+      }
 
-      // get the product by id
-      let product = &mut Products::<T>::require_product(product_id)?;
+      ensure!(!items.is_empty(), Error::<T>::NoItemsInOrder);
+      ensure!(items.len() <= T::MaxOrderItems::get() as usize, Error::<T>::TooManyOrderItems);
 
       //get the storefront by id
       let storefront = &mut Storefronts::<T>::require_storefront(storefront_id)?;
-      //Storefronts::<T>::ensure_storefront_exists(storefront_id)?; // double check ??
-     
 
-        // ensure given product_id belongs to the given store, if data incoherence, cancel order
-        if let Some(product_storefront_id) = product.storefront_id {
-        if storefront_id != product_storefront_id {
-          //Storefronts::<T>::ensure_storefront_exists(storefront_id)?;
-          return Err(Error::<T>::ProductIdNotFoundInGivenStorefront.into());
-        }
-    }
       // no order on hidden
       ensure!(!storefront.hidden, Error::<T>::CannotCreateInHiddenScope);
-      ensure!(!product.hidden, Error::<T>::CannotCreateInHiddenScope);
 
-      // check seller vs store/product owner
-      let product_seller = &storefront.owner;
-      ensure!(!storefront.is_owner(&creator), Error::<T>::CanNotOrderOwnProducts);
-      if product_seller != &storefront.owner {
-        return Err(Error::<T>::OrderGivenSellerIsNotProductOwner.into());
-      }
+      Storefronts::<T>::ensure_storefront_is_not_blocked(storefront)?;
+      Storefronts::<T>::ensure_account_is_not_blocked(creator.clone(), storefront_id)?;
+      Storefronts::<T>::ensure_content_is_not_blocked(content.clone(), storefront_id)?;
 
-      // simple escrow PoC
+      // the price is always taken from the products, the buyer cannot choose what to pay
+      let (order_items, order_total) = Self::build_order_items(storefront_id, items)?;
+      ensure!(order_total <= max_total, Error::<T>::OrderTotalExceedsMaxTotal);
 
-      //calc escrow buyer
-      //let buyer_escrow_total = Utils::<T>::u32_to_balance(new_order.order_total + new_order.buyer_escrow);
-      let buyer = &creator.clone();
-      <T as pallet_utils::Trait>::Currency::reserve(buyer,order_total)
-      .map_err(|_| "buyer can't afford to lock the amount requested")?;
-      
-      Self::deposit_event(RawEvent::LockEscrowFunds(buyer.clone(), new_order_id));
-      //T::Currency::transfer(&creator, &Utils::<T>::treasury_account(), BalanceOf::<T>::buyer_escrow_total.into(), ExistenceRequirement::KeepAlive);
+      // the seller is always the storefront owner
+      ensure!(!storefront.is_owner(&creator), Error::<T>::CanNotOrderOwnProducts);
 
+      // escrows are always taken from the storefront settings, so neither party can bypass them
+      let settings = Self::order_settings_by_storefront_id(storefront_id);
+      let buyer_escrow = settings.buyer_escrow * order_total;
+      let seller_escrow = settings.seller_escrow * order_total;
 
-     // <T as pallet_utils::Trait>::Currency::transfer(&creator, &Utils::<T>::treasury_account(), BalanceOf<T>::buyer_escrow_total.into(), ExistenceRequirement::KeepAlive);
-     // Self::fund_buyer_escrow(&creator, &Utils::<T>::treasury_account(), buyer_escrow_total.into())?;
+      let seller = storefront.owner.clone();
+      let new_order_id = Self::next_order_id();
+      let mut new_order: Order<T> = Order::new(
+        new_order_id, creator.clone(), storefront_id, order_items,
+        order_total, seller, buyer_escrow, seller_escrow, content, buyer_message
+      );
 
-      // <T as pallet_utils::Trait>::Currency::transfer(
-      //   &creator,
-      //   &Utils::<T>::treasury_account(),
-      //   BalanceOf::<T>::from(buyer_escrow_total), // .into() injecting u32 balance / price
-      //   ExistenceRequirement::KeepAlive
-      // )?;
+      let buyer = &creator.clone();
+      let storage_deposit = Utils::<T>::storage_deposit(new_order.encode().len());
+      let buyer_reserve = order_total.saturating_add(buyer_escrow).saturating_add(storage_deposit);
+      ensure!(
+        <T as pallet_utils::Trait>::Currency::can_reserve(buyer, buyer_reserve),
+        Error::<T>::InsufficientFunds
+      );
+
+      // Nothing can fail from here: stock of every item was checked while building the order items
+      // and the buyer's funds were checked above.
+      for item in new_order.items.iter() {
+        Products::<T>::take_product_stock(item.product_id, item.quantity)?;
+      }
 
-      //Self::deposit_event(RawEvent::EscrowInitiated(&creator, new_order_id));
-       // order history
-       OrderIdsByStorefrontId::mutate(storefront.id, |ids| ids.push(new_order_id));
-       OrderIdsByProductId::mutate(product.id, |ids| ids.push(new_order_id));
-       OrderById::<T>::insert(new_order_id, new_order);
-       OrderIdsByAccount::<T>::mutate(&creator, |ids| ids.push(new_order_id));
-    
-      // TODO : Escrow !!
-      // TODO : Alpha1 release : simple % lock ? based on OrderState ? Full-Auto ? Sudo?
+      <T as pallet_utils::Trait>::Currency::reserve(buyer, buyer_reserve)
+      .map_err(|_| "buyer can't afford to lock the amount requested")?;
+      <StorageDepositByOrderId<T>>::insert(new_order_id, StorageDeposit { depositor: buyer.clone(), amount: storage_deposit });
 
-      // 1 - Buyer buys -> OrderState::New -> buyer fund % locked somewhere
-      // 2 - Seller is notified about new order 
-      //         -> Accepts -> OrderState::Pending -> seller fund % locked somewhere
-      //         -> or Refuses -> > OrderState::Canceled -> buyers funds % released
-      // 3 - If accepted, seller ships order + proof of shipping -> OrderState::Shipped
-      // 4 - Buyer receives order -> OrderState::Completed -> buyer and seller fund % released
-      // 5 - Buyer receives nothing -> ?? think about moderation or just both funds are lost and goes in treasury account?
+      Self::deposit_event(RawEvent::LockEscrowFunds(buyer.clone(), new_order_id));
 
+      Self::schedule_order_deadline(&mut new_order, T::AcceptOrderTimeout::get());
 
-       // increment orderId
-       NextOrderId::mutate(|n| { *n += 1; });
+      OrderIdsByStorefrontId::mutate(storefront.id, |ids| ids.push(new_order_id));
+      for item in new_order.items.iter() {
+        OrderIdsByProductId::mutate(item.product_id, |ids| ids.push(new_order_id));
+      }
+      OrderById::<T>::insert(new_order_id, new_order);
+      OrderIdsByAccount::<T>::mutate(&creator, |ids| ids.push(new_order_id));
+
+      NextOrderId::mutate(|n| { *n += 1; });
 
-//       println!("Order created : {:#?}", new_order_id);
-         debug::info!("Order created : {:?}", new_order_id);
+      debug::info!("Order created : {:?}", new_order_id);
 
       Self::deposit_event(RawEvent::OrderCreated(creator, new_order_id));
       Ok(())
@@ -279,11 +509,16 @@ decl_module! {
   let editor = ensure_signed(origin)?;
 
   let mut order = Self::order_by_id(order_id).ok_or(Error::<T>::OrderNotFound)?;
-  //let buyer_escrow_total = order.order_total + order.buyer_escrow;
-  // let has_updates = update.order_state.match();
-  // ensure!(has_updates, Error::<T>::NoUpdatesForOrder);
 
-  let buyer = &order.owner;
+  // validate the message before any funds are moved
+  if let Some(ref message) = update.message {
+    Self::ensure_valid_message(message)?;
+    if !order.is_owner(&editor) {
+      Self::ensure_seller_permission(
+        &editor, &order, StorefrontPermission::ViewOrderMessages, Error::<T>::NoPermissionToViewOrderMessages
+      )?;
+    }
+  }
 
   match update.order_state {
     
@@ -295,7 +530,9 @@ decl_module! {
             // check editor is storefront/product owner + previous state is New or Pending
             if order.order_state == OrderState::New  {
               ensure!(!order.is_owner(&editor), Error::<T>::MustWaitSellerAcceptsOrder);
-              ensure!(order.seller == editor, Error::<T>::NotInvolvedInThisOrder);
+              Self::ensure_seller_permission(
+                &editor, &order, StorefrontPermission::AcceptOrders, Error::<T>::NoPermissionToAcceptOrders
+              )?;
             }
             else {
               return Err(Error::<T>::OrderStateDoesNotExpectUpdate.into());
@@ -306,13 +543,17 @@ decl_module! {
       // check editor is storefront/product owner + previous state is New or Pending
       if order.order_state == OrderState::New || order.order_state == OrderState::Pending {
         ensure!(!order.is_owner(&editor), Error::<T>::MustWaitSellerAcceptsOrder);
-        ensure!(order.seller == editor, Error::<T>::NotInvolvedInThisOrder);
-        
-        <T as pallet_utils::Trait>::Currency::reserve(&editor, order.seller_escrow)
+        Self::ensure_seller_permission(
+          &editor, &order, StorefrontPermission::AcceptOrders, Error::<T>::NoPermissionToAcceptOrders
+        )?;
+
+        // the seller escrow is always locked on the seller, even if the order is accepted by a staff member
+        <T as pallet_utils::Trait>::Currency::reserve(&order.seller, order.seller_escrow)
         .map_err(|_| "seller can't afford to lock the amount requested")?;
 
-        //let now = <system::Module<T>>::block_number();
-        Self::deposit_event(RawEvent::LockEscrowFunds(editor.clone(), order.id));
+        Self::cancel_order_deadline(&mut order);
+
+        Self::deposit_event(RawEvent::LockEscrowFunds(order.seller.clone(), order.id));
       }
       else {
         return Err(Error::<T>::OrderStateDoesNotExpectUpdate.into());
@@ -323,11 +564,12 @@ decl_module! {
       // check editor is storefront/product owner + previous state is New or Pending
       if order.order_state == OrderState::New || order.order_state == OrderState::Pending {
         ensure!(!order.is_owner(&editor), Error::<T>::MustWaitSellerAcceptsOrder);
-        ensure!(order.seller == editor, Error::<T>::NotInvolvedInThisOrder);
-        <T as pallet_utils::Trait>::Currency::unreserve(&buyer, order.order_total);
+        Self::ensure_seller_permission(
+          &editor, &order, StorefrontPermission::AcceptOrders, Error::<T>::NoPermissionToAcceptOrders
+        )?;
 
-
-        Self::deposit_event(RawEvent::UnlockEscrowFunds(buyer.clone(), order.id));
+        Self::cancel_order_deadline(&mut order);
+        Self::release_buyer_funds(&order);
       }
       else {
         return Err(Error::<T>::OrderStateDoesNotExpectUpdate.into());
@@ -335,45 +577,43 @@ decl_module! {
     }
 
     OrderState::Shipped => {
+      if order.order_state == OrderState::Accepted {
+        Self::ensure_seller_permission(
+          &editor, &order, StorefrontPermission::ShipOrders, Error::<T>::NoPermissionToShipOrders
+        )?;
 
+        Self::schedule_order_deadline(&mut order, T::ConfirmOrderTimeout::get());
+      }
+      else {
+        return Err(Error::<T>::OrderStateDoesNotExpectUpdate.into());
+      }
     }
 
     OrderState::Complete => {
       if order.order_state == OrderState::Shipped {
           ensure!(order.is_owner(&editor), Error::<T>::OnlyOrderOwnerCanComplete);
-       
-        // unlock buyer fund and pay seller
-        <T as pallet_utils::Trait>::Currency::unreserve(&editor, order.order_total);
-        Self::deposit_event(RawEvent::UnlockEscrowFunds(buyer.clone(), order.id));
-        <T as pallet_utils::Trait>::Currency::transfer(&editor, &order.seller, order.order_total, ExistenceRequirement::KeepAlive);
-        Self::deposit_event(RawEvent::OrderCompleteConfirmation(buyer.clone(), order.id));
-        // unlock seller fund
-        <T as pallet_utils::Trait>::Currency::unreserve(&order.seller, order.seller_escrow);
-        Self::deposit_event(RawEvent::UnlockEscrowFunds(order.seller.clone(), order.id));
+
+        Self::complete_order(&order)?;
+        Self::cancel_order_deadline(&mut order);
       }
       else {
         return Err(Error::<T>::OrderStateDoesNotExpectUpdate.into());
       };
     }
-    OrderState::Refunded => {
-      return Err(Error::<T>::OrderFLowNotFullyImplementedYet.into());
-    }
     OrderState::Dispute => {
-      return Err(Error::<T>::OrderFLowNotFullyImplementedYet.into());
-    }
-
-    OrderState::SlashedBuyer => {
-      return Err(Error::<T>::OrderFLowNotFullyImplementedYet.into());
+      return Err(Error::<T>::DisputeMustBeOpenedExplicitly.into());
     }
 
-    OrderState::SlashedSeller => {
-      return Err(Error::<T>::OrderFLowNotFullyImplementedYet.into());
+    OrderState::Cancelled => {
+      return Err(Error::<T>::OrderMustBeCancelledExplicitly.into());
     }
 
-    OrderState::SlashedBoth => {
-      return Err(Error::<T>::OrderFLowNotFullyImplementedYet.into());
+    OrderState::Refunded
+    | OrderState::SlashedBuyer
+    | OrderState::SlashedSeller
+    | OrderState::SlashedBoth => {
+      return Err(Error::<T>::StateIsSetByDisputeRuling.into());
     }
-// TODO : implement Dispute mode
 
 
 }
@@ -384,129 +624,312 @@ decl_module! {
      
       let mut old_data = OrderUpdate::default();
 
+      old_data.order_state = old_state;
+
       if let Some(content) = update.content {
         if content != order.content {
         Utils::<T>::is_valid_content(content.clone())?;
         old_data.content = Some(order.content);
-        old_data.order_state = old_state;
         order.content = content;
-        order.order_state = update.order_state;
       }
     }
+
+      if let Some(message) = update.message {
+        Self::set_order_message(&editor, &mut order, message, &mut old_data)?;
+      }
       
     order.order_state = update.order_state;
       order.updated = Some(WhoAndWhen::<T>::new(editor.clone()));
+      Self::clear_stale_cancellation_request(&order);
 
       debug::info!("Order updated : {:?}", order.order_state);
 
       <OrderById<T>>::insert(order.id, order.clone());
+      Self::release_order_storage_deposit(&order);
       T::AfterOrderUpdated::after_order_updated(editor.clone(), &order, old_data);
       
       Self::deposit_event(RawEvent::OrderUpdated(editor, order_id, order.order_state));
 
+  Ok(())
 
-// #[weight = 100_000 + T::DbWeight::get().reads_writes(5, 3)]
-// pub fn update_order(origin, order_id: OrderId, update: OrderUpdate) -> DispatchResult {
-//   let editor = ensure_signed(origin)?;
+}
 
+    /// Cancel an order. The buyer can cancel a new or pending order at once.
+    /// An accepted or shipped order is cancelled only when both the buyer and the seller call this.
+    /// Users with a `RefundOrders` permission can act for the seller.
+    #[weight = 50_000 + T::DbWeight::get().reads_writes(4, 4)]
+    pub fn cancel_order(origin, order_id: OrderId) -> DispatchResult {
+      let who = ensure_signed(origin)?;
+
+      let mut order = Self::require_order(order_id)?;
+      if !order.is_owner(&who) {
+        Self::ensure_seller_permission(
+          &who, &order, StorefrontPermission::RefundOrders, Error::<T>::NoPermissionToRefundOrders
+        )?;
+      }
 
-//   let mut order = Self::require_order(order_id)?;
-//   let mut order_state = order.order_state;
+      match order.order_state {
+        OrderState::New | OrderState::Pending => {
+          ensure!(order.is_owner(&who), Error::<T>::OnlyBuyerCanCancelNotAcceptedOrder);
 
-//   let has_updates =
-//     update.order_state != order.order_state ||
-//     update.content.is_some();
+          Self::release_buyer_funds(&order);
+        }
 
-//   ensure!(has_updates, Error::<T>::NoUpdatesForOrder);
+        OrderState::Accepted | OrderState::Shipped => {
+          match Self::cancellation_requested_by(order_id) {
+            Some(requester) => {
+              // the other side of the order should agree to the cancellation
+              ensure!(order.is_owner(&requester) != order.is_owner(&who), Error::<T>::CancellationAlreadyRequested);
+              CancellationRequestedBy::<T>::remove(order_id);
+
+              if order.order_state == OrderState::Shipped {
+                // The items have left the seller already, so they are not returned to the stock.
+                Self::unreserve_buyer_funds(&order);
+              } else {
+                Self::release_buyer_funds(&order);
+              }
+              <T as pallet_utils::Trait>::Currency::unreserve(&order.seller, order.seller_escrow);
+              Self::deposit_event(RawEvent::UnlockEscrowFunds(order.seller.clone(), order_id));
+            }
+            None => {
+              CancellationRequestedBy::<T>::insert(order_id, who.clone());
+              Self::deposit_event(RawEvent::OrderCancellationRequested(who, order_id));
+              return Ok(());
+            }
+          }
+        }
 
+        _ => return Err(Error::<T>::OrderCannotBeCancelled.into()),
+      }
 
-//   let mut is_update_applied = false;
-//   let mut old_data = OrderUpdate::default();
-//     // TODO : perms
-//   // If  OrderState::New : only seller can update to accepted/pending or canceled/refund
-//   // If  OrderState::Shipped : only buyer can update to received/complete
+      Self::cancel_order_deadline(&mut order);
+      Self::change_order_state(who.clone(), &mut order, OrderState::Cancelled);
 
- 
+      Self::deposit_event(RawEvent::OrderCancelled(who, order_id));
+      Ok(())
+    }
 
-//   if let Some(content) = update.content {
-//     if content != order.content {
-//       Utils::<T>::is_valid_content(content.clone())?;
-//       old_data.content = Some(order.content);
-//       order.content = content;
-//       is_update_applied = true;
-//     }
-//   }
+    /// Send an encrypted message to the other party of the order without changing its state.
+    #[weight = 25_000 + T::DbWeight::get().reads_writes(2, 2)]
+    pub fn send_order_message(origin, order_id: OrderId, message: EncryptedMessage) -> DispatchResult {
+      let who = ensure_signed(origin)?;
 
+      let mut order = Self::require_order(order_id)?;
 
- 
-//   if let OrderState<T> = update.order_state {
-   
-//       if order.order_state == OrderState::New {
-//         ensure!(!order.is_owner(&editor), Error::<T>::MustWaitSellerAcceptsOrder);
-//         // TODO : balances -> move 50% of order total from each buyer balance
-//       }
-    
-//       else if order.order_state == OrderState::Shipped {
-//         ensure!(order.is_owner(&editor), Error::<T>::MustWaitBuyerConfirmsShipment);
-//       }
-    
-//       else if order.order_state == OrderState::Complete {
-//           // TODO : release escrow / funds and seller gets 100% order total
-//           return Err(Error::<T>::OrderFLowNotFullyImplementedYet.into());
-//       }
-    
-//       else if order.order_state == OrderState::Refused {
-//               // TODO : release escrow / funds for both
-//               return Err(Error::<T>::OrderFLowNotFullyImplementedYet.into());
-//       }
-    
-//       // else {
-//       //   return Err(Error::<T>::OrderStateDoesNotExpectUpdate.into());
-//       // }
-    
-    
+      let mut old_data = OrderUpdate::default();
+      old_data.order_state = order.order_state;
 
-//       old_data.order_state = order.order_state;
-//       order.order_state = order_state;
-//       is_update_applied = true;
-//     }
-  
+      Self::set_order_message(&who, &mut order, message, &mut old_data)?;
+      order.updated = Some(WhoAndWhen::<T>::new(who.clone()));
 
+      <OrderById<T>>::insert(order_id, order.clone());
+      T::AfterOrderUpdated::after_order_updated(who.clone(), &order, old_data);
 
+      Self::deposit_event(RawEvent::OrderMessageSent(who, order_id));
+      Ok(())
+    }
 
+    // ******* Disputes *********
 
+    #[weight = 50_000 + T::DbWeight::get().reads_writes(3, 2)]
+    pub fn open_dispute(origin, order_id: OrderId, evidence: Content) -> DispatchResult {
+      let who = ensure_signed(origin)?;
 
+      Utils::<T>::ensure_content_is_some(&evidence)?;
+      Utils::<T>::is_valid_content(evidence.clone())?;
 
+      let mut order = Self::require_order(order_id)?;
+      if !order.is_owner(&who) {
+        Self::ensure_seller_permission(
+          &who, &order, StorefrontPermission::OpenDisputes, Error::<T>::NoPermissionToOpenDisputes
+        )?;
+      }
+      ensure!(
+        order.order_state == OrderState::Accepted || order.order_state == OrderState::Shipped,
+        Error::<T>::OrderCannotBeDisputed
+      );
 
+      let dispute = Dispute::<T>::new(who.clone(), &order, evidence);
+      DisputeByOrderId::<T>::insert(order_id, dispute);
 
-//       // Update this product only if at least one field should be updated:
-//       if is_update_applied {
-//         order.updated = Some(WhoAndWhen::<T>::new(editor.clone()));
+      // An open dispute stops the order from being auto-completed.
+      Self::cancel_order_deadline(&mut order);
 
+      Self::change_order_state(who.clone(), &mut order, OrderState::Dispute);
+      Self::deposit_event(RawEvent::DisputeOpened(who, order_id));
+      Ok(())
+    }
 
-//         <OrderById<T>>::insert(order.id, order.clone());
-//         T::AfterOrderUpdated::after_order_updated(editor.clone(), &order, old_data);
+    #[weight = 25_000 + T::DbWeight::get().reads_writes(3, 1)]
+    pub fn add_dispute_evidence(origin, order_id: OrderId, evidence: Content) -> DispatchResult {
+      let who = ensure_signed(origin)?;
 
-//         Self::deposit_event(RawEvent::OrderUpdated(editor, order_id));
-//       }
+      Utils::<T>::ensure_content_is_some(&evidence)?;
+      Utils::<T>::is_valid_content(evidence.clone())?;
 
+      let order = Self::require_order(order_id)?;
+      if !order.is_owner(&who) {
+        Self::ensure_seller_permission(
+          &who, &order, StorefrontPermission::OpenDisputes, Error::<T>::NoPermissionToOpenDisputes
+        )?;
+      }
 
-  Ok(())
+      let mut dispute = Self::require_open_dispute(order_id)?;
+      ensure!(
+        dispute.evidence.len() < T::MaxDisputeEvidence::get() as usize,
+        Error::<T>::TooManyDisputeEvidence
+      );
 
-}
+      dispute.evidence.push(DisputeEvidence {
+        created: WhoAndWhen::<T>::new(who.clone()),
+        content: evidence,
+      });
+      dispute.updated = Some(WhoAndWhen::<T>::new(who.clone()));
+      DisputeByOrderId::<T>::insert(order_id, dispute);
+
+      Self::deposit_event(RawEvent::DisputeEvidenceAdded(who, order_id));
+      Ok(())
+    }
+
+    /// Rule on an open dispute. `ruling` must be one of `Refunded`, `SlashedBuyer`,
+    /// `SlashedSeller` or `SlashedBoth`; reserved funds are released accordingly.
+    /// The seller or an account with `ResolveDisputes` permission can only rule `Refunded`.
+    #[weight = 100_000 + T::DbWeight::get().reads_writes(5, 4)]
+    pub fn resolve_dispute(origin, order_id: OrderId, ruling: OrderState) -> DispatchResult {
+      let who = ensure_signed(origin)?;
+
+      let mut order = Self::require_order(order_id)?;
+      if Self::is_dispute_arbiter(&who) {
+        ensure!(!order.is_involved(&who), Error::<T>::ArbiterIsInvolvedInThisOrder);
+      } else {
+        ensure!(
+          Self::is_seller_or_has_permission(&who, &order, StorefrontPermission::ResolveDisputes),
+          Error::<T>::NotADisputeArbiter
+        );
+        ensure!(ruling == OrderState::Refunded, Error::<T>::SellerCanOnlyRefundDispute);
+      }
 
+      let mut dispute = Self::require_open_dispute(order_id)?;
 
+      Self::settle_dispute_funds(&order, ruling)?;
 
+      dispute.ruling = Some(ruling);
+      dispute.updated = Some(WhoAndWhen::<T>::new(who.clone()));
+      DisputeByOrderId::<T>::insert(order_id, dispute);
 
+      Self::change_order_state(who.clone(), &mut order, ruling);
+      Self::deposit_event(RawEvent::DisputeResolved(who, order_id, ruling));
+      Ok(())
+    }
 
+    #[weight = 10_000 + T::DbWeight::get().reads_writes(1, 1)]
+    pub fn add_dispute_arbiter(origin, arbiter: T::AccountId) -> DispatchResult {
+      ensure_root(origin)?;
 
+      ensure!(!Self::is_dispute_arbiter(&arbiter), Error::<T>::DisputeArbiterAlreadyAdded);
+      DisputeArbiters::<T>::mutate(|arbiters| arbiters.push(arbiter.clone()));
 
-    } // decl_module
+      Self::deposit_event(RawEvent::DisputeArbiterAdded(arbiter));
+      Ok(())
+    }
 
+    #[weight = 10_000 + T::DbWeight::get().reads_writes(1, 1)]
+    pub fn remove_dispute_arbiter(origin, arbiter: T::AccountId) -> DispatchResult {
+      ensure_root(origin)?;
 
+      ensure!(Self::is_dispute_arbiter(&arbiter), Error::<T>::DisputeArbiterNotFound);
+      DisputeArbiters::<T>::mutate(|arbiters| vec_remove_on(arbiters, arbiter.clone()));
 
-    
-}
+      Self::deposit_event(RawEvent::DisputeArbiterRemoved(arbiter));
+      Ok(())
+    }
+
+    /// Override the marketplace fee for a storefront, or reset it to the default one with `None`.
+    #[weight = 10_000 + T::DbWeight::get().reads_writes(1, 1)]
+    pub fn set_marketplace_fee_override(
+      origin,
+      storefront_id: StorefrontId,
+      fee_opt: Option<Perbill>
+    ) -> DispatchResult {
+      ensure_root(origin)?;
+
+      Storefronts::<T>::ensure_storefront_exists(storefront_id)?;
+
+      match fee_opt {
+        Some(fee) => MarketplaceFeeOverrideByStorefrontId::insert(storefront_id, fee),
+        None => MarketplaceFeeOverrideByStorefrontId::remove(storefront_id),
+      }
 
+      Self::deposit_event(RawEvent::MarketplaceFeeOverrideSet(storefront_id, fee_opt));
+      Ok(())
+    }
 
+    #[weight = 10_000 + T::DbWeight::get().reads_writes(2, 1)]
+    pub fn update_storefront_order_settings(
+      origin,
+      storefront_id: StorefrontId,
+      update: StorefrontOrderSettingsUpdate
+    ) -> DispatchResult {
+      let who = ensure_signed(origin)?;
 
+      let has_updates = update.buyer_escrow.is_some() || update.seller_escrow.is_some();
+      ensure!(has_updates, Error::<T>::NoUpdatesForStorefrontOrderSettings);
+
+      let storefront = Storefronts::<T>::require_storefront(storefront_id)?;
+
+      Storefronts::<T>::ensure_account_has_storefront_permission(
+        who.clone(),
+        &storefront,
+        StorefrontPermission::UpdateStorefrontSettings,
+        Error::<T>::NoPermissionToUpdateStorefrontSettings.into()
+      )?;
+
+      let mut settings = Self::order_settings_by_storefront_id(storefront_id);
+      let mut is_updated = false;
+
+      if let Some(buyer_escrow) = update.buyer_escrow {
+        if buyer_escrow != settings.buyer_escrow {
+          settings.buyer_escrow = buyer_escrow;
+          is_updated = true;
+        }
+      }
+
+      if let Some(seller_escrow) = update.seller_escrow {
+        if seller_escrow != settings.seller_escrow {
+          settings.seller_escrow = seller_escrow;
+          is_updated = true;
+        }
+      }
+
+      if is_updated {
+        OrderSettingsByStorefrontId::insert(storefront_id, settings);
+        Self::deposit_event(RawEvent::StorefrontOrderSettingsUpdated(who, storefront_id));
+      }
+      Ok(())
+    }
+  }
+} // decl_module
+
+impl<T: Trait> BeforeProductDeleted<T> for Module<T> {
+    fn before_product_deleted(_account: T::AccountId, product: &Product<T>) -> DispatchResult {
+        let has_open_orders = Self::order_ids_by_product_id(product.id)
+            .iter()
+            .filter_map(|order_id| Self::order_by_id(order_id))
+            .any(|order| !order.is_closed());
+
+        ensure!(!has_open_orders, Error::<T>::ProductHasOpenOrders);
+        Ok(())
+    }
+}
+
+impl<T: Trait> BeforeStorefrontDeleted<T> for Module<T> {
+    fn before_storefront_deleted(_account: T::AccountId, storefront: &mut Storefront<T>) -> DispatchResult {
+        let has_open_orders = OrderIdsByStorefrontId::get(storefront.id)
+            .iter()
+            .filter_map(|order_id| Self::order_by_id(order_id))
+            .any(|order| !order.is_closed());
+
+        ensure!(!has_open_orders, Error::<T>::StorefrontHasOpenOrders);
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+use frame_support::storage::IterableStorageMap;
+use sp_std::cell::Cell;
+
+use super::*;
+
+/// An order of a single product as it was stored before cart orders,
+/// encrypted messages and deadlines were added.
+#[derive(Decode)]
+struct OldOrder<T: Trait> {
+    id: OrderId,
+    created: WhoAndWhen<T>,
+    updated: Option<WhoAndWhen<T>>,
+    owner: T::AccountId,
+    order_state: OrderState,
+    order_total: BalanceOf<T>,
+    seller: T::AccountId,
+    buyer_escrow: BalanceOf<T>,
+    seller_escrow: BalanceOf<T>,
+    storefront_id: StorefrontId,
+    product_id: ProductId,
+    content: Content,
+}
+
+impl<T: Trait> From<OldOrder<T>> for Order<T> {
+    fn from(old: OldOrder<T>) -> Self {
+        Order {
+            id: old.id,
+            created: old.created,
+            updated: old.updated,
+            owner: old.owner,
+            order_state: old.order_state,
+            order_total: old.order_total,
+            seller: old.seller,
+            buyer_escrow: old.buyer_escrow,
+            seller_escrow: old.seller_escrow,
+            storefront_id: old.storefront_id,
+            // An old order is a single item that cost the whole order total.
+            items: vec![OrderItem {
+                product_id: old.product_id,
+                quantity: 1,
+                unit_price: old.order_total,
+            }],
+            content: old.content,
+            buyer_message: None,
+            seller_message: None,
+            deadline: None,
+        }
+    }
+}
+
+/// Convert all orders to the current layout.
+pub(crate) fn migrate_orders<T: Trait>() -> Weight {
+    let orders_count = Cell::new(0u64);
+
+    OrderById::<T>::translate(|_order_id, old_order: OldOrder<T>| {
+        orders_count.set(orders_count.get() + 1);
+        Some(old_order.into())
+    });
+
+    IsOrderLayoutMigrated::put(true);
+
+    let changes = orders_count.get();
+    T::DbWeight::get().reads_writes(changes, changes + 1)
+}
@@ -1,6 +1,8 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use codec::{Decode, Encode};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
 use frame_support::{
   decl_module,
   traits::Get
@@ -15,6 +17,7 @@ use frame_system::{self as system};
 use pallet_utils::StorefrontId;
 
 #[derive(Encode, Decode, Ord, PartialOrd, Clone, Eq, PartialEq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub enum StorefrontPermission {
   /// Create, update, delete, grant and revoke roles in this storefront.
   ManageRoles,
@@ -78,6 +81,25 @@ pub enum StorefrontPermission {
   // Related to Storefront settings
   /// Update collection of storefront settings in different pallets
   UpdateStorefrontSettings,
+
+  // Related to storefront funds
+  /// Withdraw funds from the storefront account
+  ManageTreasury,
+
+  // Related to orders of this storefront
+  /// Accept or refuse new orders on behalf of the seller
+  AcceptOrders,
+  /// Mark accepted orders as shipped
+  ShipOrders,
+  /// Agree to cancel accepted or shipped orders with a full refund to the buyer
+  RefundOrders,
+  /// Read and reply to encrypted order messages on behalf of the seller
+  ViewOrderMessages,
+  /// Open disputes and add evidence on behalf of the seller
+  OpenDisputes,
+  /// Settle open disputes on behalf of the seller by refunding the buyer.
+  /// Other rulings are made only by dispute arbiters.
+  ResolveDisputes,
 }
 
 pub type StorefrontPermissionSet = BTreeSet<StorefrontPermission>;
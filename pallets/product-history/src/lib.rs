@@ -1,18 +1,20 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use codec::{Decode, Encode};
-use frame_support::{decl_module, decl_storage};
+use frame_support::{decl_module, decl_storage, weights::Weight};
 use sp_runtime::RuntimeDebug;
 use sp_std::prelude::Vec;
 use frame_system::{self as system};
 
-use pallet_products::{ProductId, Product, ProductUpdate, AfterProductUpdated};
+use pallet_products::{ProductId, Product, ProductUpdate, AfterProductUpdated, BalanceOf};
 use pallet_utils::WhoAndWhen;
 
+mod migration;
+
 #[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
 pub struct ProductHistoryRecord<T: Trait> {
     pub edited: WhoAndWhen<T>,
-    pub old_data: ProductUpdate,
+    pub old_data: ProductUpdate<BalanceOf<T>>,
 }
 
 /// The pallet's configuration trait.
@@ -26,15 +28,26 @@ decl_storage! {
     trait Store for Module<T: Trait> as ProductHistoryModule {
         pub EditHistory get(fn edit_history):
             map hasher(twox_64_concat) ProductId => Vec<ProductHistoryRecord<T>>;
+
+        /// Whether history records are stored in the current layout. New chains start with it.
+        pub IsProductHistoryLayoutMigrated get(fn is_product_history_layout_migrated)
+            build(|_config: &GenesisConfig| true): bool;
     }
 }
 
 decl_module! {
-  pub struct Module<T: Trait> for enum Call where origin: T::Origin {}
+  pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+    fn on_runtime_upgrade() -> Weight {
+      if Self::is_product_history_layout_migrated() {
+        return 0;
+      }
+      migration::migrate_product_history::<T>()
+    }
+  }
 }
 
 impl<T: Trait> ProductHistoryRecord<T> {
-    fn new(updated_by: T::AccountId, old_data: ProductUpdate) -> Self {
+    fn new(updated_by: T::AccountId, old_data: ProductUpdate<BalanceOf<T>>) -> Self {
         ProductHistoryRecord {
             edited: WhoAndWhen::<T>::new(updated_by),
             old_data
@@ -43,7 +56,7 @@ impl<T: Trait> ProductHistoryRecord<T> {
 }
 
 impl<T: Trait> AfterProductUpdated<T> for Module<T> {
-    fn after_product_updated(sender: T::AccountId, product: &Product<T>, old_data: ProductUpdate) {
+    fn after_product_updated(sender: T::AccountId, product: &Product<T>, old_data: ProductUpdate<BalanceOf<T>>) {
         <EditHistory<T>>::mutate(product.id, |ids|
             ids.push(ProductHistoryRecord::<T>::new(sender, old_data)));
     }
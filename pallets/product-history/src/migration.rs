@@ -0,0 +1,50 @@
+use frame_support::{storage::IterableStorageMap, traits::Get};
+use sp_std::cell::Cell;
+
+use pallet_utils::{StorefrontId, Content};
+
+use super::*;
+
+/// A product update as it was stored before prices and categories were added.
+#[derive(Decode)]
+struct OldProductUpdate {
+    storefront_id: Option<StorefrontId>,
+    content: Option<Content>,
+    hidden: Option<bool>,
+}
+
+#[derive(Decode)]
+struct OldProductHistoryRecord<T: Trait> {
+    edited: WhoAndWhen<T>,
+    old_data: OldProductUpdate,
+}
+
+impl<T: Trait> From<OldProductHistoryRecord<T>> for ProductHistoryRecord<T> {
+    fn from(old: OldProductHistoryRecord<T>) -> Self {
+        ProductHistoryRecord {
+            edited: old.edited,
+            old_data: ProductUpdate {
+                storefront_id: old.old_data.storefront_id,
+                content: old.old_data.content,
+                hidden: old.old_data.hidden,
+                price: None,
+                categories: None,
+            },
+        }
+    }
+}
+
+/// Convert all product history records to the current layout.
+pub(crate) fn migrate_product_history<T: Trait>() -> Weight {
+    let histories_count = Cell::new(0u64);
+
+    EditHistory::<T>::translate(|_product_id, old_records: Vec<OldProductHistoryRecord<T>>| {
+        histories_count.set(histories_count.get() + 1);
+        Some(old_records.into_iter().map(Into::into).collect())
+    });
+
+    IsProductHistoryLayoutMigrated::put(true);
+
+    let changes = histories_count.get();
+    T::DbWeight::get().reads_writes(changes, changes + 1)
+}
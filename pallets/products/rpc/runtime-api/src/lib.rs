@@ -0,0 +1,31 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_std::prelude::*;
+
+use pallet_categories::CategoryId;
+use pallet_products::ProductId;
+use pallet_utils::StorefrontId;
+
+sp_api::decl_runtime_apis! {
+	pub trait ProductsApi {
+		fn get_product_ids_by_storefront(
+			storefront_id: StorefrontId,
+			include_hidden: bool,
+			offset: u64,
+			limit: u64,
+		) -> Vec<ProductId>;
+
+		fn get_product_ids_by_category(
+			category_id: CategoryId,
+			include_hidden: bool,
+			offset: u64,
+			limit: u64,
+		) -> Vec<ProductId>;
+
+		fn get_reply_ids_tree(
+			product_id: ProductId,
+			offset: u64,
+			limit: u64,
+		) -> Vec<(ProductId, Vec<ProductId>)>;
+	}
+}
@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+use pallet_categories::CategoryId;
+use pallet_products::ProductId;
+use pallet_utils::StorefrontId;
+pub use pallet_products_rpc_runtime_api::ProductsApi as ProductsRuntimeApi;
+
+#[rpc]
+pub trait ProductsApi<BlockHash> {
+	#[rpc(name = "products_getProductIdsByStorefront")]
+	fn get_product_ids_by_storefront(
+		&self,
+		storefront_id: StorefrontId,
+		include_hidden: bool,
+		offset: u64,
+		limit: u64,
+		at: Option<BlockHash>,
+	) -> Result<Vec<ProductId>>;
+
+	#[rpc(name = "products_getProductIdsByCategory")]
+	fn get_product_ids_by_category(
+		&self,
+		category_id: CategoryId,
+		include_hidden: bool,
+		offset: u64,
+		limit: u64,
+		at: Option<BlockHash>,
+	) -> Result<Vec<ProductId>>;
+
+	#[rpc(name = "products_getReplyIdsTree")]
+	fn get_reply_ids_tree(
+		&self,
+		product_id: ProductId,
+		offset: u64,
+		limit: u64,
+		at: Option<BlockHash>,
+	) -> Result<Vec<(ProductId, Vec<ProductId>)>>;
+}
+
+/// A struct that implements the `ProductsApi`.
+pub struct Products<C, Block> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<Block>,
+}
+
+impl<C, Block> Products<C, Block> {
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: Default::default() }
+	}
+}
+
+/// Error code of a failed runtime API call.
+const RUNTIME_ERROR: i64 = 1;
+
+fn runtime_error(err: impl std::fmt::Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(RUNTIME_ERROR),
+		message: "Unable to query products.".into(),
+		data: Some(format!("{:?}", err).into()),
+	}
+}
+
+impl<C, Block> ProductsApi<<Block as BlockT>::Hash> for Products<C, Block>
+where
+	Block: BlockT,
+	C: Send + Sync + 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: ProductsRuntimeApi<Block>,
+{
+	fn get_product_ids_by_storefront(
+		&self,
+		storefront_id: StorefrontId,
+		include_hidden: bool,
+		offset: u64,
+		limit: u64,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<ProductId>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.get_product_ids_by_storefront(&at, storefront_id, include_hidden, offset, limit).map_err(runtime_error)
+	}
+
+	fn get_product_ids_by_category(
+		&self,
+		category_id: CategoryId,
+		include_hidden: bool,
+		offset: u64,
+		limit: u64,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<ProductId>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.get_product_ids_by_category(&at, category_id, include_hidden, offset, limit).map_err(runtime_error)
+	}
+
+	fn get_reply_ids_tree(
+		&self,
+		product_id: ProductId,
+		offset: u64,
+		limit: u64,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<(ProductId, Vec<ProductId>)>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.get_reply_ids_tree(&at, product_id, offset, limit).map_err(runtime_error)
+	}
+}
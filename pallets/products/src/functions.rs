@@ -1,6 +1,7 @@
 use frame_support::dispatch::DispatchResult;
+use sp_runtime::traits::Zero;
 
-use pallet_utils::{StorefrontId, vec_remove_on};
+use pallet_utils::{StorefrontId, vec_remove_on, paginate};
 
 use super::*;
 
@@ -11,7 +12,9 @@ impl<T: Trait> Product<T> {
         created_by: T::AccountId,
         storefront_id_opt: Option<StorefrontId>,
         extension: ProductExtension,
-        content: Content
+        content: Content,
+        price: Option<ProductPrice<BalanceOf<T>>>,
+        categories: Vec<CategoryId>
     ) -> Self {
         Product {
             id,
@@ -22,12 +25,17 @@ impl<T: Trait> Product<T> {
             storefront_id: storefront_id_opt,
             content,
             hidden: false,
+            categories,
+            price,
+            stock: None,
             replies_count: 0,
             hidden_replies_count: 0,
             shares_count: 0,
             upvotes_count: 0,
             downvotes_count: 0,
-            score: 0
+            score: 0,
+            reviews_count: 0,
+            ratings_sum: 0,
         }
     }
 
@@ -35,6 +43,11 @@ impl<T: Trait> Product<T> {
         self.owner == *account
     }
 
+    pub fn add_rating(&mut self, rating: u8) {
+        self.reviews_count = self.reviews_count.saturating_add(1);
+        self.ratings_sum = self.ratings_sum.saturating_add(rating as u64);
+    }
+
     pub fn is_root_product(&self) -> bool {
         !self.is_comment()
     }
@@ -87,6 +100,11 @@ impl<T: Trait> Product<T> {
         None
     }
 
+    /// Whether there are at least `quantity` items of this product available for ordering.
+    pub fn has_stock(&self, quantity: u32) -> bool {
+        self.stock.map_or(true, |stock| stock >= quantity)
+    }
+
     // TODO use macros to generate inc/dec fns for Storefront, Product.
 
     pub fn inc_replies(&mut self) {
@@ -139,12 +157,14 @@ impl<T: Trait> Product<T> {
     }
 }
 
-impl Default for ProductUpdate {
+impl<Balance> Default for ProductUpdate<Balance> {
     fn default() -> Self {
         ProductUpdate {
             storefront_id: None,
             content: None,
-            hidden: None
+            hidden: None,
+            price: None,
+            categories: None
         }
     }
 }
@@ -163,6 +183,95 @@ impl<T: Trait> Module<T> {
         Ok(Self::product_by_id(product_id).ok_or(Error::<T>::ProductNotFound)?)
     }
 
+    /// Check that a product is not blocked by moderators of the `scope` storefront
+    /// or return `ProductIsBlocked` error.
+    pub fn ensure_product_is_not_blocked(product_id: ProductId, scope: StorefrontId) -> DispatchResult {
+        ensure!(!T::IsProductBlocked::is_product_blocked(product_id, scope), Error::<T>::ProductIsBlocked);
+        Ok(())
+    }
+
+    /// Check that a price can be set on a product with such `extension`.
+    pub fn ensure_valid_price(
+        extension: ProductExtension,
+        price: ProductPrice<BalanceOf<T>>
+    ) -> DispatchResult {
+        ensure!(extension == ProductExtension::RegularProduct, Error::<T>::PriceIsAllowedOnlyOnRegularProduct);
+        ensure!(!price.amount.is_zero(), Error::<T>::ZeroProductPrice);
+        Ok(())
+    }
+
+    /// Check that a product can be tagged with its categories in a given storefront.
+    pub fn ensure_valid_categories(product: &Product<T>, storefront_id: StorefrontId) -> DispatchResult {
+        if product.categories.is_empty() {
+            return Ok(());
+        }
+
+        ensure!(
+            product.extension == ProductExtension::RegularProduct,
+            Error::<T>::CategoriesAreAllowedOnlyOnRegularProduct
+        );
+        Categories::<T>::ensure_product_categories_are_valid(storefront_id, &product.categories)
+    }
+
+    pub(crate) fn add_to_category_index(product_id: ProductId, categories: &[CategoryId]) {
+        for category_id in categories.iter() {
+            ProductIdsByCategory::mutate(category_id, |ids| ids.push(product_id));
+        }
+    }
+
+    pub(crate) fn remove_from_category_index(product_id: ProductId, categories: &[CategoryId]) {
+        for category_id in categories.iter() {
+            ProductIdsByCategory::mutate(category_id, |ids| vec_remove_on(ids, product_id));
+        }
+    }
+
+    /// Calculate a new product stock after the update.
+    pub fn apply_stock_update(stock: Option<u32>, update: StockUpdate) -> Result<Option<u32>, DispatchError> {
+        match update {
+            StockUpdate::Add(quantity) => {
+                let new_stock = stock.unwrap_or(0).checked_add(quantity)
+                    .ok_or(Error::<T>::ProductStockOverflow)?;
+                Ok(Some(new_stock))
+            }
+            StockUpdate::Remove(quantity) => {
+                let stock = stock.ok_or(Error::<T>::NoUpdatesForProductStock)?;
+                let new_stock = stock.checked_sub(quantity)
+                    .ok_or(Error::<T>::InsufficientProductStock)?;
+                Ok(Some(new_stock))
+            }
+            StockUpdate::Untrack => Ok(None),
+        }
+    }
+
+    /// Take `quantity` items of the product from its stock, if the stock is tracked.
+    pub fn take_product_stock(product_id: ProductId, quantity: u32) -> DispatchResult {
+        let mut product = Self::require_product(product_id)?;
+
+        if let Some(stock) = product.stock {
+            ensure!(stock >= quantity, Error::<T>::InsufficientProductStock);
+
+            let new_stock = stock - quantity;
+            product.stock = Some(new_stock);
+            ProductById::<T>::insert(product_id, product);
+
+            if new_stock == 0 {
+                Self::deposit_event(RawEvent::ProductSoldOut(product_id));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Return `quantity` items of the product to its stock, e.g. when an order was refused.
+    pub fn return_product_stock(product_id: ProductId, quantity: u32) {
+        if let Some(mut product) = Self::product_by_id(product_id) {
+            if let Some(stock) = product.stock {
+                product.stock = Some(stock.saturating_add(quantity));
+                ProductById::<T>::insert(product_id, product);
+            }
+        }
+    }
+
     fn share_product(
         account: T::AccountId,
         original_product: &mut Product<T>,
@@ -264,6 +373,58 @@ impl<T: Trait> Module<T> {
     }
     // TODO: maybe add for_each_reply?
 
+    /// Get a page of ids of products in a storefront. Hidden products are skipped unless `include_hidden`.
+    pub fn get_product_ids_by_storefront(
+        storefront_id: StorefrontId,
+        include_hidden: bool,
+        offset: u64,
+        limit: u64
+    ) -> Vec<ProductId> {
+        let product_ids = Self::product_ids_by_storefront_id(storefront_id)
+            .into_iter()
+            .filter(|product_id| include_hidden || Self::product_by_id(product_id).map_or(false, |p| !p.hidden));
+
+        paginate(product_ids, offset, limit)
+    }
+
+    /// Get a page of ids of products tagged with a category across all storefronts.
+    /// Hidden products are skipped unless `include_hidden`.
+    pub fn get_product_ids_by_category(
+        category_id: CategoryId,
+        include_hidden: bool,
+        offset: u64,
+        limit: u64
+    ) -> Vec<ProductId> {
+        let product_ids = Self::product_ids_by_category(category_id)
+            .into_iter()
+            .filter(|product_id| include_hidden || Self::product_by_id(product_id).map_or(false, |p| !p.hidden));
+
+        paginate(product_ids, offset, limit)
+    }
+
+    /// Get a page of nested replies of a product as pairs of a parent id and ids of its direct replies.
+    pub fn get_reply_ids_tree(product_id: ProductId, offset: u64, limit: u64) -> Vec<(ProductId, Vec<ProductId>)> {
+        paginate(Self::iter_reply_ids_tree(product_id), offset, limit)
+    }
+
+    /// Lazily walk all nested replies of a product, yielding pairs of a parent id
+    /// and ids of its direct replies. Parents without replies are skipped.
+    pub fn iter_reply_ids_tree(product_id: ProductId) -> impl Iterator<Item=(ProductId, Vec<ProductId>)> {
+        let mut parent_ids: Vec<ProductId> = Vec::new();
+        parent_ids.push(product_id);
+
+        sp_std::iter::from_fn(move || {
+            while let Some(parent_id) = parent_ids.pop() {
+                let reply_ids = Self::reply_ids_by_product_id(parent_id);
+                if !reply_ids.is_empty() {
+                    parent_ids.extend(reply_ids.iter().copied());
+                    return Some((parent_id, reply_ids));
+                }
+            }
+            None
+        })
+    }
+
     pub(crate) fn create_comment(
         creator: &T::AccountId,
         new_product_id: ProductId,
@@ -353,11 +514,136 @@ impl<T: Trait> Module<T> {
             storefront.score = storefront.score.saturating_sub(product.score);
 
             ProductIdsByStorefrontId::mutate(storefront.id, |product_ids| vec_remove_on(product_ids, product_id));
+            StorefrontById::<T>::insert(storefront.id, storefront);
+        }
+
+        ProductById::<T>::insert(product_id, product);
+        Ok(())
+    }
+
+    /// Remove a product and all its nested replies from the storage,
+    /// then update counters of its storefront, original product or ancestors.
+    /// Warning: This will affect storage state!
+    pub(crate) fn delete_product_with_replies(deleter: T::AccountId, product: &Product<T>) -> DispatchResult {
+        let mut products_to_delete: Vec<Product<T>> = Vec::new();
+        products_to_delete.push(product.clone());
+        for (_, reply_ids) in Self::iter_reply_ids_tree(product.id) {
+            products_to_delete.extend(reply_ids.iter().filter_map(|reply_id| Self::product_by_id(reply_id)));
+        }
+
+        // Let other pallets refuse the deletion or clean up their data first.
+        for product_to_delete in products_to_delete.iter() {
+            T::BeforeProductDeleted::before_product_deleted(deleter.clone(), product_to_delete)?;
+        }
+
+        match product.extension {
+            ProductExtension::Comment(comment_ext) => {
+                let removed_replies = product.replies_count.saturating_add(1);
+                let removed_hidden_replies = product.hidden_replies_count.saturating_add(product.hidden as u16);
+
+                let update_replies_counters = |p: &mut Product<T>| {
+                    p.replies_count = p.replies_count.saturating_sub(removed_replies);
+                    p.hidden_replies_count = p.hidden_replies_count.saturating_sub(removed_hidden_replies);
+                };
+
+                if let Some(parent_id) = comment_ext.parent_id {
+                    Self::for_each_product_ancestor(parent_id, update_replies_counters)?;
+                }
+                Self::mutate_product_by_id(comment_ext.root_product_id, update_replies_counters)?;
+
+                let commented_product_id = comment_ext.parent_id.unwrap_or(comment_ext.root_product_id);
+                ReplyIdsByProductId::mutate(commented_product_id, |ids| vec_remove_on(ids, product.id));
+            },
+            ProductExtension::RegularProduct | ProductExtension::SharedProduct(_) => {
+                if let Some(mut storefront) = product.try_get_storefront() {
+                    storefront.dec_products();
+                    if product.hidden {
+                        storefront.dec_hidden_products();
+                    }
+                    storefront.score = storefront.score.saturating_sub(product.score);
+
+                    ProductIdsByStorefrontId::mutate(storefront.id, |ids| vec_remove_on(ids, product.id));
+                    StorefrontById::<T>::insert(storefront.id, storefront);
+                }
+
+                if let ProductExtension::SharedProduct(original_product_id) = product.extension {
+                    // The original product could have been deleted before this sharing product.
+                    let _ = Self::mutate_product_by_id(original_product_id, Product::dec_shares);
+                    SharedProductIdsByOriginalProductId::mutate(original_product_id, |ids| vec_remove_on(ids, product.id));
+                }
+            },
+        }
+
+        for product_to_delete in products_to_delete.iter() {
+            let product_id = product_to_delete.id;
+            ProductById::<T>::remove(product_id);
+            Self::remove_from_category_index(product_id, &product_to_delete.categories);
+            ReplyIdsByProductId::remove(product_id);
+            SharedProductIdsByOriginalProductId::remove(product_id);
+
+            if let Some(deposit) = StorageDepositByProductId::<T>::take(product_id) {
+                Utils::<T>::release_storage_deposit(deposit);
+            }
         }
 
         Ok(())
     }
 
+    /// Check that the editor can move a root product to another storefront
+    /// and return this storefront.
+    pub(crate) fn ensure_product_can_be_moved(
+        editor: T::AccountId,
+        product: &Product<T>,
+        new_storefront_id: StorefrontId
+    ) -> Result<Storefront<T>, DispatchError> {
+        let new_storefront = Storefronts::<T>::require_storefront(new_storefront_id)?;
+        ensure!(!new_storefront.hidden, Error::<T>::CannotCreateInHiddenScope);
+
+        Storefronts::<T>::ensure_storefront_is_not_blocked(&new_storefront)?;
+        Storefronts::<T>::ensure_account_is_not_blocked(editor.clone(), new_storefront_id)?;
+        Storefronts::<T>::ensure_content_is_not_blocked(product.content.clone(), new_storefront_id)?;
+
+        Storefronts::ensure_account_has_storefront_permission(
+            editor,
+            &new_storefront,
+            StorefrontPermission::CreateProducts,
+            Error::<T>::NoPermissionToCreateProducts.into()
+        )?;
+
+        Categories::<T>::ensure_categories_allowed_in_storefront(new_storefront_id, &product.categories)?;
+
+        Ok(new_storefront)
+    }
+
+    /// Move a root product from its storefront to another one
+    /// together with its share of the storefront counters and score.
+    /// Warning: This will affect storage state!
+    pub(crate) fn move_product_to_storefront(product: &mut Product<T>, mut new_storefront: Storefront<T>) {
+        let new_storefront_id = new_storefront.id;
+
+        if let Some(mut old_storefront) = product.try_get_storefront() {
+            old_storefront.dec_products();
+            if product.hidden {
+                old_storefront.dec_hidden_products();
+            }
+            old_storefront.score = old_storefront.score.saturating_sub(product.score);
+
+            ProductIdsByStorefrontId::mutate(old_storefront.id, |ids| vec_remove_on(ids, product.id));
+            StorefrontById::<T>::insert(old_storefront.id, old_storefront);
+        }
+
+        new_storefront.inc_products();
+        if product.hidden {
+            new_storefront.inc_hidden_products();
+        }
+        new_storefront.score = new_storefront.score.saturating_add(product.score);
+
+        ProductIdsByStorefrontId::mutate(new_storefront_id, |ids| ids.push(product.id));
+        StorefrontById::<T>::insert(new_storefront_id, new_storefront);
+
+        product.storefront_id = Some(new_storefront_id);
+    }
+
     /// Rewrite ancestor counters when Product hidden status changes
     /// Warning: This will affect storage state!
     pub(crate) fn update_counters_on_comment_hidden_change(
@@ -3,20 +3,26 @@
 use codec::{Decode, Encode};
 use frame_support::{
     decl_error, decl_event, decl_module, decl_storage,
-    dispatch::{DispatchError, DispatchResult}, ensure, traits::Get,
+    dispatch::{DispatchError, DispatchResult}, ensure, traits::{Currency, Get},
+    weights::Weight,
 };
 use sp_runtime::RuntimeDebug;
 use sp_std::prelude::*;
 use frame_system::{self as system, ensure_signed};
 
+use df_traits::moderation::IsProductBlocked;
+use pallet_categories::{Module as Categories, CategoryId};
 use pallet_permissions::StorefrontPermission;
-use pallet_storefronts::{Module as Storefronts, Storefront, StorefrontById};
-use pallet_utils::{Module as Utils, StorefrontId, WhoAndWhen, Content};
+use pallet_storefronts::{Module as Storefronts, BeforeStorefrontDeleted, Storefront, StorefrontById};
+use pallet_utils::{Module as Utils, StorefrontId, WhoAndWhen, Content, StorageDepositOf};
 
 pub mod functions;
+mod migration;
 
 pub type ProductId = u64;
 
+pub type BalanceOf<T> = <<T as pallet_utils::Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+
 #[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
 pub struct Product<T: Trait> {
     pub id: ProductId,
@@ -31,6 +37,15 @@ pub struct Product<T: Trait> {
     pub content: Content,
     pub hidden: bool,
 
+    /// Categories of a regular product, indexed in `ProductIdsByCategory`.
+    pub categories: Vec<CategoryId>,
+
+    /// A price of one item. Only regular products can be priced and ordered.
+    pub price: Option<ProductPrice<BalanceOf<T>>>,
+
+    /// A number of items available for ordering. `None` if stock is not tracked.
+    pub stock: Option<u32>,
+
     pub replies_count: u16,
     pub hidden_replies_count: u16,
 
@@ -39,13 +54,51 @@ pub struct Product<T: Trait> {
     pub downvotes_count: u16,
 
     pub score: i32,
+
+    /// A number of verified-purchase reviews of this product.
+    pub reviews_count: u32,
+    /// A sum of ratings of these reviews. An average rating is `ratings_sum / reviews_count`.
+    pub ratings_sum: u64,
 }
 
 #[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
-pub struct ProductUpdate {
+pub struct ProductUpdate<Balance> {
     pub storefront_id: Option<StorefrontId>,
     pub content: Option<Content>,
     pub hidden: Option<bool>,
+    pub price: Option<Option<ProductPrice<Balance>>>,
+    pub categories: Option<Vec<CategoryId>>,
+}
+
+#[derive(Encode, Decode, Clone, Copy, Eq, PartialEq, RuntimeDebug)]
+pub struct ProductPrice<Balance> {
+    pub amount: Balance,
+    pub currency: PriceCurrency,
+}
+
+/// A currency in which a product price is denominated.
+#[derive(Encode, Decode, Clone, Copy, Eq, PartialEq, RuntimeDebug)]
+pub enum PriceCurrency {
+    /// A native token of this chain.
+    Native,
+}
+
+impl Default for PriceCurrency {
+    fn default() -> Self {
+        PriceCurrency::Native
+    }
+}
+
+/// A change of a product stock. Stock is changed by a number of items rather than set,
+/// so that items taken or returned by orders in the meantime are not lost.
+#[derive(Encode, Decode, Clone, Copy, Eq, PartialEq, RuntimeDebug)]
+pub enum StockUpdate {
+    /// Add items to the stock. Starts tracking the stock if it was not tracked yet.
+    Add(u32),
+    /// Remove items from the stock, e.g. the ones sold elsewhere.
+    Remove(u32),
+    /// Stop tracking the stock, so the product can be ordered in any quantity.
+    Untrack,
 }
 
 #[derive(Encode, Decode, Clone, Copy, Eq, PartialEq, RuntimeDebug)]
@@ -71,6 +124,7 @@ impl Default for ProductExtension {
 pub trait Trait: system::Trait
     + pallet_utils::Trait
     + pallet_storefronts::Trait
+    + pallet_categories::Trait
 {
     /// The overarching event type.
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
@@ -81,6 +135,10 @@ pub trait Trait: system::Trait
     type ProductScores: ProductScores<Self>;
 
     type AfterProductUpdated: AfterProductUpdated<Self>;
+
+    type BeforeProductDeleted: BeforeProductDeleted<Self>;
+
+    type IsProductBlocked: IsProductBlocked<ProductId>;
 }
 
 pub trait ProductScores<T: Trait> {
@@ -99,7 +157,21 @@ impl<T: Trait> ProductScores<T> for () {
 
 #[impl_trait_for_tuples::impl_for_tuples(10)]
 pub trait AfterProductUpdated<T: Trait> {
-    fn after_product_updated(account: T::AccountId, product: &Product<T>, old_data: ProductUpdate);
+    fn after_product_updated(account: T::AccountId, product: &Product<T>, old_data: ProductUpdate<BalanceOf<T>>);
+}
+
+/// Handler that will be called right before the product is deleted.
+/// If any handler returns an error, the product will not be deleted.
+pub trait BeforeProductDeleted<T: Trait> {
+    fn before_product_deleted(account: T::AccountId, product: &Product<T>) -> DispatchResult;
+}
+
+#[impl_trait_for_tuples::impl_for_tuples(10)]
+impl<T: Trait> BeforeProductDeleted<T> for Tuple {
+    fn before_product_deleted(account: T::AccountId, product: &Product<T>) -> DispatchResult {
+        for_tuples!( #( Tuple::before_product_deleted(account.clone(), product)?; )* );
+        Ok(())
+    }
 }
 
 // This pallet's storage items.
@@ -118,6 +190,16 @@ decl_storage! {
         // TODO rename 'Shared...' to 'Sharing...'
         pub SharedProductIdsByOriginalProductId get(fn shared_product_ids_by_original_product_id):
             map hasher(twox_64_concat) ProductId => Vec<ProductId>;
+
+        pub ProductIdsByCategory get(fn product_ids_by_category):
+            map hasher(twox_64_concat) CategoryId => Vec<ProductId>;
+
+        pub StorageDepositByProductId get(fn storage_deposit_by_product_id):
+            map hasher(twox_64_concat) ProductId => Option<StorageDepositOf<T>>;
+
+        /// Whether products are stored in the current layout. New chains start with it.
+        pub IsProductLayoutMigrated get(fn is_product_layout_migrated)
+            build(|_config: &GenesisConfig| true): bool;
     }
 }
 
@@ -129,6 +211,8 @@ decl_event!(
         ProductUpdated(AccountId, ProductId),
         ProductDeleted(AccountId, ProductId),
         ProductShared(AccountId, ProductId),
+        ProductRestocked(AccountId, ProductId),
+        ProductSoldOut(ProductId),
     }
 );
 
@@ -148,6 +232,31 @@ decl_error! {
         /// Product has no any replies
         NoRepliesOnProduct,
 
+        // Price related errors:
+
+        /// Only regular products can have a price.
+        PriceIsAllowedOnlyOnRegularProduct,
+        /// Product price cannot be zero.
+        ZeroProductPrice,
+
+        // Category related errors:
+
+        /// Only regular products can be tagged with categories.
+        CategoriesAreAllowedOnlyOnRegularProduct,
+
+        // Stock related errors:
+
+        /// Only regular products can have a stock.
+        StockIsAllowedOnlyOnRegularProduct,
+        /// Not enough items of this product in stock.
+        InsufficientProductStock,
+        /// Product stock would not change.
+        NoUpdatesForProductStock,
+        /// Product stock overflows when adding items to it.
+        ProductStockOverflow,
+        /// Product is blocked in a given storefront.
+        ProductIsBlocked,
+
         // Sharing related errors:
 
         /// Original product not found when sharing.
@@ -184,6 +293,16 @@ decl_error! {
         NoPermissionToUpdateOwnProducts,
         /// A comment owner is not allowed to update their own comments in this storefront.
         NoPermissionToUpdateOwnComments,
+        /// User is not a product author and has no permission to delete products in this storefront.
+        NoPermissionToDeleteAnyProduct,
+        /// A product owner is not allowed to delete their own products in this storefront.
+        NoPermissionToDeleteOwnProducts,
+        /// A comment owner is not allowed to delete their own comments in this storefront.
+        NoPermissionToDeleteOwnComments,
+        /// A product has more nested replies than allowed to delete with it.
+        TooManyRepliesToDelete,
+        /// Only a product owner can move it to another storefront.
+        OnlyProductOwnerCanMoveProduct,
     }
 }
 
@@ -198,19 +317,37 @@ decl_module! {
     // Initializing events
     fn deposit_event() = default;
 
-    #[weight = 100_000 + T::DbWeight::get().reads_writes(8, 8)]
+    fn on_runtime_upgrade() -> Weight {
+      if Self::is_product_layout_migrated() {
+        return 0;
+      }
+      migration::migrate_products::<T>()
+    }
+
+    #[weight = 100_000 + T::DbWeight::get().reads_writes(
+      9 + categories.len() as u64,
+      9 + categories.len() as u64
+    )]
     pub fn create_product(
       origin,
       storefront_id_opt: Option<StorefrontId>,
       extension: ProductExtension,
-      content: Content
+      content: Content,
+      price: Option<ProductPrice<BalanceOf<T>>>,
+      categories: Vec<CategoryId>
     ) -> DispatchResult {
       let creator = ensure_signed(origin)?;
 
       Utils::<T>::is_valid_content(content.clone())?;
 
+      if let Some(price) = price {
+        Self::ensure_valid_price(extension, price)?;
+      }
+
       let new_product_id = Self::next_product_id();
-      let new_product: Product<T> = Product::new(new_product_id, creator.clone(), storefront_id_opt, extension, content);
+      let new_product: Product<T> = Product::new(
+        new_product_id, creator.clone(), storefront_id_opt, extension, content, price, categories
+      );
 
       // Get storefront from either storefront_id_opt or Comment if a comment provided
       let storefront = &mut new_product.get_storefront()?;
@@ -219,6 +356,15 @@ decl_module! {
       let root_product = &mut new_product.get_root_product()?;
       ensure!(!root_product.hidden, Error::<T>::CannotCreateInHiddenScope);
 
+      Storefronts::<T>::ensure_storefront_is_not_blocked(storefront)?;
+      Storefronts::<T>::ensure_account_is_not_blocked(creator.clone(), storefront.id)?;
+      Storefronts::<T>::ensure_content_is_not_blocked(new_product.content.clone(), storefront.id)?;
+      if !new_product.is_root_product() {
+        Self::ensure_product_is_not_blocked(root_product.id, storefront.id)?;
+      }
+
+      Self::ensure_valid_categories(&new_product, storefront.id)?;
+
       // Check whether account has permission to create Product (by extension)
       let mut permission_to_check = StorefrontPermission::CreateProducts;
       let mut error_on_permission_failed = Error::<T>::NoPermissionToCreateProducts;
@@ -235,32 +381,40 @@ decl_module! {
         error_on_permission_failed.into()
       )?;
 
+      Utils::<T>::ensure_can_reserve_storage_deposit(&creator, &new_product)?;
+
       match extension {
         ProductExtension::RegularProduct => storefront.inc_products(),
         ProductExtension::SharedProduct(product_id) => Self::create_sharing_product(&creator, new_product_id, product_id, storefront)?,
         ProductExtension::Comment(comment_ext) => Self::create_comment(&creator, new_product_id, comment_ext, root_product)?,
       }
 
+      let deposit = Utils::<T>::reserve_storage_deposit(&creator, &new_product)?;
+
       if new_product.is_root_product() {
         StorefrontById::insert(storefront.id, storefront.clone());
         ProductIdsByStorefrontId::mutate(storefront.id, |ids| ids.push(new_product_id));
       }
 
+      Self::add_to_category_index(new_product_id, &new_product.categories);
       ProductById::insert(new_product_id, new_product);
+      <StorageDepositByProductId<T>>::insert(new_product_id, deposit);
       NextProductId::mutate(|n| { *n += 1; });
 
       Self::deposit_event(RawEvent::ProductCreated(creator, new_product_id));
       Ok(())
     }
 
-    #[weight = 100_000 + T::DbWeight::get().reads_writes(5, 3)]
-    pub fn update_product(origin, product_id: ProductId, update: ProductUpdate) -> DispatchResult {
+    #[weight = 100_000 + T::DbWeight::get().reads_writes(8, 8)]
+    pub fn update_product(origin, product_id: ProductId, update: ProductUpdate<BalanceOf<T>>) -> DispatchResult {
       let editor = ensure_signed(origin)?;
 
       let has_updates =
-        // update.storefront_id.is_some() ||
+        update.storefront_id.is_some() ||
         update.content.is_some() ||
-        update.hidden.is_some();
+        update.hidden.is_some() ||
+        update.price.is_some() ||
+        update.categories.is_some();
 
       ensure!(has_updates, Error::<T>::NoUpdatesForProduct);
 
@@ -289,14 +443,18 @@ decl_module! {
         }
       }
 
+      let storefront = product.get_storefront()?;
+      storefront.ensure_not_archived()?;
+
       Storefronts::ensure_account_has_storefront_permission(
         editor.clone(),
-        &product.get_storefront()?,
+        &storefront,
         permission_to_check,
         permission_error
       )?;
 
       let mut storefront_opt: Option<Storefront<T>> = None;
+      let mut new_storefront_opt: Option<Storefront<T>> = None;
       let mut is_update_applied = false;
       let mut old_data = ProductUpdate::default();
 
@@ -309,6 +467,38 @@ decl_module! {
         }
       }
 
+      if let Some(categories) = update.categories {
+        if categories != product.categories {
+          ensure!(
+            product.extension == ProductExtension::RegularProduct,
+            Error::<T>::CategoriesAreAllowedOnlyOnRegularProduct
+          );
+
+          let old_categories = sp_std::mem::replace(&mut product.categories, categories);
+
+          let storefront_id = update.storefront_id.or(product.storefront_id)
+            .ok_or(Error::<T>::ProductHasNoStorefrontId)?;
+          Categories::<T>::ensure_product_categories_are_valid(storefront_id, &product.categories)?;
+
+          old_data.categories = Some(old_categories);
+          is_update_applied = true;
+        }
+      }
+
+      // Move this product to another storefront:
+      if let Some(storefront_id) = update.storefront_id {
+        if product.storefront_id != Some(storefront_id) {
+          ensure!(product.is_root_product(), Error::<T>::CannotUpdateStorefrontIdOnComment);
+          ensure!(is_owner, Error::<T>::OnlyProductOwnerCanMoveProduct);
+
+          let new_storefront = Self::ensure_product_can_be_moved(editor.clone(), &product, storefront_id)?;
+          new_storefront_opt = Some(new_storefront);
+
+          old_data.storefront_id = product.storefront_id;
+          is_update_applied = true;
+        }
+      }
+
       if let Some(hidden) = update.hidden {
         if hidden != product.hidden {
           storefront_opt = product.try_get_storefront().map(|mut storefront| {
@@ -321,53 +511,49 @@ decl_module! {
             storefront
           });
 
-          if let ProductExtension::Comment(comment_ext) = product.extension {
-            Self::update_counters_on_comment_hidden_change(&comment_ext, hidden)?;
-          }
-
           old_data.hidden = Some(product.hidden);
           product.hidden = hidden;
           is_update_applied = true;
         }
       }
 
-      /*
-      // Move this product to another storefront:
-      if let Some(storefront_id) = update.storefront_id {
-        ensure!(product.is_root_product(), Error::<T>::CannotUpdateStorefrontIdOnComment);
-
-        if let Some(product_storefront_id) = product.storefront_id {
-          if storefront_id != product_storefront_id {
-            Storefronts::<T>::ensure_storefront_exists(storefront_id)?;
-            // TODO check that the current user has CreateProducts permission in new storefront_id.
-            // TODO test whether new_storefront.products_count increases
-            // TODO test whether new_storefront.hidden_products_count increases if product is hidden
-            // TODO update (hidden_)replies_count of ancestors
-            // TODO test whether reactions are updated correctly:
-            //  - subtract score from an old storefront
-            //  - add score to a new storefront
-
-            // Remove product_id from its old storefront:
-            ProductIdsByStorefrontId::mutate(product_storefront_id, |product_ids| vec_remove_on(product_ids, product_id));
-
-            // Add product_id to its new storefront:
-            ProductIdsByStorefrontId::mutate(storefront_id, |ids| ids.push(product_id));
-            old_data.storefront_id = product.storefront_id;
-            product.storefront_id = Some(storefront_id);
-            is_update_applied = true;
+      if let Some(price_opt) = update.price {
+        if price_opt != product.price {
+          if let Some(price) = price_opt {
+            Self::ensure_valid_price(product.extension, price)?;
           }
+
+          old_data.price = Some(product.price);
+          product.price = price_opt;
+          is_update_applied = true;
         }
       }
-      */
 
       // Update this product only if at least one field should be updated:
       if is_update_applied {
         product.updated = Some(WhoAndWhen::<T>::new(editor.clone()));
 
+        if let ProductExtension::Comment(comment_ext) = product.extension {
+          if old_data.hidden.is_some() {
+            Self::update_counters_on_comment_hidden_change(&comment_ext, product.hidden)?;
+          }
+        }
+
         if let Some(storefront) = storefront_opt {
             <StorefrontById<T>>::insert(storefront.id, storefront);
         }
 
+        // Move the product after its old storefront has counted a hidden change,
+        // so that both storefronts count it by its new hidden state.
+        if let Some(new_storefront) = new_storefront_opt {
+          Self::move_product_to_storefront(&mut product, new_storefront);
+        }
+
+        if let Some(old_categories) = &old_data.categories {
+          Self::remove_from_category_index(product.id, old_categories);
+          Self::add_to_category_index(product.id, &product.categories);
+        }
+
         <ProductById<T>>::insert(product.id, product.clone());
         T::AfterProductUpdated::after_product_updated(editor.clone(), &product, old_data);
 
@@ -375,5 +561,88 @@ decl_module! {
       }
       Ok(())
     }
+
+    /// Delete a product together with all its nested replies.
+    /// The weight grows with `max_replies` that must be no less than the number of these replies.
+    #[weight = 100_000 + T::DbWeight::get().reads_writes(9, 9)
+      + (*max_replies as Weight).saturating_mul(25_000 + T::DbWeight::get().reads_writes(8, 8))]
+    pub fn delete_product(origin, product_id: ProductId, max_replies: u16) -> DispatchResult {
+      let deleter = ensure_signed(origin)?;
+
+      let product = Self::require_product(product_id)?;
+      ensure!(product.replies_count <= max_replies, Error::<T>::TooManyRepliesToDelete);
+
+      let is_owner = product.is_owner(&deleter);
+
+      let (permission_to_check, permission_error) = if product.is_comment() {
+        ensure!(is_owner, Error::<T>::NotACommentAuthor);
+        (StorefrontPermission::DeleteOwnComments, Error::<T>::NoPermissionToDeleteOwnComments)
+      } else if is_owner {
+        (StorefrontPermission::DeleteOwnProducts, Error::<T>::NoPermissionToDeleteOwnProducts)
+      } else {
+        (StorefrontPermission::DeleteAnyProduct, Error::<T>::NoPermissionToDeleteAnyProduct)
+      };
+
+      Storefronts::ensure_account_has_storefront_permission(
+        deleter.clone(),
+        &product.get_storefront()?,
+        permission_to_check,
+        permission_error.into()
+      )?;
+
+      Self::delete_product_with_replies(deleter.clone(), &product)?;
+
+      Self::deposit_event(RawEvent::ProductDeleted(deleter, product_id));
+      Ok(())
+    }
+
+    /// Add or remove items available for ordering, or stop tracking the stock.
+    #[weight = 25_000 + T::DbWeight::get().reads_writes(3, 1)]
+    pub fn restock_product(origin, product_id: ProductId, update: StockUpdate) -> DispatchResult {
+      let editor = ensure_signed(origin)?;
+
+      let mut product = Self::require_product(product_id)?;
+      ensure!(product.extension == ProductExtension::RegularProduct, Error::<T>::StockIsAllowedOnlyOnRegularProduct);
+
+      let stock = Self::apply_stock_update(product.stock, update)?;
+      ensure!(product.stock != stock, Error::<T>::NoUpdatesForProductStock);
+
+      let (permission_to_check, permission_error) = if product.is_owner(&editor) {
+        (StorefrontPermission::UpdateOwnProducts, Error::<T>::NoPermissionToUpdateOwnProducts)
+      } else {
+        (StorefrontPermission::UpdateAnyProduct, Error::<T>::NoPermissionToUpdateAnyProduct)
+      };
+
+      Storefronts::ensure_account_has_storefront_permission(
+        editor.clone(),
+        &product.get_storefront()?,
+        permission_to_check,
+        permission_error.into()
+      )?;
+
+      product.stock = stock;
+      product.updated = Some(WhoAndWhen::<T>::new(editor.clone()));
+      <ProductById<T>>::insert(product_id, product);
+
+      Self::deposit_event(RawEvent::ProductRestocked(editor, product_id));
+      Ok(())
+    }
   }
 }
+
+impl<T: Trait> BeforeStorefrontDeleted<T> for Module<T> {
+    /// Hide all products of a storefront that is about to be archived.
+    fn before_storefront_deleted(_account: T::AccountId, storefront: &mut Storefront<T>) -> DispatchResult {
+        for product_id in Self::product_ids_by_storefront_id(storefront.id) {
+            if let Some(mut product) = Self::product_by_id(product_id) {
+                if !product.hidden {
+                    product.hidden = true;
+                    storefront.inc_hidden_products();
+                    <ProductById<T>>::insert(product_id, product);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use frame_support::{storage::IterableStorageMap, weights::Weight};
+use sp_std::cell::Cell;
+
+use super::*;
+
+/// A product as it was stored before prices, stock, categories and review ratings were added.
+#[derive(Decode)]
+struct OldProduct<T: Trait> {
+    id: ProductId,
+    created: WhoAndWhen<T>,
+    updated: Option<WhoAndWhen<T>>,
+
+    owner: T::AccountId,
+
+    extension: ProductExtension,
+
+    storefront_id: Option<StorefrontId>,
+    content: Content,
+    hidden: bool,
+
+    replies_count: u16,
+    hidden_replies_count: u16,
+
+    shares_count: u16,
+    upvotes_count: u16,
+    downvotes_count: u16,
+
+    score: i32,
+}
+
+impl<T: Trait> From<OldProduct<T>> for Product<T> {
+    fn from(old: OldProduct<T>) -> Self {
+        Product {
+            id: old.id,
+            created: old.created,
+            updated: old.updated,
+            owner: old.owner,
+            extension: old.extension,
+            storefront_id: old.storefront_id,
+            content: old.content,
+            hidden: old.hidden,
+            categories: Vec::new(),
+            price: None,
+            stock: None,
+            replies_count: old.replies_count,
+            hidden_replies_count: old.hidden_replies_count,
+            shares_count: old.shares_count,
+            upvotes_count: old.upvotes_count,
+            downvotes_count: old.downvotes_count,
+            score: old.score,
+            reviews_count: 0,
+            ratings_sum: 0,
+        }
+    }
+}
+
+/// Convert all products to the current layout. Migrated products have no price,
+/// so they cannot be ordered until their owners set one.
+pub(crate) fn migrate_products<T: Trait>() -> Weight {
+    let products_count = Cell::new(0u64);
+
+    ProductById::<T>::translate(|_product_id, old_product: OldProduct<T>| {
+        products_count.set(products_count.get() + 1);
+        Some(old_product.into())
+    });
+
+    IsProductLayoutMigrated::put(true);
+
+    let changes = products_count.get();
+    T::DbWeight::get().reads_writes(changes, changes + 1)
+}
@@ -11,7 +11,7 @@ use sp_std::prelude::*;
 use frame_system::{self as system, ensure_signed};
 
 use pallet_permissions::StorefrontPermission;
-use pallet_products::{Module as Products, Product, ProductById, ProductId};
+use pallet_products::{Module as Products, BeforeProductDeleted, Product, ProductById, ProductId};
 use pallet_storefronts::Module as Storefronts;
 use pallet_utils::{vec_remove_on, WhoAndWhen};
 
@@ -123,6 +123,9 @@ decl_module! {
       ensure!(!storefront.hidden, Error::<T>::CannotReactWhenStorefrontHidden);
       ensure!(Products::<T>::is_root_product_visible(product_id)?, Error::<T>::CannotReactWhenProductHidden);
 
+      Storefronts::<T>::ensure_account_is_not_blocked(owner.clone(), storefront.id)?;
+      Products::<T>::ensure_product_is_not_blocked(product_id, storefront.id)?;
+
       let reaction_id = Self::insert_new_reaction(owner.clone(), kind);
 
       match kind {
@@ -251,6 +254,18 @@ impl<T: Trait> Module<T> {
     }
 }
 
+impl<T: Trait> BeforeProductDeleted<T> for Module<T> {
+    fn before_product_deleted(_account: T::AccountId, product: &Product<T>) -> DispatchResult {
+        for reaction_id in ReactionIdsByProductId::take(product.id) {
+            if let Some(reaction) = <ReactionById<T>>::take(reaction_id) {
+                <ProductReactionIdByAccount<T>>::remove((reaction.created.account, product.id));
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /// Handler that will be called right before the product reaction is toggled.
 pub trait ProductReactionScores<T: Trait> {
     fn score_product_on_reaction(actor: T::AccountId, product: &mut Product<T>, reaction_kind: ReactionKind) -> DispatchResult;
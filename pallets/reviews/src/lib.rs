@@ -0,0 +1,219 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Decode, Encode};
+use frame_support::{
+    decl_error, decl_event, decl_module, decl_storage, ensure,
+    dispatch::{DispatchError, DispatchResult}, traits::Get,
+};
+use sp_runtime::RuntimeDebug;
+use sp_std::prelude::*;
+use frame_system::{self as system, ensure_signed};
+
+use pallet_orders::{Module as Orders, OrderId, OrderState};
+use pallet_permissions::StorefrontPermission;
+use pallet_products::{Module as Products, BeforeProductDeleted, Product, ProductById, ProductId};
+use pallet_storefronts::{Module as Storefronts, StorefrontById};
+use pallet_utils::{Module as Utils, StorefrontId, WhoAndWhen, Content};
+
+pub type ReviewId = u64;
+
+pub const MIN_RATING: u8 = 1;
+pub const MAX_RATING: u8 = 5;
+
+#[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
+pub struct Review<T: Trait> {
+    pub id: ReviewId,
+    pub created: WhoAndWhen<T>,
+    pub updated: Option<WhoAndWhen<T>>,
+
+    pub order_id: OrderId,
+    pub product_id: ProductId,
+    pub storefront_id: StorefrontId,
+
+    /// A rating from `MIN_RATING` to `MAX_RATING`.
+    pub rating: u8,
+    pub content: Content,
+
+    /// A public reply of the seller to this review.
+    pub reply: Option<ReviewReply<T>>,
+}
+
+#[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
+pub struct ReviewReply<T: Trait> {
+    pub created: WhoAndWhen<T>,
+    pub content: Content,
+}
+
+/// The pallet's configuration trait.
+pub trait Trait: system::Trait
+    + pallet_utils::Trait
+    + pallet_orders::Trait
+{
+    /// The overarching event type.
+    type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+}
+
+// This pallet's storage items.
+decl_storage! {
+    trait Store for Module<T: Trait> as ReviewsModule {
+        pub NextReviewId get(fn next_review_id): ReviewId = 1;
+
+        pub ReviewById get(fn review_by_id):
+            map hasher(twox_64_concat) ReviewId => Option<Review<T>>;
+
+        pub ReviewIdByOrderAndProduct get(fn review_id_by_order_and_product):
+            map hasher(twox_64_concat) (OrderId, ProductId) => Option<ReviewId>;
+
+        pub ReviewIdsByProductId get(fn review_ids_by_product_id):
+            map hasher(twox_64_concat) ProductId => Vec<ReviewId>;
+    }
+}
+
+decl_event!(
+    pub enum Event<T> where
+        <T as system::Trait>::AccountId,
+    {
+        ReviewCreated(AccountId, ProductId, ReviewId),
+        ReviewReplied(AccountId, ReviewId),
+    }
+);
+
+decl_error! {
+    pub enum Error for Module<T: Trait> {
+        /// Review was not found by id.
+        ReviewNotFound,
+        /// Rating should be in range from 1 to 5.
+        InvalidRating,
+        /// Only the buyer of an order can review its products.
+        NotAnOrderBuyer,
+        /// Products can be reviewed only when an order is complete.
+        OrderIsNotComplete,
+        /// Product is not a part of this order.
+        ProductNotInOrder,
+        /// This product has already been reviewed within this order.
+        ReviewAlreadyExists,
+        /// User has no permission to reply to reviews on behalf of the storefront.
+        NoPermissionToReplyToReviews,
+        /// The seller has already replied to this review.
+        ReplyAlreadyExists,
+    }
+}
+
+decl_module! {
+  pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+
+    // Initializing errors
+    type Error = Error<T>;
+
+    // Initializing events
+    fn deposit_event() = default;
+
+    /// Review a product of a complete order. Ratings are aggregated on the product and its storefront.
+    #[weight = 25_000 + T::DbWeight::get().reads_writes(5, 6)]
+    pub fn create_review(
+      origin,
+      order_id: OrderId,
+      product_id: ProductId,
+      rating: u8,
+      content: Content
+    ) -> DispatchResult {
+      let buyer = ensure_signed(origin)?;
+
+      ensure!(rating >= MIN_RATING && rating <= MAX_RATING, Error::<T>::InvalidRating);
+      Utils::<T>::is_valid_content(content.clone())?;
+
+      let order = Orders::<T>::require_order(order_id)?;
+      ensure!(order.is_owner(&buyer), Error::<T>::NotAnOrderBuyer);
+      ensure!(order.order_state == OrderState::Complete, Error::<T>::OrderIsNotComplete);
+      ensure!(
+        order.items.iter().any(|item| item.product_id == product_id),
+        Error::<T>::ProductNotInOrder
+      );
+      ensure!(
+        Self::review_id_by_order_and_product((order_id, product_id)).is_none(),
+        Error::<T>::ReviewAlreadyExists
+      );
+
+      let mut product = Products::<T>::require_product(product_id)?;
+      let mut storefront = Storefronts::<T>::require_storefront(order.storefront_id)?;
+
+      let review_id = Self::next_review_id();
+      let new_review = Review::<T> {
+        id: review_id,
+        created: WhoAndWhen::<T>::new(buyer.clone()),
+        updated: None,
+        order_id,
+        product_id,
+        storefront_id: order.storefront_id,
+        rating,
+        content,
+        reply: None,
+      };
+
+      product.add_rating(rating);
+      <ProductById<T>>::insert(product_id, product);
+
+      storefront.add_rating(rating);
+      <StorefrontById<T>>::insert(storefront.id, storefront);
+
+      <ReviewById<T>>::insert(review_id, new_review);
+      ReviewIdByOrderAndProduct::insert((order_id, product_id), review_id);
+      ReviewIdsByProductId::mutate(product_id, |ids| ids.push(review_id));
+      NextReviewId::mutate(|n| { *n += 1; });
+
+      Self::deposit_event(RawEvent::ReviewCreated(buyer, product_id, review_id));
+      Ok(())
+    }
+
+    /// Publicly reply to a review. Allowed to users who can represent the storefront externally,
+    /// which includes its owner by default.
+    #[weight = 10_000 + T::DbWeight::get().reads_writes(3, 1)]
+    pub fn reply_to_review(origin, review_id: ReviewId, content: Content) -> DispatchResult {
+      let who = ensure_signed(origin)?;
+
+      Utils::<T>::ensure_content_is_some(&content)?;
+      Utils::<T>::is_valid_content(content.clone())?;
+
+      let mut review = Self::require_review(review_id)?;
+      ensure!(review.reply.is_none(), Error::<T>::ReplyAlreadyExists);
+
+      let storefront = Storefronts::<T>::require_storefront(review.storefront_id)?;
+      Storefronts::<T>::ensure_account_has_storefront_permission(
+        who.clone(),
+        &storefront,
+        StorefrontPermission::RepresentStorefrontExternally,
+        Error::<T>::NoPermissionToReplyToReviews.into()
+      )?;
+
+      review.reply = Some(ReviewReply {
+        created: WhoAndWhen::<T>::new(who.clone()),
+        content,
+      });
+      <ReviewById<T>>::insert(review_id, review);
+
+      Self::deposit_event(RawEvent::ReviewReplied(who, review_id));
+      Ok(())
+    }
+  }
+}
+
+impl<T: Trait> Module<T> {
+
+    /// Get `Review` by id from the storage or return `ReviewNotFound` error.
+    pub fn require_review(review_id: ReviewId) -> Result<Review<T>, DispatchError> {
+        Ok(Self::review_by_id(review_id).ok_or(Error::<T>::ReviewNotFound)?)
+    }
+}
+
+impl<T: Trait> BeforeProductDeleted<T> for Module<T> {
+    /// Remove reviews of a deleted product. Their ratings stay counted in the storefront,
+    /// so a seller cannot get rid of bad ratings by deleting a product.
+    fn before_product_deleted(_account: T::AccountId, product: &Product<T>) -> DispatchResult {
+        for review_id in ReviewIdsByProductId::take(product.id) {
+            if let Some(review) = <ReviewById<T>>::take(review_id) {
+                ReviewIdByOrderAndProduct::remove((review.order_id, review.product_id));
+            }
+        }
+        Ok(())
+    }
+}
@@ -2,6 +2,7 @@ use super::*;
 
 use frame_support::dispatch::DispatchError;
 use pallet_permissions::StorefrontPermissionsContext;
+use pallet_storefronts::{BeforeStorefrontDeleted, Storefront};
 use pallet_utils::StorefrontId;
 
 impl<T: Trait> Module<T> {
@@ -27,6 +28,13 @@ impl<T: Trait> Module<T> {
     )
   }
 
+  /// Return a storage deposit of a deleted role to the account that created it.
+  pub(crate) fn release_role_storage_deposit(role_id: RoleId) {
+    if let Some(deposit) = <StorageDepositByRoleId<T>>::take(role_id) {
+      Utils::<T>::release_storage_deposit(deposit);
+    }
+  }
+
   fn ensure_user_has_storefront_permission_with_load_storefront(
     user: User<T::AccountId>,
     storefront_id: StorefrontId,
@@ -200,3 +208,18 @@ impl<T: Trait> PermissionChecker for Module<T> {
     )
   }
 }
+
+impl<T: Trait + pallet_storefronts::Trait> BeforeStorefrontDeleted<T> for Module<T> {
+  /// Delete all roles of a storefront that is about to be archived.
+  fn before_storefront_deleted(_account: T::AccountId, storefront: &mut Storefront<T>) -> DispatchResult {
+    for role_id in RoleIdsByStorefrontId::take(storefront.id) {
+      if let Some(role) = <RoleById<T>>::take(role_id) {
+        role.revoke_from_users(Self::users_by_role_id(role_id));
+        <UsersByRoleId<T>>::remove(role_id);
+        Self::release_role_storage_deposit(role_id);
+      }
+    }
+
+    Ok(())
+  }
+}
@@ -13,7 +13,7 @@ use frame_system::{self as system, ensure_signed};
 
 use df_traits::{PermissionChecker, StorefrontFollowsProvider, StorefrontForRolesProvider};
 use pallet_permissions::{Module as Permissions, StorefrontPermission, StorefrontPermissionSet};
-use pallet_utils::{Module as Utils, StorefrontId, User, WhoAndWhen, Content};
+use pallet_utils::{Module as Utils, StorefrontId, User, WhoAndWhen, Content, StorageDepositOf};
 
 pub mod functions;
 
@@ -91,6 +91,8 @@ decl_error! {
         RoleAlreadyDisabled,
         /// Cannot enable a role that is already enabled.
         RoleAlreadyEnabled,
+        /// Cannot create roles in an archived storefront.
+        StorefrontIsArchived,
     }
 }
 
@@ -116,6 +118,10 @@ decl_storage! {
         /// A list of all role ids granted to this user (account or storefront) within this storefront.
         pub RoleIdsByUserInStorefront get(fn role_ids_by_user_in_storefront):
             map hasher(blake2_128_concat) (User<T::AccountId>, StorefrontId) => Vec<RoleId>;
+
+        /// A storage deposit reserved from the creator of this role.
+        pub StorageDepositByRoleId get(fn storage_deposit_by_role_id):
+            map hasher(twox_64_concat) RoleId => Option<StorageDepositOf<T>>;
     }
 }
 
@@ -135,7 +141,7 @@ decl_module! {
     /// `content` points to the off-chain content with such additional info about this role
     /// as its name, description, color, etc.
     /// Only the storefront owner or a user with `ManageRoles` permission call this dispatch.
-    #[weight = 10_000 + T::DbWeight::get().reads_writes(2, 3)]
+    #[weight = 10_000 + T::DbWeight::get().reads_writes(2, 4)]
     pub fn create_role(
       origin,
       storefront_id: StorefrontId,
@@ -149,15 +155,20 @@ decl_module! {
 
       Utils::<T>::is_valid_content(content.clone())?;
 
+      let storefront = T::Storefronts::get_storefront(storefront_id)?;
+      ensure!(!storefront.archived, Error::<T>::StorefrontIsArchived);
+
       Self::ensure_role_manager(who.clone(), storefront_id)?;
 
       let permissions_set = BTreeSet::from_iter(permissions.into_iter());
       let new_role = Role::<T>::new(who.clone(), storefront_id, time_to_live, content, permissions_set)?;
 
       let next_role_id = new_role.id.checked_add(1).ok_or(Error::<T>::RoleIdOverflow)?;
+      let deposit = Utils::<T>::reserve_storage_deposit(&who, &new_role)?;
       NextRoleId::put(next_role_id);
 
       <RoleById<T>>::insert(new_role.id, new_role.clone());
+      <StorageDepositByRoleId<T>>::insert(new_role.id, deposit);
       RoleIdsByStorefrontId::mutate(storefront_id, |role_ids| { role_ids.push(new_role.id) });
 
       Self::deposit_event(RawEvent::RoleCreated(who, storefront_id, new_role.id));
@@ -221,7 +232,7 @@ decl_module! {
 
     /// Delete a role from all associated storage items.
     /// Only the storefront owner or a user with `ManageRoles` permission call this dispatch.
-    #[weight = 1_000_000 + T::DbWeight::get().reads_writes(6, 5)]
+    #[weight = 1_000_000 + T::DbWeight::get().reads_writes(7, 6)]
     pub fn delete_role(origin, role_id: RoleId) -> DispatchResult {
       let who = ensure_signed(origin)?;
 
@@ -246,6 +257,7 @@ decl_module! {
 
       <RoleById<T>>::remove(role_id);
       <UsersByRoleId<T>>::remove(role_id);
+      Self::release_role_storage_deposit(role_id);
 
       Self::deposit_event(RawEvent::RoleDeleted(who, role_id));
       Ok(())
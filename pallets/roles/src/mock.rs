@@ -99,6 +99,8 @@ impl pallet_utils::Trait for Test {
     type Currency = Balances;
     type MinHandleLen = MinHandleLen;
     type MaxHandleLen = MaxHandleLen;
+    type StorageDepositBase = ();
+    type StorageDepositPerByte = ();
 }
 
 parameter_types! {
@@ -152,6 +154,13 @@ parameter_types! {
 
       SP::SuggestEntityStatus,
       SP::UpdateEntityStatus,
+
+      SP::AcceptOrders,
+      SP::ShipOrders,
+      SP::RefundOrders,
+      SP::ViewOrderMessages,
+      SP::OpenDisputes,
+      SP::ResolveDisputes,
     ].into_iter())),
   };
 }
@@ -185,7 +194,7 @@ impl<T: Trait> StorefrontForRolesProvider for Module<T> {
     // Currently, we have a list of valid storefront id's to check
     fn get_storefront(id: StorefrontId) -> Result<StorefrontForRoles<Self::AccountId>, DispatchError> {
         if self::valid_storefront_ids().contains(&id) {
-            return Ok(StorefrontForRoles { owner: ACCOUNT1, permissions: None })
+            return Ok(StorefrontForRoles { owner: ACCOUNT1, permissions: None, archived: false })
         }
 
         Err("StorefrontNotFound".into())
@@ -9,10 +9,11 @@ use sp_runtime::RuntimeDebug;
 use sp_std::prelude::*;
 use frame_system::{self as system};
 
-use pallet_products::{ProductScores, Product, ProductById, ProductExtension, ProductId};
+use pallet_orders::{AfterOrderUpdated, Order, OrderState, OrderUpdate};
+use pallet_products::{Module as Products, BeforeProductDeleted, ProductScores, Product, ProductById, ProductExtension, ProductId};
 use pallet_profile_follows::{BeforeAccountFollowed, BeforeAccountUnfollowed};
 use pallet_profiles::{Module as Profiles, SocialAccountById};
-use pallet_reactions::{ProductReactionScores, ReactionKind};
+use pallet_reactions::{Module as Reactions, ProductReactionScores, ReactionKind};
 use pallet_storefront_follows::{BeforeStorefrontFollowed, BeforeStorefrontUnfollowed};
 use pallet_storefronts::{Storefront, StorefrontById};
 use pallet_utils::log_2;
@@ -28,6 +29,9 @@ pub enum ScoringAction {
     ShareComment,
     FollowStorefront,
     FollowAccount,
+    CompleteOrder,
+    RefuseOrder,
+    LoseDispute,
 }
 
 impl Default for ScoringAction {
@@ -61,6 +65,11 @@ pub trait Trait: system::Trait
     type ShareCommentActionWeight: Get<i16>;
     type UpvoteCommentActionWeight: Get<i16>;
     type DownvoteCommentActionWeight: Get<i16>;
+
+    // Weights of the order outcomes, applied to a seller and their storefront
+    type CompleteOrderActionWeight: Get<i16>;
+    type RefuseOrderActionWeight: Get<i16>;
+    type LoseDisputeActionWeight: Get<i16>;
 }
 
 decl_error! {
@@ -110,6 +119,11 @@ decl_module! {
         const DownvoteCommentActionWeight: i16 = T::DownvoteCommentActionWeight::get();
         const ShareCommentActionWeight: i16 = T::ShareCommentActionWeight::get();
 
+        /// Weights of the order outcomes
+        const CompleteOrderActionWeight: i16 = T::CompleteOrderActionWeight::get();
+        const RefuseOrderActionWeight: i16 = T::RefuseOrderActionWeight::get();
+        const LoseDisputeActionWeight: i16 = T::LoseDisputeActionWeight::get();
+
         // Initializing errors
         type Error = Error<T>;
 
@@ -342,6 +356,18 @@ impl<T: Trait> Module<T> {
             ShareComment => T::ShareCommentActionWeight::get(),
             FollowStorefront => T::FollowStorefrontActionWeight::get(),
             FollowAccount => T::FollowAccountActionWeight::get(),
+            CompleteOrder => T::CompleteOrderActionWeight::get(),
+            RefuseOrder => T::RefuseOrderActionWeight::get(),
+            LoseDispute => T::LoseDisputeActionWeight::get(),
+        }
+    }
+
+    pub fn scoring_action_by_order_state(order_state: OrderState) -> Option<ScoringAction> {
+        match order_state {
+            OrderState::Complete => Some(ScoringAction::CompleteOrder),
+            OrderState::Refused => Some(ScoringAction::RefuseOrder),
+            OrderState::SlashedSeller | OrderState::SlashedBoth => Some(ScoringAction::LoseDispute),
+            _ => None,
         }
     }
 }
@@ -434,3 +460,75 @@ impl<T: Trait> ProductReactionScores<T> for Module<T> {
         Self::change_product_score_with_reaction(actor, product, reaction_kind)
     }
 }
+
+impl<T: Trait> BeforeProductDeleted<T> for Module<T> {
+    /// Remove score diffs of a product that is about to be deleted.
+    /// The reputation that its owner has earned with this product is kept.
+    fn before_product_deleted(_account: T::AccountId, product: &Product<T>) -> DispatchResult {
+        let product_id = product.id;
+
+        for reaction_id in Reactions::<T>::reaction_ids_by_product_id(product_id) {
+            if let Some(reaction) = Reactions::<T>::reaction_by_id(reaction_id) {
+                let action = Self::scoring_action_by_product_extension(product.extension, reaction.kind);
+                <ProductScoreByAccount<T>>::remove((reaction.created.account, product_id, action));
+            }
+        }
+
+        let share_action =
+            if product.is_comment() { ScoringAction::ShareComment }
+            else { ScoringAction::ShareProduct };
+
+        for sharing_product_id in Products::<T>::shared_product_ids_by_original_product_id(product_id) {
+            if let Some(sharing_product) = Products::<T>::product_by_id(sharing_product_id) {
+                <ProductScoreByAccount<T>>::remove((sharing_product.owner, product_id, share_action));
+            }
+        }
+
+        // This hook is called for every deleted reply, so a score diff of a comment
+        // on its root product is removed here instead of walking all replies of the root.
+        if let ProductExtension::Comment(comment_ext) = product.extension {
+            <ProductScoreByAccount<T>>::remove(
+                (product.owner.clone(), comment_ext.root_product_id, ScoringAction::CreateComment)
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Trait + pallet_orders::Trait> Module<T> {
+
+    /// Change a seller reputation and a storefront score by an order outcome.
+    /// Unlike social actions, order outcomes are never reverted, so every order counts.
+    fn score_seller_on_order_outcome(order: &Order<T>, action: ScoringAction) {
+        let buyer_reputation = Profiles::<T>::get_or_new_social_account(order.owner.clone()).reputation;
+        let score_diff = Self::score_diff_for_action(buyer_reputation, action);
+
+        if let Some(mut storefront) = <StorefrontById<T>>::get(order.storefront_id) {
+            storefront.change_score(score_diff);
+            <StorefrontById<T>>::insert(storefront.id, storefront);
+        }
+
+        let mut social_account = Profiles::<T>::get_or_new_social_account(order.seller.clone());
+        if social_account.reputation as i64 + score_diff as i64 <= 1 {
+            social_account.reputation = 1;
+        } else {
+            social_account.change_reputation(score_diff);
+        }
+        <SocialAccountById<T>>::insert(order.seller.clone(), social_account.clone());
+
+        Self::deposit_event(RawEvent::AccountReputationChanged(order.seller.clone(), action, social_account.reputation));
+    }
+}
+
+impl<T: Trait + pallet_orders::Trait> AfterOrderUpdated<T> for Module<T> {
+    fn after_order_updated(_account: T::AccountId, order: &Order<T>, old_data: OrderUpdate) {
+        if old_data.order_state == order.order_state {
+            return
+        }
+
+        if let Some(action) = Self::scoring_action_by_order_state(order.order_state) {
+            Self::score_seller_on_order_outcome(order, action);
+        }
+    }
+}
@@ -4,23 +4,29 @@
 use codec::{Decode, Encode};
 use sp_std::prelude::*;
 use sp_runtime::RuntimeDebug;
-use sp_runtime::traits::{Zero, Dispatchable, Saturating, SaturatedConversion};
+use sp_runtime::traits::{Zero, Dispatchable, Saturating};
 use frame_support::{
     decl_error, decl_event, decl_module, decl_storage, ensure,
     weights::{
-        GetDispatchInfo, DispatchClass, WeighData,
+        GetDispatchInfo, DispatchClass, DispatchInfo, WeighData,
         Weight, ClassifyDispatch, PaysFee, Pays,
     },
-    dispatch::{DispatchError, DispatchResult, ProductDispatchInfo},
+    dispatch::{DispatchError, DispatchResult, DispatchResultWithPostInfo, PostDispatchInfo},
     traits::{
         Currency, Get, ExistenceRequirement,
-        OriginTrait, IsType, Filter,
+        OriginTrait, IsType, Filter, InstanceFilter,
     },
     Parameter,
 };
 use frame_system::{self as system, ensure_signed};
 
-use pallet_utils::WhoAndWhen;
+use pallet_utils::{WhoAndWhen, vec_remove_on};
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
 
 struct CalculateProxyWeight<T: Trait>(Box<<T as Trait>::Call>);
 impl<T: Trait> WeighData<(&Box<<T as Trait>::Call>,)> for CalculateProxyWeight<T> {
@@ -52,7 +58,17 @@ impl<T: Trait> PaysFee<(&Box<<T as Trait>::Call>,)> for CalculateProxyWeight<T>
 
 type BalanceOf<T> = <<T as pallet_utils::Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
 
-// TODO define session key permissions
+/// Computes a fee the same way it's charged for an extrinsic with such length and dispatch info,
+/// e.g. with `pallet_transaction_payment::Module::compute_fee`.
+pub trait ComputeProxyFee<Balance> {
+    fn compute_fee(len: u32, info: &DispatchInfo) -> Balance;
+}
+
+impl<Balance: Zero> ComputeProxyFee<Balance> for () {
+    fn compute_fee(_len: u32, _info: &DispatchInfo) -> Balance {
+        Zero::zero()
+    }
+}
 
 #[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
 pub struct SessionKey<T: Trait> {
@@ -71,7 +87,15 @@ pub struct SessionKey<T: Trait> {
     /// How much tokens this session key already spent.
     pub spent: BalanceOf<T>,
 
-    // TODO allowed_actions: ...
+    /// Filters of calls this session key is allowed to proxy.
+    pub allowed_calls: Vec<T::CallFilter>,
+}
+
+#[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
+pub struct SessionKeyUpdate<BlockNumber, Balance, CallFilter> {
+    pub time_to_live: Option<BlockNumber>,
+    pub limit: Option<Option<Balance>>,
+    pub allowed_calls: Option<Vec<CallFilter>>,
 }
 
 /// The pallet's configuration trait.
@@ -81,26 +105,38 @@ pub trait Trait: system::Trait + pallet_utils::Trait {
 
     /// The overarching call type.
     type Call: Parameter
-        + Dispatchable<Origin=Self::Origin, ProductInfo=ProductDispatchInfo>
+        + Dispatchable<Origin=Self::Origin, PostInfo=PostDispatchInfo>
         + GetDispatchInfo + From<frame_system::Call<Self>>
         + IsType<<Self as frame_system::Trait>::Call>;
 
     /// The maximum amount of session keys allowed for a single account.
     type MaxSessionKeysPerAccount: Get<u16>;
 
+    /// The maximum amount of allowed call filters per a single session key.
+    type MaxAllowedCallsPerKey: Get<u16>;
+
+    /// A runtime-defined kind of calls a session key can be allowed to proxy,
+    /// e.g. an enum that matches variants of the runtime `Call`.
+    type CallFilter: Parameter + InstanceFilter<<Self as Trait>::Call>;
+
     /// Base Call filter for the session keys' proxy
     type BaseFilter: Filter<<Self as Trait>::Call>;
+
+    /// Fee calculator used to refund a session key for proxy calls at the owner's expense.
+    type ProxyFee: ComputeProxyFee<BalanceOf<Self>>;
 }
 
 decl_event!(
     pub enum Event<T> where
-        <T as system::Trait>::AccountId
+        <T as system::Trait>::AccountId,
+        Balance = BalanceOf<T>
     {
         SessionKeyAdded(/* owner */ AccountId, /* session key */ AccountId),
+        SessionKeyUpdated(/* owner */ AccountId, /* session key */ AccountId),
         SessionKeyRemoved(/* session key */ AccountId),
         AllSessionKeysRemoved(/* owner */ AccountId),
-        /// A proxy was executed correctly, with the given result.
-		ProxyExecuted(DispatchResult),
+        /// A proxy was executed correctly, with the given result and a fee charged from the key owner.
+		ProxyExecuted(DispatchResult, Balance),
     }
 );
 
@@ -122,6 +158,16 @@ decl_error! {
         SessionKeyLimitReached,
         /// Only a session key owner can manage their keys.
         NeitherSessionKeyOwnerNorExpired,
+        /// Only a session key owner can update it.
+        NotASessionKeyOwner,
+        /// A session key should be allowed to proxy at least one call.
+        NoAllowedCalls,
+        /// There are too many allowed calls for a single session key.
+        TooManyAllowedCalls,
+        /// This call is not allowed for this session key.
+        CallIsNotAllowed,
+        /// Nothing to update in a session key.
+        NoUpdatesForSessionKey,
     }
 }
 
@@ -153,6 +199,8 @@ decl_module! {
 
         const MaxSessionKeysPerAccount: u16 = T::MaxSessionKeysPerAccount::get();
 
+        const MaxAllowedCallsPerKey: u16 = T::MaxAllowedCallsPerKey::get();
+
         // Initializing errors
         type Error = Error<T>;
 
@@ -165,11 +213,13 @@ decl_module! {
             key_account: T::AccountId,
             time_to_live: T::BlockNumber,
             limit: Option<BalanceOf<T>>,
+            allowed_calls: Vec<T::CallFilter>,
         ) -> DispatchResult {
             let who = ensure_signed(origin)?;
 
             ensure!(time_to_live > Zero::zero(), Error::<T>::ZeroTimeToLive);
             ensure!(limit != Some(Zero::zero()), Error::<T>::ZeroLimit);
+            Self::ensure_valid_allowed_calls(&allowed_calls)?;
             ensure!(!KeyDetails::<T>::contains_key(key_account.clone()), Error::<T>::SessionKeyAlreadyAdded);
 
             let mut keys = KeysByOwner::<T>::get(who.clone());
@@ -181,21 +231,68 @@ decl_module! {
             keys.insert(i, key_account.clone());
             KeysByOwner::<T>::insert(&who, keys);
 
-            let details = SessionKey::<T>::new(who.clone(), time_to_live, limit);
-            KeyDetails::<T>::insert(key_account.clone(), details);
-
-            let current_block = system::Module::<T>::block_number();
-            let expiration_block = current_block.saturating_add(time_to_live);
+            let details = SessionKey::<T>::new(who.clone(), time_to_live, limit, allowed_calls);
 
             SessionKeysByExpireBlock::<T>::mutate(
-                expiration_block,
+                details.expires_at,
                 |keys| keys.push((who.clone(), key_account.clone()))
             );
 
+            KeyDetails::<T>::insert(key_account.clone(), details);
+
             Self::deposit_event(RawEvent::SessionKeyAdded(who, key_account));
             Ok(())
         }
 
+        /// Update TTL, spending limit or allowed calls of a session key owned by `origin`.
+        /// A new TTL is counted from the current block.
+        #[weight = 10_000 + T::DbWeight::get().reads_writes(3, 3)]
+        fn update_key(origin,
+            key_account: T::AccountId,
+            update: SessionKeyUpdate<T::BlockNumber, BalanceOf<T>, T::CallFilter>
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let has_updates =
+                update.time_to_live.is_some() ||
+                update.limit.is_some() ||
+                update.allowed_calls.is_some();
+
+            ensure!(has_updates, Error::<T>::NoUpdatesForSessionKey);
+
+            let mut details = Self::require_key(key_account.clone())?;
+            ensure!(details.is_owner(&who), Error::<T>::NotASessionKeyOwner);
+            ensure!(!details.is_expired(), Error::<T>::SessionKeyExpired);
+
+            if let Some(time_to_live) = update.time_to_live {
+                ensure!(time_to_live > Zero::zero(), Error::<T>::ZeroTimeToLive);
+
+                let new_expires_at = system::Module::<T>::block_number().saturating_add(time_to_live);
+                if new_expires_at != details.expires_at {
+                    let key_entry = (who.clone(), key_account.clone());
+                    SessionKeysByExpireBlock::<T>::mutate(details.expires_at, |keys| vec_remove_on(keys, key_entry.clone()));
+                    SessionKeysByExpireBlock::<T>::mutate(new_expires_at, |keys| keys.push(key_entry));
+                    details.expires_at = new_expires_at;
+                }
+            }
+
+            if let Some(limit) = update.limit {
+                ensure!(limit != Some(Zero::zero()), Error::<T>::ZeroLimit);
+                details.limit = limit;
+            }
+
+            if let Some(allowed_calls) = update.allowed_calls {
+                Self::ensure_valid_allowed_calls(&allowed_calls)?;
+                details.allowed_calls = allowed_calls;
+            }
+
+            details.updated = Some(WhoAndWhen::<T>::new(who.clone()));
+            KeyDetails::<T>::insert(key_account.clone(), details);
+
+            Self::deposit_event(RawEvent::SessionKeyUpdated(who, key_account));
+            Ok(())
+        }
+
         /// A key could be removed either the origin is an owner or key is expired.
         #[weight = 10_000 + T::DbWeight::get().reads_writes(2, 2)]
         fn remove_key(origin, key_account: T::AccountId) -> DispatchResult {
@@ -225,7 +322,7 @@ decl_module! {
 
         /// `origin` is a session key
         #[weight = CalculateProxyWeight::<T>(call.clone())]
-        fn proxy(origin, call: Box<<T as Trait>::Call>) -> DispatchResult {
+        fn proxy(origin, call: Box<<T as Trait>::Call>) -> DispatchResultWithPostInfo {
             let key = ensure_signed(origin)?;
 
             let mut details = Self::require_key(key.clone())?;
@@ -235,40 +332,52 @@ decl_module! {
                 return Err(Error::<T>::SessionKeyExpired.into());
             }
 
+            ensure!(details.is_call_allowed(&call), Error::<T>::CallIsNotAllowed);
+
             let real = details.owner();
-            let can_spend: BalanceOf<T>;
+            let allowed_calls = details.allowed_calls.clone();
 
             // TODO get limit from account settings
 
+            // The actual fee is known only after dispatch, so the limit is checked against
+            // the fee of the pre-dispatch weight, which is the maximum one.
+            let len = Self::proxy_extrinsic_len(&call);
+            let max_fee = T::ProxyFee::compute_fee(len, &Self::proxy_dispatch_info(&call, None));
+
             if let Some(limit) = details.limit {
-                can_spend = limit.saturating_sub(details.spent);
-                ensure!(can_spend > Zero::zero(), Error::<T>::SessionKeyLimitReached);
+                ensure!(details.spent.saturating_add(max_fee) <= limit, Error::<T>::SessionKeyLimitReached);
             }
 
-            let call_dispatch_info = call.get_dispatch_info();
-            if call_dispatch_info.pays_fee == Pays::Yes {
-                let spent_on_call = BalanceOf::<T>::saturated_from(call_dispatch_info.weight.into());
-                T::Currency::transfer(&real, &key, spent_on_call, ExistenceRequirement::KeepAlive)?;
-
-                // TODO: what if balance left is less than InclusionFee on the next call?
+            // Nested calls (e.g. batches) are checked against the same allowed calls.
+            let mut origin: T::Origin = frame_system::RawOrigin::Signed(real.clone()).into();
+			origin.add_filter(move |c: &<T as frame_system::Trait>::Call| {
+				let c = <T as Trait>::Call::from_ref(c);
+				T::BaseFilter::filter(c) && allowed_calls.iter().any(|allowed| allowed.filter(c))
+			});
 
-                details.spent = details.spent.saturating_add(spent_on_call);
+            let e = call.clone().dispatch(origin);
+            let actual_call_weight = match &e {
+                Ok(post_info) => post_info.actual_weight,
+                Err(err) => err.post_info.actual_weight,
+            };
+
+            // The key pays a fee for this extrinsic, so the owner tops the key up by the same amount
+            // to keep enough balance on it for the next call.
+            let info = Self::proxy_dispatch_info(&call, actual_call_weight);
+            let mut fee = T::ProxyFee::compute_fee(len, &info);
+
+            if fee.is_zero() || T::Currency::transfer(&real, &key, fee, ExistenceRequirement::KeepAlive).is_err() {
+                fee = Zero::zero();
+            } else {
+                details.spent = details.spent.saturating_add(fee);
                 details.updated = Some(WhoAndWhen::<T>::new(key.clone()));
 
                 KeyDetails::<T>::insert(key, details);
             }
 
-            // TODO check that this call is among allowed calls per this account/session key.
-            let mut origin: T::Origin = frame_system::RawOrigin::Signed(real).into();
-			origin.add_filter(move |c: &<T as frame_system::Trait>::Call| {
-				let c = <T as Trait>::Call::from_ref(c);
-				T::BaseFilter::filter(c)
-			});
-
-            let e = call.dispatch(origin);
-            Self::deposit_event(RawEvent::ProxyExecuted(e.map(|_| ()).map_err(|e| e.error)));
+            Self::deposit_event(RawEvent::ProxyExecuted(e.map(|_| ()).map_err(|e| e.error), fee));
 
-            Ok(())
+            Ok(Some(info.weight).into())
         }
 
         fn on_finalize(block_number: T::BlockNumber) {
@@ -286,16 +395,22 @@ impl<T: Trait> SessionKey<T> {
         created_by: T::AccountId,
         time_to_live: T::BlockNumber,
         limit: Option<BalanceOf<T>>,
+        allowed_calls: Vec<T::CallFilter>,
     ) -> Self {
         SessionKey::<T> {
             created: WhoAndWhen::new(created_by),
             updated: None,
-            expires_at: time_to_live + <system::Module<T>>::block_number(),
+            expires_at: <system::Module<T>>::block_number().saturating_add(time_to_live),
             limit,
             spent: Zero::zero(),
+            allowed_calls,
         }
     }
 
+    pub fn is_call_allowed(&self, call: &<T as Trait>::Call) -> bool {
+        self.allowed_calls.iter().any(|allowed| allowed.filter(call))
+    }
+
     pub fn owner(&self) -> T::AccountId {
         self.created.account.clone()
     }
@@ -324,6 +439,47 @@ impl<T: Trait> Module<T> {
         Ok(Self::key_details(key_account).ok_or(Error::<T>::SessionKeyNotFound)?)
     }
 
+    /// Get the length of the proxy extrinsic that is being executed, including its signature.
+    /// Outside of an extrinsic (e.g. in tests) the encoded `call` is used as a lower bound.
+    #[allow(clippy::borrowed_box)]
+    fn proxy_extrinsic_len(call: &Box<<T as Trait>::Call>) -> u32 {
+        let len = system::Module::<T>::extrinsic_index()
+            .map(|index| system::Module::<T>::extrinsic_data(index).len())
+            .filter(|len| *len > 0)
+            .unwrap_or_else(|| call.encoded_size());
+
+        len as u32
+    }
+
+    /// Get dispatch info of the proxy extrinsic that wraps `call`.
+    /// If `actual_call_weight` is provided, it replaces the pre-dispatch weight of `call`.
+    #[allow(clippy::borrowed_box)]
+    fn proxy_dispatch_info(call: &Box<<T as Trait>::Call>, actual_call_weight: Option<Weight>) -> DispatchInfo {
+        let weigher = CalculateProxyWeight::<T>(call.clone());
+        let target = (call,);
+
+        let mut weight = weigher.weigh_data(target);
+        if let Some(actual_call_weight) = actual_call_weight {
+            let unused_weight = call.get_dispatch_info().weight.saturating_sub(actual_call_weight);
+            weight = weight.saturating_sub(unused_weight);
+        }
+
+        DispatchInfo {
+            weight,
+            class: weigher.classify_dispatch(target),
+            pays_fee: weigher.pays_fee(target),
+        }
+    }
+
+    fn ensure_valid_allowed_calls(allowed_calls: &[T::CallFilter]) -> DispatchResult {
+        ensure!(!allowed_calls.is_empty(), Error::<T>::NoAllowedCalls);
+        ensure!(
+            allowed_calls.len() <= T::MaxAllowedCallsPerKey::get() as usize,
+            Error::<T>::TooManyAllowedCalls
+        );
+        Ok(())
+    }
+
     /// Remove `SessionKey` data from storages if found
     fn try_remove_key(owner: T::AccountId, key_account: T::AccountId) -> DispatchResult {
         KeyDetails::<T>::remove(key_account.clone());
@@ -0,0 +1,248 @@
+// Creating mock runtime here
+use crate::{Module, Trait, SessionKeyUpdate, ComputeProxyFee};
+
+use codec::{Decode, Encode};
+use sp_core::H256;
+use sp_io::TestExternalities;
+use sp_runtime::{
+    traits::{BlakeTwo256, IdentityLookup}, testing::Header, RuntimeDebug, Perbill,
+};
+use std::cell::RefCell;
+
+use frame_support::{
+    impl_outer_origin, impl_outer_dispatch, parameter_types,
+    assert_ok,
+    weights::{Weight, DispatchInfo},
+    dispatch::DispatchResult,
+    traits::InstanceFilter,
+};
+use frame_system as system;
+
+impl_outer_origin! {
+    pub enum Origin for Test {}
+}
+
+impl_outer_dispatch! {
+    pub enum Call for Test where origin: Origin {
+        frame_system::System,
+        pallet_balances::Balances,
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, RuntimeDebug)]
+pub struct Test;
+
+parameter_types! {
+    pub const BlockHashCount: u64 = 250;
+    pub const MaximumBlockWeight: Weight = 1024;
+    pub const MaximumBlockLength: u32 = 2 * 1024;
+    pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
+}
+
+impl system::Trait for Test {
+    type BaseCallFilter = ();
+    type Origin = Origin;
+    type Call = Call;
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = u64;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type Event = ();
+    type BlockHashCount = BlockHashCount;
+    type MaximumBlockWeight = MaximumBlockWeight;
+    type DbWeight = ();
+    type BlockExecutionWeight = ();
+    type ExtrinsicBaseWeight = ();
+    type MaximumExtrinsicWeight = MaximumBlockWeight;
+    type MaximumBlockLength = MaximumBlockLength;
+    type AvailableBlockRatio = AvailableBlockRatio;
+    type Version = ();
+    type ModuleToIndex = ();
+    type AccountData = pallet_balances::AccountData<u64>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+}
+
+parameter_types! {
+    pub const MinimumPeriod: u64 = 5;
+}
+
+impl pallet_timestamp::Trait for Test {
+    type Moment = u64;
+    type OnTimestampSet = ();
+    type MinimumPeriod = MinimumPeriod;
+}
+
+parameter_types! {
+    pub const ExistentialDeposit: u64 = 1;
+}
+
+impl pallet_balances::Trait for Test {
+    type Balance = u64;
+    type DustRemoval = ();
+    type Event = ();
+    type ExistentialDeposit = ExistentialDeposit;
+    type AccountStore = System;
+}
+
+parameter_types! {
+    pub const MinHandleLen: u32 = 5;
+    pub const MaxHandleLen: u32 = 50;
+}
+
+impl pallet_utils::Trait for Test {
+    type Event = ();
+    type Currency = Balances;
+    type MinHandleLen = MinHandleLen;
+    type MaxHandleLen = MaxHandleLen;
+    type StorageDepositBase = ();
+    type StorageDepositPerByte = ();
+}
+
+#[derive(Encode, Decode, Clone, Copy, Eq, PartialEq, RuntimeDebug)]
+pub enum TestCallFilter {
+    Remarks,
+    Transfers,
+}
+
+impl InstanceFilter<Call> for TestCallFilter {
+    fn filter(&self, c: &Call) -> bool {
+        match self {
+            TestCallFilter::Remarks => matches!(c, Call::System(system::Call::remark(..))),
+            TestCallFilter::Transfers => matches!(c, Call::Balances(pallet_balances::Call::transfer(..))),
+        }
+    }
+}
+
+thread_local! {
+    static FEE_PER_BYTE: RefCell<u64> = RefCell::new(0);
+}
+
+/// Proxy calls are free by default. Otherwise a fee depends only on the extrinsic length.
+pub struct TestProxyFee;
+
+impl ComputeProxyFee<u64> for TestProxyFee {
+    fn compute_fee(len: u32, _info: &DispatchInfo) -> u64 {
+        FEE_PER_BYTE.with(|v| *v.borrow()) * len as u64
+    }
+}
+
+pub(crate) fn set_fee_per_byte(fee: u64) {
+    FEE_PER_BYTE.with(|v| *v.borrow_mut() = fee);
+}
+
+parameter_types! {
+    pub const MaxSessionKeysPerAccount: u16 = 2;
+    pub const MaxAllowedCallsPerKey: u16 = 2;
+}
+
+impl Trait for Test {
+    type Event = ();
+    type Call = Call;
+    type MaxSessionKeysPerAccount = MaxSessionKeysPerAccount;
+    type MaxAllowedCallsPerKey = MaxAllowedCallsPerKey;
+    type CallFilter = TestCallFilter;
+    type BaseFilter = ();
+    type ProxyFee = TestProxyFee;
+}
+
+pub(crate) type System = system::Module<Test>;
+pub(crate) type Balances = pallet_balances::Module<Test>;
+pub(crate) type SessionKeys = Module<Test>;
+
+pub(crate) type AccountId = u64;
+pub(crate) type BlockNumber = u64;
+pub(crate) type Balance = u64;
+
+pub struct ExtBuilder;
+
+impl ExtBuilder {
+    pub fn build() -> TestExternalities {
+        let mut storage = system::GenesisConfig::default()
+            .build_storage::<Test>()
+            .unwrap();
+
+        let _ = pallet_balances::GenesisConfig::<Test> {
+            balances: vec![(OWNER, OWNER_INITIAL_BALANCE)],
+        }.assimilate_storage(&mut storage);
+
+        let mut ext = TestExternalities::from(storage);
+        ext.execute_with(|| System::set_block_number(1));
+
+        ext
+    }
+
+    pub fn build_with_key() -> TestExternalities {
+        let mut ext = Self::build();
+        ext.execute_with(|| {
+            assert_ok!(_add_default_key());
+        });
+
+        ext
+    }
+}
+
+pub(crate) const OWNER_INITIAL_BALANCE: Balance = 1_000;
+
+pub(crate) const OWNER: AccountId = 1;
+pub(crate) const KEY: AccountId = 2;
+pub(crate) const ACCOUNT3: AccountId = 3;
+
+pub(crate) const TIME_TO_LIVE: BlockNumber = 100;
+
+pub(crate) fn remark_call() -> Box<Call> {
+    Box::new(Call::System(system::Call::remark(vec![])))
+}
+
+pub(crate) fn transfer_call(amount: Balance) -> Box<Call> {
+    Box::new(Call::Balances(pallet_balances::Call::transfer(ACCOUNT3, amount)))
+}
+
+pub(crate) fn _add_default_key() -> DispatchResult {
+    _add_key(None, None, None)
+}
+
+pub(crate) fn _add_key(
+    origin: Option<Origin>,
+    limit: Option<Option<Balance>>,
+    allowed_calls: Option<Vec<TestCallFilter>>,
+) -> DispatchResult {
+    SessionKeys::add_key(
+        origin.unwrap_or_else(|| Origin::signed(OWNER)),
+        KEY,
+        TIME_TO_LIVE,
+        limit.unwrap_or_default(),
+        allowed_calls.unwrap_or_else(|| vec![TestCallFilter::Remarks]),
+    )
+}
+
+pub(crate) fn _update_key(
+    origin: Option<Origin>,
+    update: SessionKeyUpdate<BlockNumber, Balance, TestCallFilter>,
+) -> DispatchResult {
+    SessionKeys::update_key(origin.unwrap_or_else(|| Origin::signed(OWNER)), KEY, update)
+}
+
+pub(crate) fn _proxy(call: Box<Call>) -> DispatchResult {
+    SessionKeys::proxy(Origin::signed(KEY), call).map(|_| ()).map_err(|e| e.error)
+}
+
+/// Notes the current extrinsic of `len` bytes, as if it is being executed in a block.
+pub(crate) fn note_proxy_extrinsic(len: usize) {
+    System::set_extrinsic_index(0);
+    System::note_extrinsic(vec![0; len]);
+}
+
+pub(crate) fn session_key_update(
+    time_to_live: Option<BlockNumber>,
+    allowed_calls: Option<Vec<TestCallFilter>>,
+) -> SessionKeyUpdate<BlockNumber, Balance, TestCallFilter> {
+    SessionKeyUpdate {
+        time_to_live,
+        limit: None,
+        allowed_calls,
+    }
+}
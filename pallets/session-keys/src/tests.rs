@@ -0,0 +1,159 @@
+use crate::{Error, mock::*};
+use frame_support::{assert_ok, assert_noop};
+
+// Add key
+// ----------------------------------------------------------------------------
+
+#[test]
+fn add_key_should_work() {
+    ExtBuilder::build().execute_with(|| {
+        assert_ok!(_add_default_key());
+
+        let key = SessionKeys::key_details(KEY).unwrap();
+        assert_eq!(key.owner(), OWNER);
+        assert_eq!(key.expires_at, 1 + TIME_TO_LIVE);
+        assert_eq!(key.allowed_calls, vec![TestCallFilter::Remarks]);
+        assert_eq!(SessionKeys::keys_by_owner(OWNER), vec![KEY]);
+
+        // 2 * ExistentialDeposit is sent to the key to keep it alive
+        assert_eq!(Balances::free_balance(KEY), 2);
+    });
+}
+
+#[test]
+fn add_key_should_fail_with_no_allowed_calls() {
+    ExtBuilder::build().execute_with(|| {
+        assert_noop!(
+            _add_key(None, None, Some(vec![])),
+            Error::<Test>::NoAllowedCalls
+        );
+    });
+}
+
+// Proxy
+// ----------------------------------------------------------------------------
+
+#[test]
+fn proxy_should_dispatch_allowed_call_on_behalf_of_owner() {
+    ExtBuilder::build().execute_with(|| {
+        assert_ok!(_add_key(None, None, Some(vec![TestCallFilter::Transfers])));
+
+        assert_ok!(_proxy(transfer_call(10)));
+
+        assert_eq!(Balances::free_balance(ACCOUNT3), 10);
+        assert_eq!(Balances::free_balance(OWNER), OWNER_INITIAL_BALANCE - 2 - 10);
+    });
+}
+
+#[test]
+fn proxy_should_fail_when_call_is_not_allowed() {
+    ExtBuilder::build_with_key().execute_with(|| {
+        assert_noop!(
+            _proxy(transfer_call(10)),
+            Error::<Test>::CallIsNotAllowed
+        );
+    });
+}
+
+#[test]
+fn proxy_should_fail_when_key_is_expired() {
+    ExtBuilder::build_with_key().execute_with(|| {
+        System::set_block_number(1 + TIME_TO_LIVE);
+
+        assert_eq!(_proxy(remark_call()), Err(Error::<Test>::SessionKeyExpired.into()));
+        assert!(SessionKeys::key_details(KEY).is_none());
+    });
+}
+
+#[test]
+fn proxy_should_refund_fee_of_full_extrinsic_to_key() {
+    ExtBuilder::build().execute_with(|| {
+        assert_ok!(_add_key(None, Some(Some(100)), None));
+        set_fee_per_byte(1);
+        note_proxy_extrinsic(40);
+
+        assert_ok!(_proxy(remark_call()));
+
+        // The fee is charged from the owner for the whole extrinsic, not only for the proxied call
+        let key = SessionKeys::key_details(KEY).unwrap();
+        assert_eq!(key.spent, 40);
+        assert_eq!(Balances::free_balance(KEY), 2 + 40);
+        assert_eq!(Balances::free_balance(OWNER), OWNER_INITIAL_BALANCE - 2 - 40);
+
+        assert_ok!(_proxy(remark_call()));
+        assert_eq!(SessionKeys::key_details(KEY).unwrap().spent, 80);
+    });
+}
+
+#[test]
+fn proxy_should_fail_when_fee_exceeds_key_limit() {
+    ExtBuilder::build().execute_with(|| {
+        assert_ok!(_add_key(None, Some(Some(100)), None));
+        set_fee_per_byte(1);
+        note_proxy_extrinsic(60);
+
+        assert_ok!(_proxy(remark_call())); // Spent 60 of 100
+
+        assert_noop!(_proxy(remark_call()), Error::<Test>::SessionKeyLimitReached);
+        assert_eq!(SessionKeys::key_details(KEY).unwrap().spent, 60);
+    });
+}
+
+// Update key
+// ----------------------------------------------------------------------------
+
+#[test]
+fn update_key_should_change_allowed_calls() {
+    ExtBuilder::build_with_key().execute_with(|| {
+        assert_noop!(_proxy(transfer_call(10)), Error::<Test>::CallIsNotAllowed);
+
+        assert_ok!(_update_key(None, session_key_update(None, Some(vec![TestCallFilter::Transfers]))));
+        assert_eq!(SessionKeys::key_details(KEY).unwrap().allowed_calls, vec![TestCallFilter::Transfers]);
+
+        assert_ok!(_proxy(transfer_call(10)));
+        assert_eq!(Balances::free_balance(ACCOUNT3), 10);
+        assert_noop!(_proxy(remark_call()), Error::<Test>::CallIsNotAllowed);
+    });
+}
+
+#[test]
+fn update_key_should_prolong_time_to_live() {
+    ExtBuilder::build_with_key().execute_with(|| {
+        System::set_block_number(50);
+
+        assert_ok!(_update_key(None, session_key_update(Some(TIME_TO_LIVE), None)));
+        assert_eq!(SessionKeys::key_details(KEY).unwrap().expires_at, 50 + TIME_TO_LIVE);
+    });
+}
+
+#[test]
+fn update_key_should_fail_when_not_an_owner() {
+    ExtBuilder::build_with_key().execute_with(|| {
+        assert_noop!(
+            _update_key(Some(Origin::signed(ACCOUNT3)), session_key_update(Some(TIME_TO_LIVE), None)),
+            Error::<Test>::NotASessionKeyOwner
+        );
+    });
+}
+
+#[test]
+fn update_key_should_fail_with_no_updates() {
+    ExtBuilder::build_with_key().execute_with(|| {
+        assert_noop!(
+            _update_key(None, session_key_update(None, None)),
+            Error::<Test>::NoUpdatesForSessionKey
+        );
+    });
+}
+
+#[test]
+fn update_key_should_fail_with_too_many_allowed_calls() {
+    ExtBuilder::build_with_key().execute_with(|| {
+        let allowed_calls = vec![TestCallFilter::Remarks, TestCallFilter::Transfers, TestCallFilter::Remarks];
+
+        assert_noop!(
+            _update_key(None, session_key_update(None, Some(allowed_calls))),
+            Error::<Test>::TooManyAllowedCalls
+        );
+    });
+}
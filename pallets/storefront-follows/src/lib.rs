@@ -10,8 +10,8 @@ use frame_system::{self as system, ensure_signed};
 
 use df_traits::StorefrontFollowsProvider;
 use pallet_profiles::{Module as Profiles, SocialAccountById};
-use pallet_storefronts::{BeforeStorefrontCreated, Module as Storefronts, Storefront, StorefrontById};
-use pallet_utils::{StorefrontId, vec_remove_on};
+use pallet_storefronts::{BeforeStorefrontCreated, BeforeStorefrontDeleted, Module as Storefronts, Storefront, StorefrontById};
+use pallet_utils::{Module as Utils, StorefrontId, StorageDepositOf, vec_remove_on};
 
 /// The pallet's configuration trait.
 pub trait Trait: system::Trait
@@ -51,6 +51,10 @@ decl_storage! {
 
         pub StorefrontsFollowedByAccount get(fn storefronts_followed_by_account):
             map hasher(blake2_128_concat) T::AccountId => Vec<StorefrontId>;
+
+        /// A storage deposit reserved from a follower. The creator of a storefront follows it for free.
+        pub StorageDepositByFollow get(fn storage_deposit_by_follow):
+            map hasher(blake2_128_concat) (T::AccountId, StorefrontId) => Option<StorageDepositOf<T>>;
     }
 }
 
@@ -72,7 +76,7 @@ decl_module! {
     // Initializing events
     fn deposit_event() = default;
 
-    #[weight = 10_000 + T::DbWeight::get().reads_writes(5, 5)]
+    #[weight = 10_000 + T::DbWeight::get().reads_writes(5, 6)]
     pub fn follow_storefront(origin, storefront_id: StorefrontId) -> DispatchResult {
       let follower = ensure_signed(origin)?;
 
@@ -80,14 +84,15 @@ decl_module! {
 
       let storefront = &mut Storefronts::require_storefront(storefront_id)?;
       ensure!(!storefront.hidden, Error::<T>::CannotFollowHiddenStorefront);
+      Storefronts::<T>::ensure_account_is_not_blocked(follower.clone(), storefront_id)?;
 
-      Self::add_storefront_follower(follower, storefront)?;
+      Self::add_storefront_follower(follower, storefront, true)?;
       <StorefrontById<T>>::insert(storefront_id, storefront);
 
       Ok(())
     }
 
-    #[weight = 10_000 + T::DbWeight::get().reads_writes(5, 5)]
+    #[weight = 10_000 + T::DbWeight::get().reads_writes(6, 6)]
     pub fn unfollow_storefront(origin, storefront_id: StorefrontId) -> DispatchResult {
       let follower = ensure_signed(origin)?;
 
@@ -99,7 +104,13 @@ decl_module! {
 }
 
 impl<T: Trait> Module<T> {
-    fn add_storefront_follower(follower: T::AccountId, storefront: &mut Storefront<T>) -> DispatchResult {
+    /// Add a follower to a storefront. A storage deposit is reserved from the follower
+    /// only if `with_storage_deposit` is true, which is not the case for a storefront creator.
+    fn add_storefront_follower(
+        follower: T::AccountId,
+        storefront: &mut Storefront<T>,
+        with_storage_deposit: bool
+    ) -> DispatchResult {
         storefront.inc_followers();
 
         let mut social_account = Profiles::get_or_new_social_account(follower.clone());
@@ -109,6 +120,12 @@ impl<T: Trait> Module<T> {
             follower.clone(), social_account.reputation, storefront)?;
 
         let storefront_id = storefront.id;
+        if with_storage_deposit {
+            let follow_key = (follower.clone(), storefront_id);
+            let deposit = Utils::<T>::reserve_storage_deposit(&follower, &follow_key)?;
+            <StorageDepositByFollow<T>>::insert(follow_key, deposit);
+        }
+
         <StorefrontFollowers<T>>::mutate(storefront_id, |followers| followers.push(follower.clone()));
         <StorefrontFollowedByAccount<T>>::insert((follower.clone(), storefront_id), true);
         <StorefrontsFollowedByAccount<T>>::mutate(follower.clone(), |storefront_ids| storefront_ids.push(storefront_id));
@@ -132,11 +149,18 @@ impl<T: Trait> Module<T> {
         <StorefrontFollowers<T>>::mutate(storefront_id, |account_ids| vec_remove_on(account_ids, follower.clone()));
         <StorefrontFollowedByAccount<T>>::remove((follower.clone(), storefront_id));
         <SocialAccountById<T>>::insert(follower.clone(), social_account);
+        Self::release_follow_storage_deposit(follower.clone(), storefront_id);
         <StorefrontById<T>>::insert(storefront_id, storefront);
 
         Self::deposit_event(RawEvent::StorefrontUnfollowed(follower, storefront_id));
         Ok(())
     }
+
+    fn release_follow_storage_deposit(follower: T::AccountId, storefront_id: StorefrontId) {
+        if let Some(deposit) = <StorageDepositByFollow<T>>::take((follower, storefront_id)) {
+            Utils::<T>::release_storage_deposit(deposit);
+        }
+    }
 }
 
 impl<T: Trait> StorefrontFollowsProvider for Module<T> {
@@ -150,7 +174,30 @@ impl<T: Trait> StorefrontFollowsProvider for Module<T> {
 impl<T: Trait> BeforeStorefrontCreated<T> for Module<T> {
     fn before_storefront_created(creator: T::AccountId, storefront: &mut Storefront<T>) -> DispatchResult {
         // Make a storefront creator the first follower of this storefront:
-        Module::<T>::add_storefront_follower(creator, storefront)
+        Module::<T>::add_storefront_follower(creator, storefront, false)
+    }
+}
+
+impl<T: Trait> BeforeStorefrontDeleted<T> for Module<T> {
+    /// Unfollow all followers of a storefront that is about to be archived.
+    fn before_storefront_deleted(_account: T::AccountId, storefront: &mut Storefront<T>) -> DispatchResult {
+        let storefront_id = storefront.id;
+
+        for follower in <StorefrontFollowers<T>>::take(storefront_id) {
+            T::BeforeStorefrontUnfollowed::before_storefront_unfollowed(follower.clone(), storefront)?;
+            storefront.dec_followers();
+
+            if let Some(mut social_account) = Profiles::social_account_by_id(follower.clone()) {
+                social_account.dec_following_storefronts();
+                <SocialAccountById<T>>::insert(follower.clone(), social_account);
+            }
+
+            <StorefrontsFollowedByAccount<T>>::mutate(follower.clone(), |storefront_ids| vec_remove_on(storefront_ids, storefront_id));
+            <StorefrontFollowedByAccount<T>>::remove((follower.clone(), storefront_id));
+            Self::release_follow_storage_deposit(follower, storefront_id);
+        }
+
+        Ok(())
     }
 }
 
@@ -1,7 +1,7 @@
 use super::*;
 
 use sp_std::collections::btree_set::BTreeSet;
-use frame_support::{dispatch::DispatchResult};
+use frame_support::{dispatch::DispatchResult, traits::OriginTrait};
 
 impl<T: Trait> Module<T> {
 
@@ -30,6 +30,18 @@ impl<T: Trait> Module<T> {
     }
 
     <StorefrontOwnersByStorefrontById<T>>::insert(storefront_id, storefront_owners);
+
+    if let Some(call) = change.call.clone() {
+      let mut origin: T::Origin = frame_system::RawOrigin::Signed(
+        Storefronts::<T>::storefront_account(storefront_id)
+      ).into();
+      origin.add_filter(|c: &<T as frame_system::Trait>::Call| {
+        T::CallFilter::filter(<T as Trait>::Call::from_ref(c))
+      });
+      let result = call.dispatch(origin).map(|_| ()).map_err(|e| e.error);
+      Self::deposit_event(RawEvent::ChangeCallExecuted(storefront_id, change_id, result));
+    }
+
     <ChangeById<T>>::insert(change_id, change);
     Self::deposit_event(RawEvent::StorefrontOwnersUpdated(who, storefront_id, change_id));
 
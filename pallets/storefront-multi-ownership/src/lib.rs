@@ -1,9 +1,16 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use codec::{Decode, Encode};
-use frame_support::{decl_error, decl_event, decl_module, decl_storage, ensure, traits::Get};
-use pallet_utils::{StorefrontId, WhoAndWhen};
-use sp_runtime::{RuntimeDebug, traits::Zero};
+use frame_support::{
+  decl_error, decl_event, decl_module, decl_storage, ensure,
+  dispatch::{DispatchResult, PostDispatchInfo},
+  traits::{Get, Filter, IsType},
+  weights::{GetDispatchInfo, Weight},
+  Parameter,
+};
+use pallet_storefronts::{Module as Storefronts, StorefrontById, StorefrontIdsByOwner};
+use pallet_utils::{StorefrontId, WhoAndWhen, vec_remove_on};
+use sp_runtime::{RuntimeDebug, traits::{Dispatchable, Zero}};
 use sp_std::collections::{btree_map::BTreeMap, btree_set::BTreeSet};
 use sp_std::prelude::*;
 use frame_system::{self as system, ensure_signed};
@@ -30,20 +37,33 @@ pub struct Change<T: Trait> {
   pub add_owners: Vec<T::AccountId>,
   pub remove_owners: Vec<T::AccountId>,
   pub new_threshold: Option<u16>,
+  /// A storefront-scoped call to dispatch on behalf of the storefront account once confirmed.
+  pub call: Option<Box<<T as Trait>::Call>>,
   pub notes: Vec<u8>,
   pub confirmed_by: Vec<T::AccountId>,
   pub expires_at: T::BlockNumber,
 }
 
-type ChangeId = u64;
+pub type ChangeId = u64;
 
 /// The pallet's configuration trait.
 pub trait Trait: system::Trait
   + pallet_timestamp::Trait
+  + pallet_utils::Trait
+  + pallet_storefronts::Trait
 {
   /// The overarching event type.
   type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
 
+  /// The overarching call type.
+  type Call: Parameter
+    + Dispatchable<Origin=Self::Origin, PostInfo=PostDispatchInfo>
+    + GetDispatchInfo + From<frame_system::Call<Self>>
+    + IsType<<Self as frame_system::Trait>::Call>;
+
+  /// Calls that a storefront account is allowed to dispatch on confirmed changes.
+  type CallFilter: Filter<<Self as Trait>::Call>;
+
   /// Minimum storefront owners allowed.
   type MinStorefrontOwners: Get<u16>;
 
@@ -107,20 +127,31 @@ decl_error! {
 
     /// Overflow when incrementing a counter of executed changes
     ChangesCountOverflow,
+    /// Only the current storefront owner can make it multi-owned
+    NotAStorefrontOwnerToCreateOwners,
+    /// This call cannot be dispatched on behalf of a storefront
+    CallIsNotAllowed,
+    /// Max weight is less than the weight of a change call
+    MaxWeightTooLow,
   }
 }
 
 // This pallet's storage items.
 decl_storage! {
   trait Store for Module<T: Trait> as StorefrontOwnersModule {
-    StorefrontOwnersByStorefrontById get(storefront_owners_by_storefront_id): map StorefrontId => Option<StorefrontOwners<T>>;
-    StorefrontIdsOwnedByAccountId get(storefront_ids_owned_by_account_id): map T::AccountId => BTreeSet<StorefrontId> = BTreeSet::new();
-
-    NextChangeId get(next_change_id): ChangeId = 1;
-    ChangeById get(change_by_id): map ChangeId => Option<Change<T>>;
-    PendingChangeIdByStorefrontId get(pending_change_id_by_storefront_id): map StorefrontId => Option<ChangeId>;
-    PendingChangeIds get(pending_change_ids): BTreeSet<ChangeId> = BTreeSet::new();
-    ExecutedChangeIdsByStorefrontId get(executed_change_ids_by_storefront_id): map StorefrontId => Vec<ChangeId>;
+    StorefrontOwnersByStorefrontById get(fn storefront_owners_by_storefront_id):
+      map hasher(twox_64_concat) StorefrontId => Option<StorefrontOwners<T>>;
+    StorefrontIdsOwnedByAccountId get(fn storefront_ids_owned_by_account_id):
+      map hasher(twox_64_concat) T::AccountId => BTreeSet<StorefrontId> = BTreeSet::new();
+
+    NextChangeId get(fn next_change_id): ChangeId = 1;
+    ChangeById get(fn change_by_id):
+      map hasher(twox_64_concat) ChangeId => Option<Change<T>>;
+    PendingChangeIdByStorefrontId get(fn pending_change_id_by_storefront_id):
+      map hasher(twox_64_concat) StorefrontId => Option<ChangeId>;
+    PendingChangeIds get(fn pending_change_ids): BTreeSet<ChangeId> = BTreeSet::new();
+    ExecutedChangeIdsByStorefrontId get(fn executed_change_ids_by_storefront_id):
+      map hasher(twox_64_concat) StorefrontId => Vec<ChangeId>;
   }
 }
 
@@ -142,6 +173,9 @@ decl_module! {
     /// Period in blocks to initialize deleting of pending changes that are outdated.
     const DeleteExpiredChangesPeriod: T::BlockNumber = T::DeleteExpiredChangesPeriod::get();
 
+    // Initializing errors
+    type Error = Error<T>;
+
     // Initializing events
     fn deposit_event() = default;
 
@@ -149,16 +183,23 @@ decl_module! {
       Self::delete_expired_changes(n);
     }
 
+    /// Make a storefront multi-owned. The storefront ownership is transferred
+    /// to the storefront account that acts only on confirmed changes of the `owners`.
+    #[weight = 50_000 + T::DbWeight::get().reads_writes(3, 4) + T::DbWeight::get().writes(owners.len() as u64)]
     pub fn create_storefront_owners(
       origin,
       storefront_id: StorefrontId,
       owners: Vec<T::AccountId>,
       threshold: u16
-    ) {
+    ) -> DispatchResult {
       let who = ensure_signed(origin)?;
 
       ensure!(Self::storefront_owners_by_storefront_id(storefront_id).is_none(), Error::<T>::StorefrontOwnersAlreadyExist);
 
+      let mut storefront = Storefronts::<T>::require_storefront(storefront_id)?;
+      ensure!(storefront.is_owner(&who), Error::<T>::NotAStorefrontOwnerToCreateOwners);
+      storefront.ensure_not_archived()?;
+
       let mut owners_map: BTreeMap<T::AccountId, bool> = BTreeMap::new();
       let mut unique_owners: Vec<T::AccountId> = Vec::new();
 
@@ -190,27 +231,45 @@ decl_module! {
         <StorefrontIdsOwnedByAccountId<T>>::mutate(owner.clone(), |ids| ids.insert(storefront_id));
       }
 
+      // From now on the storefront is managed only by the storefront account.
+      let storefront_account = Storefronts::<T>::storefront_account(storefront_id);
+      storefront.owner = storefront_account.clone();
+      <StorefrontById<T>>::insert(storefront_id, storefront);
+      <StorefrontIdsByOwner<T>>::mutate(who.clone(), |ids| vec_remove_on(ids, storefront_id));
+      <StorefrontIdsByOwner<T>>::mutate(storefront_account, |ids| ids.push(storefront_id));
+
       Self::deposit_event(RawEvent::StorefrontOwnersCreated(who, storefront_id));
+      Ok(())
     }
 
+    /// Propose to change owners or threshold, and/or to dispatch a `call`
+    /// with the storefront account as a signed origin.
+    /// Only calls allowed by `CallFilter` can be proposed.
+    #[weight = 50_000 + T::DbWeight::get().reads_writes(3, 4)]
     pub fn propose_change(
       origin,
       storefront_id: StorefrontId,
       add_owners: Vec<T::AccountId>,
       remove_owners: Vec<T::AccountId>,
       new_threshold: Option<u16>,
+      call: Option<Box<<T as Trait>::Call>>,
       notes: Vec<u8>
-    ) {
+    ) -> DispatchResult {
       let who = ensure_signed(origin)?;
 
       let has_updates =
         !add_owners.is_empty() ||
         !remove_owners.is_empty() ||
-        new_threshold.is_some();
+        new_threshold.is_some() ||
+        call.is_some();
 
       ensure!(has_updates, Error::<T>::NoUpdatesProposed);
       ensure!(notes.len() <= T::MaxChangeNotesLength::get() as usize, Error::<T>::ChangeNotesOversize);
 
+      if let Some(call) = &call {
+        ensure!(T::CallFilter::filter(call), Error::<T>::CallIsNotAllowed);
+      }
+
       let storefront_owners = Self::storefront_owners_by_storefront_id(storefront_id).ok_or(Error::<T>::StorefrontOwnersNotFound)?;
       ensure!(Self::pending_change_id_by_storefront_id(storefront_id).is_none(), Error::<T>::PendingChangeAlreadyExists);
 
@@ -233,6 +292,10 @@ decl_module! {
         }
       }
 
+      if call.is_some() {
+        fields_updated += 1;
+      }
+
       let change_id = Self::next_change_id();
       let mut new_change = Change {
         created: WhoAndWhen::<T>::new(who.clone()),
@@ -241,6 +304,7 @@ decl_module! {
         add_owners: add_owners,
         remove_owners: remove_owners,
         new_threshold: new_threshold,
+        call,
         notes,
         confirmed_by: Vec::new(),
         expires_at: <system::Module<T>>::block_number() + T::BlocksToLive::get()
@@ -254,16 +318,21 @@ decl_module! {
         NextChangeId::mutate(|n| { *n += 1; });
 
         Self::deposit_event(RawEvent::ChangeProposed(who, storefront_id, change_id));
+        Ok(())
       } else {
-        return Err(Error::<T>::NoFieldsUpdatedOnProposal.into());
+        Err(Error::<T>::NoFieldsUpdatedOnProposal.into())
       }
     }
 
+    /// Confirm a pending change. The confirmation that reaches the threshold executes the change
+    /// and pays for its call, so `max_weight` must be no less than the weight of this call.
+    #[weight = 50_000 + T::DbWeight::get().reads_writes(4, 5) + *max_weight]
     pub fn confirm_change(
       origin,
       storefront_id: StorefrontId,
-      change_id: ChangeId
-    ) {
+      change_id: ChangeId,
+      max_weight: Weight
+    ) -> DispatchResult {
       let who = ensure_signed(origin)?;
 
       let storefront_owners = Self::storefront_owners_by_storefront_id(storefront_id).ok_or(Error::<T>::StorefrontOwnersNotFound)?;
@@ -282,19 +351,24 @@ decl_module! {
       change.confirmed_by.push(who.clone());
 
       if change.confirmed_by.len() == storefront_owners.threshold as usize {
+        if let Some(call) = &change.call {
+          ensure!(call.get_dispatch_info().weight <= max_weight, Error::<T>::MaxWeightTooLow);
+        }
         Self::update_storefront_owners(who.clone(), storefront_owners, change)?;
       } else {
         <ChangeById<T>>::insert(change_id, change);
       }
 
       Self::deposit_event(RawEvent::ChangeConfirmed(who, storefront_id, change_id));
+      Ok(())
     }
 
+    #[weight = 10_000 + T::DbWeight::get().reads_writes(3, 3)]
     pub fn cancel_change(
       origin,
       storefront_id: StorefrontId,
       change_id: ChangeId
-    ) {
+    ) -> DispatchResult {
       let who = ensure_signed(origin)?;
 
       let storefront_owners = Self::storefront_owners_by_storefront_id(storefront_id).ok_or(Error::<T>::StorefrontOwnersNotFound)?;
@@ -313,6 +387,7 @@ decl_module! {
       PendingChangeIds::mutate(|set| set.remove(&change_id));
 
       Self::deposit_event(RawEvent::ProposalCanceled(who, storefront_id));
+      Ok(())
     }
   }
 }
@@ -326,5 +401,7 @@ decl_event!(
     ProposalCanceled(AccountId, StorefrontId),
     ChangeConfirmed(AccountId, StorefrontId, ChangeId),
     StorefrontOwnersUpdated(AccountId, StorefrontId, ChangeId),
+    /// A call of a confirmed change was dispatched by the storefront account with the given result.
+    ChangeCallExecuted(StorefrontId, ChangeId, DispatchResult),
   }
 );
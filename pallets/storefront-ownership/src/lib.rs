@@ -70,6 +70,7 @@ decl_module! {
 
       let storefront = Storefronts::<T>::require_storefront(storefront_id)?;
       storefront.ensure_storefront_owner(who.clone())?;
+      storefront.ensure_not_archived()?;
 
       ensure!(who != transfer_to, Error::<T>::CannotTranferToCurrentOwner);
       Storefronts::<T>::ensure_storefront_exists(storefront_id)?;
@@ -85,6 +86,7 @@ decl_module! {
       let new_owner = ensure_signed(origin)?;
 
       let mut storefront = Storefronts::require_storefront(storefront_id)?;
+      storefront.ensure_not_archived()?;
       ensure!(!storefront.is_owner(&new_owner), Error::<T>::AlreadyAStorefrontOwner);
 
       let transfer_to = Self::pending_storefront_owner(storefront_id).ok_or(Error::<T>::NoPendingTransferOnStorefront)?;
@@ -0,0 +1,21 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use sp_std::prelude::*;
+
+use pallet_permissions::StorefrontPermission;
+use pallet_utils::StorefrontId;
+
+sp_api::decl_runtime_apis! {
+	pub trait StorefrontsApi<AccountId> where
+		AccountId: Codec
+	{
+		fn get_storefront_ids_by_owner(owner: AccountId, offset: u64, limit: u64) -> Vec<StorefrontId>;
+
+		fn has_account_storefront_permission(
+			account: AccountId,
+			storefront_id: StorefrontId,
+			permission: StorefrontPermission,
+		) -> bool;
+	}
+}
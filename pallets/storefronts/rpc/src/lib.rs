@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use codec::Codec;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+use pallet_permissions::StorefrontPermission;
+use pallet_utils::StorefrontId;
+pub use pallet_storefronts_rpc_runtime_api::StorefrontsApi as StorefrontsRuntimeApi;
+
+#[rpc]
+pub trait StorefrontsApi<BlockHash, AccountId> {
+	#[rpc(name = "storefronts_getStorefrontIdsByOwner")]
+	fn get_storefront_ids_by_owner(
+		&self,
+		owner: AccountId,
+		offset: u64,
+		limit: u64,
+		at: Option<BlockHash>,
+	) -> Result<Vec<StorefrontId>>;
+
+	#[rpc(name = "storefronts_hasAccountStorefrontPermission")]
+	fn has_account_storefront_permission(
+		&self,
+		account: AccountId,
+		storefront_id: StorefrontId,
+		permission: StorefrontPermission,
+		at: Option<BlockHash>,
+	) -> Result<bool>;
+}
+
+/// A struct that implements the `StorefrontsApi`.
+pub struct Storefronts<C, Block> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<Block>,
+}
+
+impl<C, Block> Storefronts<C, Block> {
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: Default::default() }
+	}
+}
+
+/// Error code of a failed runtime API call.
+const RUNTIME_ERROR: i64 = 1;
+
+fn runtime_error(err: impl std::fmt::Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(RUNTIME_ERROR),
+		message: "Unable to query storefronts.".into(),
+		data: Some(format!("{:?}", err).into()),
+	}
+}
+
+impl<C, Block, AccountId> StorefrontsApi<<Block as BlockT>::Hash, AccountId> for Storefronts<C, Block>
+where
+	Block: BlockT,
+	C: Send + Sync + 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: StorefrontsRuntimeApi<Block, AccountId>,
+	AccountId: Codec,
+{
+	fn get_storefront_ids_by_owner(
+		&self,
+		owner: AccountId,
+		offset: u64,
+		limit: u64,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<StorefrontId>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.get_storefront_ids_by_owner(&at, owner, offset, limit).map_err(runtime_error)
+	}
+
+	fn has_account_storefront_permission(
+		&self,
+		account: AccountId,
+		storefront_id: StorefrontId,
+		permission: StorefrontPermission,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<bool> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.has_account_storefront_permission(&at, account, storefront_id, permission).map_err(runtime_error)
+	}
+}
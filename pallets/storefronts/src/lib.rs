@@ -5,15 +5,19 @@ use frame_support::{
     decl_error, decl_event, decl_module, decl_storage, ensure,
     dispatch::{DispatchError, DispatchResult},
     traits::{Get, Currency, ExistenceRequirement},
+    weights::Weight,
 };
-use sp_runtime::RuntimeDebug;
+use sp_runtime::{ModuleId, RuntimeDebug, traits::AccountIdConversion};
 use sp_std::prelude::*;
 use frame_system::{self as system, ensure_signed};
 
 use df_traits::{StorefrontForRoles, StorefrontForRolesProvider};
 use df_traits::{PermissionChecker, StorefrontFollowsProvider};
+use df_traits::moderation::{IsAccountBlocked, IsContentBlocked, IsStorefrontBlocked};
 use pallet_permissions::{StorefrontPermission, StorefrontPermissions, StorefrontPermissionsContext};
-use pallet_utils::{Module as Utils, StorefrontId, WhoAndWhen, Content};
+use pallet_utils::{Module as Utils, StorefrontId, WhoAndWhen, Content, StorageDepositOf, paginate, vec_remove_on};
+
+mod migration;
 
 #[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
 pub struct Storefront<T: Trait> {
@@ -30,6 +34,10 @@ pub struct Storefront<T: Trait> {
     pub hidden: bool,
     pub private: bool,
 
+    /// An archived storefront is kept only for the history of its products and orders.
+    /// It cannot be updated or used as a parent of new storefronts.
+    pub archived: bool,
+
     pub products_count: u32,
     pub hidden_products_count: u32,
     pub private_products_count: u32,
@@ -37,6 +45,11 @@ pub struct Storefront<T: Trait> {
 
     pub score: i32,
 
+    /// A number of verified-purchase reviews of products of this storefront.
+    pub reviews_count: u32,
+    /// A sum of ratings of these reviews. An average rating is `ratings_sum / reviews_count`.
+    pub ratings_sum: u64,
+
     /// Allows to override the default permissions for this storefront.
     pub permissions: Option<StorefrontPermissions>,
 }
@@ -52,7 +65,10 @@ pub struct StorefrontUpdate {
     pub permissions: Option<Option<StorefrontPermissions>>,
 }
 
-type BalanceOf<T> = <<T as pallet_utils::Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+/// Used to derive an account of each storefront that receives its order proceeds.
+pub const STOREFRONT_MODULE_ID: ModuleId = ModuleId(*b"df/strfr");
+
+pub type BalanceOf<T> = <<T as pallet_utils::Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
 
 /// The pallet's configuration trait.
 pub trait Trait: system::Trait
@@ -70,7 +86,13 @@ pub trait Trait: system::Trait
 
     type AfterStorefrontUpdated: AfterStorefrontUpdated<Self>;
 
-    type StorefrontCreationFee: Get<BalanceOf<Self>>;
+    type BeforeStorefrontDeleted: BeforeStorefrontDeleted<Self>;
+
+    type IsAccountBlocked: IsAccountBlocked<Self::AccountId>;
+
+    type IsContentBlocked: IsContentBlocked;
+
+    type IsStorefrontBlocked: IsStorefrontBlocked;
 }
 
 decl_error! {
@@ -89,6 +111,20 @@ decl_error! {
     NoPermissionToCreateSubstorefronts,
     /// Storefront is at root level, no parent_id specified
     StorefrontIsAtRoot,
+    /// Account is blocked in a given storefront.
+    AccountIsBlocked,
+    /// Content is blocked in a given storefront.
+    ContentIsBlocked,
+    /// Storefront is blocked by moderators.
+    StorefrontIsBlocked,
+    /// User has no permission to withdraw funds of this storefront.
+    NoPermissionToManageTreasury,
+    /// Storefront is archived and cannot be changed anymore.
+    StorefrontIsArchived,
+    /// User has no permission to delete their own substorefronts in a parent storefront.
+    NoPermissionToDeleteOwnSubstorefronts,
+    /// User is not a substorefront owner and has no permission to delete substorefronts in a parent storefront.
+    NoPermissionToDeleteAnySubstorefront,
   }
 }
 
@@ -111,8 +147,21 @@ decl_storage! {
         pub StorefrontIdByHandle get(fn storefront_id_by_handle):
             map hasher(blake2_128_concat) Vec<u8> => Option<StorefrontId>;
 
+        /// Archived storefronts are kept here, so that their orders can still be found by seller.
         pub StorefrontIdsByOwner get(fn storefront_ids_by_owner):
             map hasher(twox_64_concat) T::AccountId => Vec<StorefrontId>;
+
+        pub StorefrontIdsByParentId get(fn storefront_ids_by_parent_id):
+            map hasher(twox_64_concat) StorefrontId => Vec<StorefrontId>;
+
+        /// A storage deposit of the storefront creator. It is released when the storefront is archived,
+        /// which requires that the storefront has no open orders.
+        pub StorageDepositByStorefrontId get(fn storage_deposit_by_storefront_id):
+            map hasher(twox_64_concat) StorefrontId => Option<StorageDepositOf<T>>;
+
+        /// Whether storefronts are stored in the current layout. New chains start with it.
+        pub IsStorefrontLayoutMigrated get(fn is_storefront_layout_migrated)
+            build(|_config: &GenesisConfig<T>| true): bool;
     }
     add_extra_genesis {
       config(endowed_account): T::AccountId;
@@ -122,10 +171,13 @@ decl_storage! {
 decl_event!(
     pub enum Event<T> where
         <T as system::Trait>::AccountId,
+        Balance = BalanceOf<T>,
     {
         StorefrontCreated(AccountId, StorefrontId),
         StorefrontUpdated(AccountId, StorefrontId),
         StorefrontDeleted(AccountId, StorefrontId),
+        /// Funds were withdrawn from the storefront account by the first account to the second one.
+        StorefrontFundsWithdrawn(AccountId, StorefrontId, AccountId, Balance),
     }
 );
 
@@ -133,15 +185,20 @@ decl_event!(
 decl_module! {
   pub struct Module<T: Trait> for enum Call where origin: T::Origin {
 
-    const StorefrontCreationFee: BalanceOf<T> = T::StorefrontCreationFee::get();
-
     // Initializing errors
     type Error = Error<T>;
 
     // Initializing events
     fn deposit_event() = default;
 
-    #[weight = 500_000 + T::DbWeight::get().reads_writes(4, 4)]
+    fn on_runtime_upgrade() -> Weight {
+      if Self::is_storefront_layout_migrated() {
+        return 0;
+      }
+      migration::migrate_storefronts::<T>()
+    }
+
+    #[weight = 500_000 + T::DbWeight::get().reads_writes(4, 5)]
     pub fn create_storefront(
       origin,
       parent_id_opt: Option<StorefrontId>,
@@ -160,6 +217,7 @@ decl_module! {
       // TODO: add tests for this case
       if let Some(parent_id) = parent_id_opt {
         let parent_storefront = Self::require_storefront(parent_id)?;
+        parent_storefront.ensure_not_archived()?;
 
         Self::ensure_account_has_storefront_permission(
           owner.clone(),
@@ -169,20 +227,20 @@ decl_module! {
         )?;
       }
 
-      <T as pallet_utils::Trait>::Currency::transfer(
-        &owner,
-        &Utils::<T>::treasury_account(),
-        T::StorefrontCreationFee::get(),
-        ExistenceRequirement::KeepAlive
-      )?;
-
       let storefront_id = Self::next_storefront_id();
       let new_storefront = &mut Storefront::new(storefront_id, parent_id_opt, owner.clone(), content, handle_opt);
 
+      Utils::<T>::ensure_can_reserve_storage_deposit(&owner, new_storefront)?;
+
       T::BeforeStorefrontCreated::before_storefront_created(owner.clone(), new_storefront)?;
 
+      let deposit = Utils::<T>::reserve_storage_deposit(&owner, new_storefront)?;
+      <StorageDepositByStorefrontId<T>>::insert(storefront_id, deposit);
       <StorefrontById<T>>::insert(storefront_id, new_storefront);
       <StorefrontIdsByOwner<T>>::mutate(owner.clone(), |ids| ids.push(storefront_id));
+      if let Some(parent_id) = parent_id_opt {
+        StorefrontIdsByParentId::mutate(parent_id, |ids| ids.push(storefront_id));
+      }
       NextStorefrontId::mutate(|n| { *n += 1; });
 
       if !handle_in_lowercase.is_empty() {
@@ -208,6 +266,7 @@ decl_module! {
       ensure!(has_updates, Error::<T>::NoUpdatesForStorefront);
 
       let mut storefront = Self::require_storefront(storefront_id)?;
+      storefront.ensure_not_archived()?;
 
       Self::ensure_account_has_storefront_permission(
         owner.clone(),
@@ -225,6 +284,7 @@ decl_module! {
 
           if let Some(parent_id) = parent_id_opt {
             let parent_storefront = Self::require_storefront(parent_id)?;
+            parent_storefront.ensure_not_archived()?;
 
             Self::ensure_account_has_storefront_permission(
               owner.clone(),
@@ -232,6 +292,11 @@ decl_module! {
               StorefrontPermission::CreateSubstorefronts,
               Error::<T>::NoPermissionToCreateSubstorefronts.into()
             )?;
+
+            StorefrontIdsByParentId::mutate(parent_id, |ids| ids.push(storefront_id));
+          }
+          if let Some(old_parent_id) = storefront.parent_id {
+            StorefrontIdsByParentId::mutate(old_parent_id, |ids| vec_remove_on(ids, storefront_id));
           }
 
           old_data.parent_id = Some(storefront.parent_id);
@@ -315,6 +380,84 @@ decl_module! {
       }
       Ok(())
     }
+
+    /// Archive a storefront: release its handle, hide its products and detach its substorefronts.
+    /// The storefront itself is kept in storage, because its products and orders refer to it.
+    #[weight = 500_000 + T::DbWeight::get().reads_writes(7, 7)]
+    pub fn delete_storefront(origin, storefront_id: StorefrontId) -> DispatchResult {
+      let who = ensure_signed(origin)?;
+
+      let mut storefront = Self::require_storefront(storefront_id)?;
+      storefront.ensure_not_archived()?;
+
+      Self::ensure_account_can_delete_storefront(who.clone(), &storefront)?;
+
+      // Load substorefronts before any hook writes to storage, so that nothing can fail after it.
+      let mut substorefronts = Vec::new();
+      for substorefront_id in Self::storefront_ids_by_parent_id(storefront_id) {
+        substorefronts.push(Self::require_storefront(substorefront_id)?);
+      }
+
+      T::BeforeStorefrontDeleted::before_storefront_deleted(who.clone(), &mut storefront)?;
+
+      StorefrontIdsByParentId::remove(storefront_id);
+      for mut substorefront in substorefronts {
+        substorefront.parent_id = None;
+        <StorefrontById<T>>::insert(substorefront.id, substorefront);
+      }
+      if let Some(parent_id) = storefront.parent_id {
+        StorefrontIdsByParentId::mutate(parent_id, |ids| vec_remove_on(ids, storefront_id));
+      }
+
+      if let Some(handle) = storefront.handle.take() {
+        StorefrontIdByHandle::remove(handle.to_ascii_lowercase());
+      }
+
+      if let Some(deposit) = <StorageDepositByStorefrontId<T>>::take(storefront_id) {
+        Utils::<T>::release_storage_deposit(deposit);
+      }
+
+      storefront.hidden = true;
+      storefront.archived = true;
+      storefront.updated = Some(WhoAndWhen::<T>::new(who.clone()));
+      <StorefrontById<T>>::insert(storefront_id, storefront);
+
+      Self::deposit_event(RawEvent::StorefrontDeleted(who, storefront_id));
+      Ok(())
+    }
+
+    /// Transfer `amount` from the storefront account to `dest`.
+    /// Allowed to the storefront owner or to users with a `ManageTreasury` permission.
+    #[weight = 100_000 + T::DbWeight::get().reads_writes(4, 2)]
+    pub fn withdraw_storefront_funds(
+      origin,
+      storefront_id: StorefrontId,
+      dest: T::AccountId,
+      amount: BalanceOf<T>
+    ) -> DispatchResult {
+      let who = ensure_signed(origin)?;
+
+      let storefront = Self::require_storefront(storefront_id)?;
+
+      if !storefront.is_owner(&who) {
+        Self::ensure_account_has_storefront_permission(
+          who.clone(),
+          &storefront,
+          StorefrontPermission::ManageTreasury,
+          Error::<T>::NoPermissionToManageTreasury.into()
+        )?;
+      }
+
+      <T as pallet_utils::Trait>::Currency::transfer(
+        &Self::storefront_account(storefront_id),
+        &dest,
+        amount,
+        ExistenceRequirement::AllowDeath
+      )?;
+
+      Self::deposit_event(RawEvent::StorefrontFundsWithdrawn(who, storefront_id, dest, amount));
+      Ok(())
+    }
   }
 }
 
@@ -336,11 +479,14 @@ impl<T: Trait> Storefront<T> {
             content,
             hidden: false,
             private: false,
+            archived: false,
             products_count: 0,
             hidden_products_count: 0,
             private_products_count: 0,
             followers_count: 0,
             score: 0,
+            reviews_count: 0,
+            ratings_sum: 0,
             permissions: None,
         }
     }
@@ -349,6 +495,11 @@ impl<T: Trait> Storefront<T> {
         self.owner == *account
     }
 
+    pub fn add_rating(&mut self, rating: u8) {
+        self.reviews_count = self.reviews_count.saturating_add(1);
+        self.ratings_sum = self.ratings_sum.saturating_add(rating as u64);
+    }
+
     pub fn is_follower(&self, account: &T::AccountId) -> bool {
         T::StorefrontFollows::is_storefront_follower(account.clone(), self.id)
     }
@@ -358,6 +509,11 @@ impl<T: Trait> Storefront<T> {
         Ok(())
     }
 
+    pub fn ensure_not_archived(&self) -> DispatchResult {
+        ensure!(!self.archived, Error::<T>::StorefrontIsArchived);
+        Ok(())
+    }
+
     pub fn inc_products(&mut self) {
         self.products_count = self.products_count.saturating_add(1);
     }
@@ -431,6 +587,32 @@ impl<T: Trait> Module<T> {
         Ok(Self::storefront_by_id(storefront_id).ok_or(Error::<T>::StorefrontNotFound)?)
     }
 
+    /// Get a page of ids of storefronts owned by the account.
+    pub fn get_storefront_ids_by_owner(owner: T::AccountId, offset: u64, limit: u64) -> Vec<StorefrontId> {
+        paginate(Self::storefront_ids_by_owner(owner).into_iter(), offset, limit)
+    }
+
+    /// Whether the account has a permission in the storefront either by default or through its roles.
+    pub fn has_account_storefront_permission(
+        account: T::AccountId,
+        storefront_id: StorefrontId,
+        permission: StorefrontPermission,
+    ) -> bool {
+        Self::require_storefront(storefront_id)
+            .and_then(|storefront| Self::ensure_account_has_storefront_permission(
+                account,
+                &storefront,
+                permission,
+                DispatchError::Other("NoStorefrontPermission"),
+            ))
+            .is_ok()
+    }
+
+    /// An account derived from the storefront id that holds funds of this storefront.
+    pub fn storefront_account(storefront_id: StorefrontId) -> T::AccountId {
+        STOREFRONT_MODULE_ID.into_sub_account(storefront_id)
+    }
+
     pub fn lowercase_and_validate_storefront_handle(handle: Vec<u8>) -> Result<Vec<u8>, DispatchError> {
         let handle_in_lowercase = Utils::<T>::lowercase_and_validate_a_handle(handle)?;
 
@@ -464,8 +646,55 @@ impl<T: Trait> Module<T> {
         )
     }
 
+    /// Check that `account` is not blocked by moderators of the `scope` storefront
+    /// or return `AccountIsBlocked` error.
+    pub fn ensure_account_is_not_blocked(account: T::AccountId, scope: StorefrontId) -> DispatchResult {
+        ensure!(!T::IsAccountBlocked::is_account_blocked(account, scope), Error::<T>::AccountIsBlocked);
+        Ok(())
+    }
+
+    /// Check that `content` is not blocked by moderators of the `scope` storefront
+    /// or return `ContentIsBlocked` error.
+    pub fn ensure_content_is_not_blocked(content: Content, scope: StorefrontId) -> DispatchResult {
+        ensure!(!T::IsContentBlocked::is_content_blocked(content, scope), Error::<T>::ContentIsBlocked);
+        Ok(())
+    }
+
+    /// Check that a storefront is not blocked within its parent storefront,
+    /// or within itself if it's a root storefront, or return `StorefrontIsBlocked` error.
+    pub fn ensure_storefront_is_not_blocked(storefront: &Storefront<T>) -> DispatchResult {
+        let scope = storefront.parent_id.unwrap_or(storefront.id);
+        ensure!(!T::IsStorefrontBlocked::is_storefront_blocked(storefront.id, scope), Error::<T>::StorefrontIsBlocked);
+        Ok(())
+    }
+
+    /// Check that an account can delete a storefront: a root storefront can be deleted only by its owner,
+    /// while a substorefront requires a delete permission in its parent storefront.
+    pub fn ensure_account_can_delete_storefront(account: T::AccountId, storefront: &Storefront<T>) -> DispatchResult {
+        match storefront.parent_id {
+            Some(parent_id) => {
+                let (permission, error) = if storefront.is_owner(&account) {
+                    (StorefrontPermission::DeleteOwnSubstorefronts, Error::<T>::NoPermissionToDeleteOwnSubstorefronts)
+                } else {
+                    (StorefrontPermission::DeleteAnySubstorefront, Error::<T>::NoPermissionToDeleteAnySubstorefront)
+                };
+
+                Self::ensure_account_has_storefront_permission(
+                    account,
+                    &Self::require_storefront(parent_id)?,
+                    permission,
+                    error.into()
+                )
+            },
+            None => storefront.ensure_storefront_owner(account),
+        }
+    }
+
     pub fn try_move_storefront_to_root(storefront_id: StorefrontId) -> DispatchResult {
         let mut storefront = Self::require_storefront(storefront_id)?;
+        if let Some(parent_id) = storefront.parent_id {
+            StorefrontIdsByParentId::mutate(parent_id, |ids| vec_remove_on(ids, storefront_id));
+        }
         storefront.parent_id = None;
 
         StorefrontById::<T>::insert(storefront_id, storefront);
@@ -482,6 +711,7 @@ impl<T: Trait> StorefrontForRolesProvider for Module<T> {
         Ok(StorefrontForRoles {
             owner: storefront.owner,
             permissions: storefront.permissions,
+            archived: storefront.archived,
         })
     }
 }
@@ -500,3 +730,17 @@ impl<T: Trait> BeforeStorefrontCreated<T> for () {
 pub trait AfterStorefrontUpdated<T: Trait> {
     fn after_storefront_updated(sender: T::AccountId, storefront: &Storefront<T>, old_data: StorefrontUpdate);
 }
+
+/// Handler that will be called right before the storefront is archived.
+/// If any handler returns an error, the storefront will not be archived.
+pub trait BeforeStorefrontDeleted<T: Trait> {
+    fn before_storefront_deleted(account: T::AccountId, storefront: &mut Storefront<T>) -> DispatchResult;
+}
+
+#[impl_trait_for_tuples::impl_for_tuples(10)]
+impl<T: Trait> BeforeStorefrontDeleted<T> for Tuple {
+    fn before_storefront_deleted(account: T::AccountId, storefront: &mut Storefront<T>) -> DispatchResult {
+        for_tuples!( #( Tuple::before_storefront_deleted(account.clone(), storefront)?; )* );
+        Ok(())
+    }
+}
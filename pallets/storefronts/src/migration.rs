@@ -0,0 +1,76 @@
+use frame_support::{storage::IterableStorageMap, weights::Weight};
+use sp_std::cell::Cell;
+
+use super::*;
+
+/// A storefront as it was stored before archival and review ratings were added.
+#[derive(Decode)]
+struct OldStorefront<T: Trait> {
+    id: StorefrontId,
+    created: WhoAndWhen<T>,
+    updated: Option<WhoAndWhen<T>>,
+
+    owner: T::AccountId,
+
+    parent_id: Option<StorefrontId>,
+    handle: Option<Vec<u8>>,
+    content: Content,
+    hidden: bool,
+    private: bool,
+
+    products_count: u32,
+    hidden_products_count: u32,
+    private_products_count: u32,
+    followers_count: u32,
+
+    score: i32,
+
+    permissions: Option<StorefrontPermissions>,
+}
+
+impl<T: Trait> From<OldStorefront<T>> for Storefront<T> {
+    fn from(old: OldStorefront<T>) -> Self {
+        Storefront {
+            id: old.id,
+            created: old.created,
+            updated: old.updated,
+            owner: old.owner,
+            parent_id: old.parent_id,
+            handle: old.handle,
+            content: old.content,
+            hidden: old.hidden,
+            private: old.private,
+            archived: false,
+            products_count: old.products_count,
+            hidden_products_count: old.hidden_products_count,
+            private_products_count: old.private_products_count,
+            followers_count: old.followers_count,
+            score: old.score,
+            reviews_count: 0,
+            ratings_sum: 0,
+            permissions: old.permissions,
+        }
+    }
+}
+
+/// Convert all storefronts to the current layout and index substorefronts by their parents.
+pub(crate) fn migrate_storefronts<T: Trait>() -> Weight {
+    let storefronts_count = Cell::new(0u64);
+    let substorefronts_count = Cell::new(0u64);
+
+    StorefrontById::<T>::translate(|storefront_id, old_storefront: OldStorefront<T>| {
+        storefronts_count.set(storefronts_count.get() + 1);
+
+        if let Some(parent_id) = old_storefront.parent_id {
+            StorefrontIdsByParentId::mutate(parent_id, |ids| ids.push(storefront_id));
+            substorefronts_count.set(substorefronts_count.get() + 1);
+        }
+
+        Some(old_storefront.into())
+    });
+
+    IsStorefrontLayoutMigrated::put(true);
+
+    let changes = storefronts_count.get() + substorefronts_count.get();
+    T::DbWeight::get().reads_writes(changes, changes + 1)
+}
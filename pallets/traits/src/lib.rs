@@ -15,6 +15,7 @@ pub mod moderation;
 pub struct StorefrontForRoles<AccountId> {
   pub owner: AccountId,
   pub permissions: Option<StorefrontPermissions>,
+  pub archived: bool,
 }
 
 pub trait StorefrontForRolesProvider {
@@ -1,25 +1,42 @@
 use crate::{StorefrontId};
+use pallet_utils::Content;
 
-pub trait IsAccountBlocked {
-    type AccountId;
+pub trait IsAccountBlocked<AccountId> {
+    fn is_account_blocked(account: AccountId, scope: StorefrontId) -> bool;
+}
 
-    fn is_account_blocked(account: Self::AccountId, scope: StorefrontId) -> bool;
+impl<AccountId> IsAccountBlocked<AccountId> for () {
+    fn is_account_blocked(_account: AccountId, _scope: StorefrontId) -> bool {
+        false
+    }
 }
 
 pub trait IsStorefrontBlocked {
-    type StorefrontId;
+    fn is_storefront_blocked(storefront_id: StorefrontId, scope: StorefrontId) -> bool;
+}
 
-    fn is_storefront_blocked(storefront_id: Self::StorefrontId, scope: StorefrontId) -> bool;
+impl IsStorefrontBlocked for () {
+    fn is_storefront_blocked(_storefront_id: StorefrontId, _scope: StorefrontId) -> bool {
+        false
+    }
 }
 
-pub trait IsProductBlocked {
-    type ProductId;
+pub trait IsProductBlocked<ProductId> {
+    fn is_product_blocked(product_id: ProductId, scope: StorefrontId) -> bool;
+}
 
-    fn is_product_blocked(product_id: Self::ProductId, scope: StorefrontId) -> bool;
+impl<ProductId> IsProductBlocked<ProductId> for () {
+    fn is_product_blocked(_product_id: ProductId, _scope: StorefrontId) -> bool {
+        false
+    }
 }
 
 pub trait IsContentBlocked {
-    type Content;
+    fn is_content_blocked(content: Content, scope: StorefrontId) -> bool;
+}
 
-    fn is_content_blocked(content: Self::Content, scope: StorefrontId) -> bool;
+impl IsContentBlocked for () {
+    fn is_content_blocked(_content: Content, _scope: StorefrontId) -> bool {
+        false
+    }
 }
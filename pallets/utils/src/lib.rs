@@ -9,7 +9,7 @@ use frame_support::{
         Imbalance, OnUnbalanced,
     },
 };
-use sp_runtime::RuntimeDebug;
+use sp_runtime::{RuntimeDebug, traits::Saturating};
 use sp_std::{
     collections::btree_set::BTreeSet,
     prelude::*,
@@ -61,7 +61,16 @@ impl Content {
     }
 }
 
-type BalanceOf<T> = <<T as Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+/// A refundable deposit reserved from an account for an item kept in the storage.
+#[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug)]
+pub struct StorageDeposit<AccountId, Balance> {
+    pub depositor: AccountId,
+    pub amount: Balance,
+}
+
+pub type StorageDepositOf<T> = StorageDeposit<<T as system::Trait>::AccountId, BalanceOf<T>>;
+
+pub type BalanceOf<T> = <<T as Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
 
 type NegativeImbalanceOf<T> = <<T as Trait>::Currency as Currency<<T as frame_system::Trait>::AccountId>>::NegativeImbalance;
 
@@ -79,6 +88,12 @@ pub trait Trait: system::Trait + pallet_timestamp::Trait
 
     /// Maximal length of storefront/profile handle
     type MaxHandleLen: Get<u32>;
+
+    /// The base amount reserved for every item kept in the storage.
+    type StorageDepositBase: Get<BalanceOf<Self>>;
+
+    /// The amount reserved per byte of an encoded item kept in the storage.
+    type StorageDepositPerByte: Get<BalanceOf<Self>>;
 }
 
 decl_storage! {
@@ -106,6 +121,12 @@ decl_module! {
         /// Maximal length of storefront/profile handle
         const MaxHandleLen: u32 = T::MaxHandleLen::get();
 
+        /// The base amount reserved for every item kept in the storage.
+        const StorageDepositBase: BalanceOf<T> = T::StorageDepositBase::get();
+
+        /// The amount reserved per byte of an encoded item kept in the storage.
+        const StorageDepositPerByte: BalanceOf<T> = T::StorageDepositPerByte::get();
+
         // Initializing errors
         type Error = Error<T>;
 
@@ -130,6 +151,8 @@ decl_error! {
         HandleContainsInvalidChars,
         /// Content type is `None`
         ContentIsEmpty,
+        /// Account has not enough free balance to reserve a storage deposit.
+        InsufficientBalanceForStorageDeposit,
     }
 }
 
@@ -155,6 +178,17 @@ pub fn log_2(x: u32) -> Option<u32> {
     } else { None }
 }
 
+/// The maximum number of items returned by a single page of a runtime API query.
+pub const MAX_PAGE_SIZE: u64 = 1000;
+
+/// Skip `offset` items and take up to `limit` items, but not more than `MAX_PAGE_SIZE`.
+pub fn paginate<I, It: Iterator<Item=I>>(items: It, offset: u64, limit: u64) -> Vec<I> {
+    items
+        .skip(offset as usize)
+        .take(limit.min(MAX_PAGE_SIZE) as usize)
+        .collect()
+}
+
 pub fn vec_remove_on<F: PartialEq>(vector: &mut Vec<F>, element: F) {
     if let Some(index) = vector.iter().position(|x| *x == element) {
         // TODO fix: swap_remove doesn't remove tha last element.
@@ -224,6 +258,44 @@ impl<T: Trait> Module<T> {
     pub fn u32_to_balance(input: u32) -> BalanceOf<T> {
         input.into()
     }
+
+    /// A storage deposit for an item of `encoded_len` bytes.
+    pub fn storage_deposit(encoded_len: usize) -> BalanceOf<T> {
+        let per_byte = T::StorageDepositPerByte::get()
+            .saturating_mul(Self::u32_to_balance(encoded_len as u32));
+
+        T::StorageDepositBase::get().saturating_add(per_byte)
+    }
+
+    /// Check that the `depositor` can afford a storage deposit for `item` without reserving it yet.
+    /// Used when the deposit can only be reserved after hooks that write to storage.
+    pub fn ensure_can_reserve_storage_deposit<I: Encode>(depositor: &T::AccountId, item: &I) -> DispatchResult {
+        let amount = Self::storage_deposit(item.encode().len());
+
+        ensure!(
+            T::Currency::can_reserve(depositor, amount),
+            Error::<T>::InsufficientBalanceForStorageDeposit
+        );
+        Ok(())
+    }
+
+    /// Reserve a storage deposit for `item` from the `depositor`'s free balance.
+    pub fn reserve_storage_deposit<I: Encode>(
+        depositor: &T::AccountId,
+        item: &I
+    ) -> Result<StorageDepositOf<T>, DispatchError> {
+        let amount = Self::storage_deposit(item.encode().len());
+
+        T::Currency::reserve(depositor, amount)
+            .map_err(|_| Error::<T>::InsufficientBalanceForStorageDeposit)?;
+
+        Ok(StorageDeposit { depositor: depositor.clone(), amount })
+    }
+
+    /// Return a previously reserved storage deposit to its depositor.
+    pub fn release_storage_deposit(deposit: StorageDepositOf<T>) {
+        T::Currency::unreserve(&deposit.depositor, deposit.amount);
+    }
 }
 
 impl<T: Trait> OnUnbalanced<NegativeImbalanceOf<T>> for Module<T> {
@@ -82,6 +82,8 @@ impl Trait for Test {
     type Currency = Balances;
     type MinHandleLen = MinHandleLen;
     type MaxHandleLen = MaxHandleLen;
+    type StorageDepositBase = ();
+    type StorageDepositPerByte = ();
 }
 
 type System = system::Module<Test>;
@@ -13,9 +13,11 @@ use sp_std::{
 	iter::FromIterator,
 };
 use sp_core::{crypto::KeyTypeId, OpaqueMetadata, Encode};
+use codec::Decode;
 use sp_runtime::{
 	ApplyExtrinsicResult, generic, create_runtime_str, impl_opaque_keys, MultiSignature,
 	transaction_validity::{TransactionValidity, TransactionSource}, Perquintill, FixedPointNumber,
+	RuntimeDebug,
 };
 use sp_runtime::traits::{
 	BlakeTwo256, Block as BlockT, IdentityLookup, Verify, IdentifyAccount, NumberFor, Saturating,
@@ -37,9 +39,9 @@ pub use balances::Call as BalancesCall;
 pub use sp_runtime::{Permill, Perbill, transaction_validity};
 pub use frame_support::{
 	construct_runtime, parameter_types, StorageValue, debug,
-	traits::{KeyOwnerProofSystem, Randomness, Currency, Imbalance, OnUnbalanced, Filter},
+	traits::{KeyOwnerProofSystem, Randomness, Currency, Imbalance, OnUnbalanced, Filter, InstanceFilter},
 	weights::{
-		Weight, IdentityFee,
+		Weight, IdentityFee, DispatchInfo,
 		constants::{BlockExecutionWeight, ExtrinsicBaseWeight, RocksDbWeight, WEIGHT_PER_SECOND},
 	},
 };
@@ -51,6 +53,10 @@ use pallet_permissions::{
 	StorefrontPermissions,
 	StorefrontPermissionSet
 };
+use pallet_orders::{OrderId, OrderState};
+use pallet_categories::CategoryId;
+use pallet_products::ProductId;
+use pallet_utils::StorefrontId;
 
 pub use pallet_ocw;
 
@@ -112,7 +118,7 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	spec_name: create_runtime_str!("darkdot"),
 	impl_name: create_runtime_str!("d4rk-darkdot"),
 	authoring_version: 0,
-	spec_version: 7,
+	spec_version: 8,
 	impl_version: 0,
 	apis: RUNTIME_API_VERSIONS,
 	transaction_version: 1,
@@ -297,6 +303,8 @@ impl pallet_utility::Trait for Runtime {
 parameter_types! {
   pub const MinHandleLen: u32 = 5;
   pub const MaxHandleLen: u32 = 50;
+  pub const StorageDepositBase: Balance = deposit(1, 0);
+  pub const StorageDepositPerByte: Balance = deposit(0, 1);
 }
 
 impl pallet_utils::Trait for Runtime {
@@ -304,6 +312,8 @@ impl pallet_utils::Trait for Runtime {
 	type Currency = Balances;
 	type MinHandleLen = MinHandleLen;
 	type MaxHandleLen = MaxHandleLen;
+	type StorageDepositBase = StorageDepositBase;
+	type StorageDepositPerByte = StorageDepositPerByte;
 }
 
 
@@ -361,6 +371,13 @@ parameter_types! {
       SP::UpdateEntityStatus,
 
       SP::UpdateStorefrontSettings,
+
+      SP::AcceptOrders,
+      SP::ShipOrders,
+      SP::RefundOrders,
+      SP::ViewOrderMessages,
+      SP::OpenDisputes,
+      SP::ResolveDisputes,
     ].into_iter())),
   };
 }
@@ -369,6 +386,28 @@ impl pallet_permissions::Trait for Runtime {
 	type DefaultStorefrontPermissions = DefaultStorefrontPermissions;
 }
 
+parameter_types! {
+	pub const DefaultAutoblockThreshold: u16 = 20;
+	pub const ReportedOrdersAutoblockThreshold: u32 = 20;
+}
+
+impl pallet_moderation::Trait for Runtime {
+	type Event = Event;
+	type DefaultAutoblockThreshold = DefaultAutoblockThreshold;
+	type ReportedOrdersAutoblockThreshold = ReportedOrdersAutoblockThreshold;
+}
+
+parameter_types! {
+  pub const MaxCategoriesPerProduct: u16 = 5;
+  pub const MaxCategoriesPerStorefront: u16 = 20;
+}
+
+impl pallet_categories::Trait for Runtime {
+	type Event = Event;
+	type MaxCategoriesPerProduct = MaxCategoriesPerProduct;
+	type MaxCategoriesPerStorefront = MaxCategoriesPerStorefront;
+}
+
 parameter_types! {
   pub const MaxCommentDepth: u32 = 10;
 }
@@ -378,6 +417,8 @@ impl pallet_products::Trait for Runtime {
 	type MaxCommentDepth = MaxCommentDepth;
 	type ProductScores = Scores;
 	type AfterProductUpdated = ProductHistory;
+	type BeforeProductDeleted = (Orders, Scores, Reactions, Reviews);
+	type IsProductBlocked = Moderation;
 }
 
 parameter_types! {}
@@ -433,6 +474,10 @@ parameter_types! {
   pub const ShareCommentActionWeight: i16 = 5;
   pub const UpvoteCommentActionWeight: i16 = 4;
   pub const DownvoteCommentActionWeight: i16 = -2;
+
+  pub const CompleteOrderActionWeight: i16 = 10;
+  pub const RefuseOrderActionWeight: i16 = -2;
+  pub const LoseDisputeActionWeight: i16 = -20;
 }
 
 impl pallet_scores::Trait for Runtime {
@@ -449,6 +494,10 @@ impl pallet_scores::Trait for Runtime {
 	type ShareCommentActionWeight = ShareCommentActionWeight;
 	type UpvoteCommentActionWeight = UpvoteCommentActionWeight;
 	type DownvoteCommentActionWeight = DownvoteCommentActionWeight;
+
+	type CompleteOrderActionWeight = CompleteOrderActionWeight;
+	type RefuseOrderActionWeight = RefuseOrderActionWeight;
+	type LoseDisputeActionWeight = LoseDisputeActionWeight;
 }
 
 parameter_types! {}
@@ -466,7 +515,40 @@ impl pallet_storefront_ownership::Trait for Runtime {
 }
 
 parameter_types! {
-	pub StorefrontCreationFee: Balance = 500 * DARKS;
+	pub const MinStorefrontOwners: u16 = 1;
+	pub const MaxStorefrontOwners: u16 = 1000;
+	pub const MaxChangeNotesLength: u16 = 1024;
+	pub const BlocksToLive: BlockNumber = 7 * DAYS;
+	pub const DeleteExpiredChangesPeriod: BlockNumber = HOURS;
+}
+
+/// A storefront account acts as a seller, so it cannot place orders with the owners' shared funds.
+pub struct StorefrontOwnersCallFilter;
+impl Filter<Call> for StorefrontOwnersCallFilter {
+	fn filter(c: &Call) -> bool {
+		match *c {
+			Call::Storefronts(..) => true,
+			Call::Products(..) => true,
+			Call::Categories(..) => true,
+			Call::Roles(..) => true,
+			Call::Orders(pallet_orders::Call::create_order(..)) => false,
+			Call::Orders(..) => true,
+			Call::Reviews(..) => true,
+			Call::Moderation(..) => true,
+			_ => false,
+		}
+	}
+}
+
+impl pallet_storefront_multi_ownership::Trait for Runtime {
+	type Event = Event;
+	type Call = Call;
+	type CallFilter = StorefrontOwnersCallFilter;
+	type MinStorefrontOwners = MinStorefrontOwners;
+	type MaxStorefrontOwners = MaxStorefrontOwners;
+	type MaxChangeNotesLength = MaxChangeNotesLength;
+	type BlocksToLive = BlocksToLive;
+	type DeleteExpiredChangesPeriod = DeleteExpiredChangesPeriod;
 }
 
 impl pallet_storefronts::Trait for Runtime {
@@ -475,7 +557,10 @@ impl pallet_storefronts::Trait for Runtime {
 	type StorefrontFollows = StorefrontFollows;
 	type BeforeStorefrontCreated = StorefrontFollows;
 	type AfterStorefrontUpdated = StorefrontHistory;
-	type StorefrontCreationFee = StorefrontCreationFee;
+	type BeforeStorefrontDeleted = (Orders, Products, StorefrontFollows, Roles);
+	type IsAccountBlocked = Moderation;
+	type IsContentBlocked = Moderation;
+	type IsStorefrontBlocked = Moderation;
 }
 
 parameter_types! {}
@@ -498,17 +583,36 @@ impl Filter<Call> for BaseFilter {
 
 
 /* Orders */
-parameter_types! {}
+parameter_types! {
+	pub const MaxOrderItems: u16 = 50;
+	pub const MaxOrderMessageLength: u32 = 2 * 1024;
+	pub const MaxDisputeEvidence: u16 = 20;
+	pub const AcceptOrderTimeout: BlockNumber = 3 * DAYS;
+	pub const ConfirmOrderTimeout: BlockNumber = 14 * DAYS;
+	pub const MaxExpiredOrdersPerBlock: u32 = 100;
+	pub const MarketplaceFee: Perbill = Perbill::from_percent(2);
+}
 
 impl pallet_orders::Trait for Runtime {
 	type Event = Event;
-	type AfterOrderUpdated = OrderHistory;
+	type AfterOrderUpdated = (OrderHistory, Scores);
+	type MaxOrderItems = MaxOrderItems;
+	type MaxOrderMessageLength = MaxOrderMessageLength;
+	type MaxDisputeEvidence = MaxDisputeEvidence;
+	type AcceptOrderTimeout = AcceptOrderTimeout;
+	type ConfirmOrderTimeout = ConfirmOrderTimeout;
+	type MaxExpiredOrdersPerBlock = MaxExpiredOrdersPerBlock;
+	type MarketplaceFee = MarketplaceFee;
 }
 
 parameter_types! {}
 
 impl pallet_order_history::Trait for Runtime {}
 
+impl pallet_reviews::Trait for Runtime {
+	type Event = Event;
+}
+
 
 /* Offchain fetcher */
 /// Payload data to be signed when making signed transaction from off-chain workers,
@@ -599,8 +703,9 @@ impl pallet_faucets::Trait for Runtime {
 }
 
 
-/*parameter_types! {
+parameter_types! {
 	pub const MaxSessionKeysPerAccount: u16 = 10;
+	pub const MaxAllowedCallsPerKey: u16 = 20;
 }
 
 pub struct SessionKeysProxyFilter;
@@ -612,17 +717,59 @@ impl Filter<Call> for SessionKeysProxyFilter {
 			Call::ProfileFollows(..) => true,
 			Call::Products(..) => true,
 			Call::Reactions(..) => true,
+			Call::Orders(pallet_orders::Call::send_order_message(..)) => true,
+			Call::Orders(pallet_orders::Call::add_dispute_evidence(..)) => true,
+			Call::Reviews(..) => true,
 			_ => false,
 		}
 	}
 }
 
+/// Kinds of calls a session key can be allowed to proxy.
+/// Calls are matched by their variants, so the filters do not depend on pallet indices.
+#[derive(Copy, Clone, Eq, PartialEq, Encode, Decode, RuntimeDebug)]
+pub enum SessionKeyCallFilter {
+	/// Follow storefronts and accounts, react to products and comments.
+	Social,
+	/// Create and update products and comments.
+	Products,
+	/// Send order messages, add dispute evidence and review products.
+	/// Calls that place or pay for orders are not allowed, as they are not counted against the key's limit.
+	Orders,
+}
+
+impl InstanceFilter<Call> for SessionKeyCallFilter {
+	fn filter(&self, c: &Call) -> bool {
+		match self {
+			SessionKeyCallFilter::Social => matches!(c,
+				Call::StorefrontFollows(..) | Call::ProfileFollows(..) | Call::Reactions(..)
+			),
+			SessionKeyCallFilter::Products => matches!(c, Call::Products(..)),
+			SessionKeyCallFilter::Orders => matches!(c,
+				Call::Orders(pallet_orders::Call::send_order_message(..))
+					| Call::Orders(pallet_orders::Call::add_dispute_evidence(..))
+					| Call::Reviews(..)
+			),
+		}
+	}
+}
+
+pub struct SessionKeysProxyFee;
+impl session_keys::ComputeProxyFee<Balance> for SessionKeysProxyFee {
+	fn compute_fee(len: u32, info: &DispatchInfo) -> Balance {
+		TransactionPayment::compute_fee(len, info, 0)
+	}
+}
+
 impl session_keys::Trait for Runtime {
 	type Event = Event;
 	type Call = Call;
 	type MaxSessionKeysPerAccount = MaxSessionKeysPerAccount;
+	type MaxAllowedCallsPerKey = MaxAllowedCallsPerKey;
+	type CallFilter = SessionKeyCallFilter;
 	type BaseFilter = SessionKeysProxyFilter;
-}*/
+	type ProxyFee = SessionKeysProxyFee;
+}
 
 construct_runtime!(
 	pub enum Runtime where
@@ -642,9 +789,11 @@ construct_runtime!(
 		Utility: pallet_utility::{Module, Call, Event},
 
 		// Darkdot custom pallets:
+		Categories: pallet_categories::{Module, Call, Storage, Event<T>},
+		Moderation: pallet_moderation::{Module, Call, Storage, Event<T>},
 		Permissions: pallet_permissions::{Module, Call},
-		Products: pallet_products::{Module, Call, Storage, Event<T>},
-		ProductHistory: pallet_product_history::{Module, Storage},
+		Products: pallet_products::{Module, Call, Storage, Event<T>, Config},
+		ProductHistory: pallet_product_history::{Module, Storage, Config},
 		ProfileFollows: pallet_profile_follows::{Module, Call, Storage, Event<T>},
 		Profiles: pallet_profiles::{Module, Call, Storage, Event<T>},
 		ProfileHistory: pallet_profile_history::{Module, Storage},
@@ -654,13 +803,15 @@ construct_runtime!(
 		StorefrontFollows: pallet_storefront_follows::{Module, Call, Storage, Event<T>},
 		StorefrontHistory: pallet_storefront_history::{Module, Storage},
 		StorefrontOwnership: pallet_storefront_ownership::{Module, Call, Storage, Event<T>},
+		StorefrontMultiOwnership: pallet_storefront_multi_ownership::{Module, Call, Storage, Event<T>},
 		Storefronts: pallet_storefronts::{Module, Call, Storage, Event<T>, Config<T>},
 		Utils: pallet_utils::{Module, Storage, Event<T>, Config<T>},
-		Orders: pallet_orders::{Module, Call, Storage, Event<T>},
-		OrderHistory: pallet_order_history::{Module, Storage},
+		Orders: pallet_orders::{Module, Call, Storage, Event<T>, Config},
+		OrderHistory: pallet_order_history::{Module, Storage, Config},
+		Reviews: pallet_reviews::{Module, Call, Storage, Event<T>},
 		OCWModule: pallet_ocw::{Module, Call, Storage, Event<T>},
 		Faucets: pallet_faucets::{Module, Call, Storage, Event<T>},
-		// SessionKeys: session_keys::{Module, Call, Storage, Config<T>, Event<T>},
+		SessionKeys: session_keys::{Module, Call, Storage, Event<T>},
 	}
 );
 
@@ -772,6 +923,68 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl pallet_storefronts_rpc_runtime_api::StorefrontsApi<Block, AccountId> for Runtime {
+		fn get_storefront_ids_by_owner(owner: AccountId, offset: u64, limit: u64) -> Vec<StorefrontId> {
+			Storefronts::get_storefront_ids_by_owner(owner, offset, limit)
+		}
+
+		fn has_account_storefront_permission(
+			account: AccountId,
+			storefront_id: StorefrontId,
+			permission: SP,
+		) -> bool {
+			Storefronts::has_account_storefront_permission(account, storefront_id, permission)
+		}
+	}
+
+	impl pallet_products_rpc_runtime_api::ProductsApi<Block> for Runtime {
+		fn get_product_ids_by_storefront(
+			storefront_id: StorefrontId,
+			include_hidden: bool,
+			offset: u64,
+			limit: u64,
+		) -> Vec<ProductId> {
+			Products::get_product_ids_by_storefront(storefront_id, include_hidden, offset, limit)
+		}
+
+		fn get_product_ids_by_category(
+			category_id: CategoryId,
+			include_hidden: bool,
+			offset: u64,
+			limit: u64,
+		) -> Vec<ProductId> {
+			Products::get_product_ids_by_category(category_id, include_hidden, offset, limit)
+		}
+
+		fn get_reply_ids_tree(
+			product_id: ProductId,
+			offset: u64,
+			limit: u64,
+		) -> Vec<(ProductId, Vec<ProductId>)> {
+			Products::get_reply_ids_tree(product_id, offset, limit)
+		}
+	}
+
+	impl pallet_orders_rpc_runtime_api::OrdersApi<Block, AccountId> for Runtime {
+		fn get_order_ids_by_buyer(
+			buyer: AccountId,
+			state: Option<OrderState>,
+			offset: u64,
+			limit: u64,
+		) -> Vec<OrderId> {
+			Orders::get_order_ids_by_buyer(buyer, state, offset, limit)
+		}
+
+		fn get_order_ids_by_seller(
+			seller: AccountId,
+			state: Option<OrderState>,
+			offset: u64,
+			limit: u64,
+		) -> Vec<OrderId> {
+			Orders::get_order_ids_by_seller(seller, state, offset, limit)
+		}
+	}
+
 	impl sp_session::SessionKeys<Block> for Runtime {
 		fn generate_session_keys(seed: Option<Vec<u8>>) -> Vec<u8> {
 			opaque::SessionKeys::generate(seed)